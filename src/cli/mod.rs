@@ -1,5 +1,16 @@
-use std::path::PathBuf;
+use std::{
+    io::Write,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use anyhow::Context;
+use awesomelify::{
+    server::RefreshOutcome,
+    storage::{fs::FsStore, ImportItems, Item, Storage, StorageKind, EXPORT_FORMAT_VERSION},
+};
+use futures::{stream::BoxStream, StreamExt};
 use tracing_subscriber::EnvFilter;
 
 #[derive(clap::Parser)]
@@ -12,13 +23,25 @@ impl Args {
     pub fn run(self) {
         match self.cmd {
             Cmd::Serve(cmd) => cmd.run().unwrap(),
+            Cmd::Export(cmd) => cmd.run().unwrap(),
+            Cmd::Import(cmd) => cmd.run().unwrap(),
+            Cmd::Refresh(cmd) => cmd.run().unwrap(),
+            Cmd::Validate(cmd) => {
+                if !cmd.run().unwrap() {
+                    std::process::exit(1);
+                }
+            }
         }
     }
 }
 
 #[derive(clap::Subcommand)]
 pub enum Cmd {
-    Serve(CmdServe),
+    Serve(Box<CmdServe>),
+    Export(CmdExport),
+    Import(CmdImport),
+    Refresh(CmdRefresh),
+    Validate(CmdValidate),
 }
 
 #[derive(clap::Parser)]
@@ -26,23 +49,550 @@ pub struct CmdServe {
     #[clap(long, env = "DATA_DIR", default_value = "data")]
     data_dir: PathBuf,
 
+    /// Storage backend.
+    ///
+    /// `memory` does not touch disk, and loses all data on restart.
+    #[clap(long, env = "STORAGE", value_enum, default_value_t)]
+    storage: StorageKind,
+
     /// Github token to use for Github API requests.
-    #[clap(long, env = "GITHUB_TOKEN")]
-    github_token: Option<String>,
+    ///
+    /// Can be repeated, or a comma-separated list, to rotate between multiple
+    /// tokens.
+    #[clap(long = "github-token", env = "GITHUB_TOKEN", value_delimiter = ',')]
+    github_tokens: Vec<String>,
+
+    /// File containing the Github token (eg: a Docker secret).
+    ///
+    /// Avoids exposing the token in the process environment.
+    #[clap(long, env = "GITHUB_TOKEN_FILE")]
+    github_token_file: Option<PathBuf>,
+
+    /// Additional Github Enterprise hosts to support (eg: github.mycorp.com).
+    #[clap(long = "github-host", env = "GITHUB_HOSTS", value_delimiter = ',')]
+    github_hosts: Vec<String>,
+
+    /// Only keep the first link to each repo when parsing READMEs.
+    #[clap(long, env = "DEDUP_LINKS")]
+    dedup_links: bool,
+
+    /// Maximum Github API requests per second. 0 disables the limit.
+    #[clap(long, env = "GITHUB_RPS", default_value_t = 10.0, value_parser = parse_rate)]
+    github_rps: f64,
+
+    /// Attempts per Github request for transient errors (timeouts, 502-504).
+    #[clap(long, env = "GITHUB_MAX_ATTEMPTS", default_value_t = 3)]
+    github_max_attempts: u32,
+
+    /// Port to listen on.
+    #[clap(long, env = "PORT", default_value_t = awesomelify::server::DEFAULT_PORT)]
+    port: u16,
+
+    /// Address to bind to. Use `127.0.0.1` to only accept local connections.
+    #[clap(long, env = "BIND_ADDR", default_value = "0.0.0.0")]
+    bind: IpAddr,
+
+    /// Token required for mutating API routes (import, delete).
+    ///
+    /// Clients must send `Authorization: Bearer <token>`. If not set, these
+    /// routes are open to everyone.
+    #[clap(long, env = "API_TOKEN", hide_env_values = true)]
+    api_token: Option<String>,
+
+    /// Log output format.
+    #[clap(long, env = "LOG_FORMAT", value_enum, default_value_t)]
+    log_format: LogFormat,
+
+    /// How long loaded lists are cached in memory (eg: 60s, 10m, 1h).
+    #[clap(long, env = "MEMORY_TTL", default_value = "60s", value_parser = parse_duration)]
+    memory_ttl: Duration,
+
+    /// Maximum number of lists kept in memory.
+    #[clap(long, env = "MEMORY_CAPACITY", default_value_t = 1000)]
+    memory_capacity: usize,
+
+    /// Age after which READMEs are refreshed from the source (eg: 12h, 5d).
+    #[clap(long, env = "README_REFRESH", default_value = "5d", value_parser = parse_duration)]
+    readme_refresh: Duration,
+
+    /// Age after which repos that were not found are queried again (eg: 7d).
+    #[clap(long, env = "NOT_FOUND_TTL", default_value = "7d", value_parser = parse_duration)]
+    not_found_ttl: Duration,
+
+    /// Minimum time between manual refreshes of the same list (eg: 5m).
+    #[clap(long, env = "REFRESH_COOLDOWN", default_value = "5m", value_parser = parse_duration)]
+    refresh_cooldown: Duration,
+
+    /// How long proxies may cache repo pages (eg: 5m). Zero disables caching.
+    #[clap(long, env = "PAGE_MAX_AGE", default_value = "5m", value_parser = parse_duration)]
+    page_max_age: Duration,
+
+    /// How long the popular lists shown on the homepage are cached (eg: 60s).
+    #[clap(long, env = "POPULAR_TTL", default_value = "60s", value_parser = parse_duration)]
+    popular_ttl: Duration,
+
+    /// Load all stored lists on startup, queueing missing repo details and
+    /// outdated READMEs for a background refresh.
+    #[clap(long, env = "WARM_ON_START")]
+    warm_on_start: bool,
+
+    /// Public URL of the site, used for absolute links in page metadata and
+    /// feed exports. Defaults to the awesomelify instance.
+    #[clap(long, env = "PUBLIC_URL")]
+    public_url: Option<String>,
+
+    /// Only serve stored data, like imported lists.
+    ///
+    /// Disables all source requests and background tasks. Lists that are not
+    /// stored can not be added.
+    #[clap(long, env = "READONLY")]
+    readonly: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug)]
+pub enum LogFormat {
+    /// Human readable.
+    #[default]
+    Text,
+    /// One JSON object per line, for log aggregation.
+    Json,
 }
 
 impl CmdServe {
     #[tokio::main]
     pub async fn run(self) -> Result<(), anyhow::Error> {
         let filter = EnvFilter::try_from_default_env().unwrap_or("info".parse().unwrap());
-        tracing_subscriber::fmt().with_env_filter(filter).init();
+        let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+        match self.log_format {
+            LogFormat::Text => subscriber.init(),
+            LogFormat::Json => subscriber.json().init(),
+        }
+
+        let github_tokens =
+            resolve_github_tokens(self.github_tokens, self.github_token_file.as_deref())?;
 
         awesomelify::server::CtxBuilder::new(self.data_dir)
-            .github_token(self.github_token)
+            .storage(self.storage)
+            .github_tokens(github_tokens)
+            .github_hosts(self.github_hosts)
+            .dedup_links(self.dedup_links)
+            .api_token(self.api_token)
+            .github_requests_per_second(self.github_rps)
+            .github_max_attempts(self.github_max_attempts)
+            .memory_ttl(self.memory_ttl)
+            .memory_capacity(self.memory_capacity)
+            .readme_refresh(self.readme_refresh)
+            .not_found_ttl(self.not_found_ttl)
+            .popular_ttl(self.popular_ttl)
+            .refresh_cooldown(self.refresh_cooldown)
+            .warm_on_start(self.warm_on_start)
+            .readonly(self.readonly)
+            .page_max_age(self.page_max_age)
+            .public_url(self.public_url)
             .build()?
-            .run_server(awesomelify::server::DEFAULT_PORT)
+            .run_server(SocketAddr::new(self.bind, self.port))
             .await?;
 
         Ok(())
     }
 }
+
+/// Export all stored data as JSON.
+#[derive(clap::Parser)]
+pub struct CmdExport {
+    #[clap(long, env = "DATA_DIR", default_value = "data")]
+    data_dir: PathBuf,
+
+    /// File to write to. Writes to stdout if not specified.
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+
+    /// Pretty-print the JSON output.
+    #[clap(long)]
+    pretty: bool,
+}
+
+impl CmdExport {
+    #[tokio::main]
+    pub async fn run(self) -> Result<(), anyhow::Error> {
+        init_stderr_tracing();
+
+        let store = FsStore::new(self.data_dir)?;
+
+        let mut out: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(std::io::BufWriter::new(
+                std::fs::File::create(path)
+                    .with_context(|| format!("could not create file '{}'", path.display()))?,
+            )),
+            None => Box::new(std::io::stdout().lock()),
+        };
+
+        let count = write_export(&mut out, store.export_stream(), self.pretty).await?;
+        writeln!(out)?;
+        out.flush()?;
+
+        tracing::info!("exported {} items", count);
+
+        Ok(())
+    }
+}
+
+/// Write items as a JSON [`ExportEnvelope`](awesomelify::storage::ExportEnvelope),
+/// one item at a time.
+///
+/// Returns the number of written items.
+async fn write_export(
+    out: &mut dyn Write,
+    mut items: BoxStream<'_, Result<Item, anyhow::Error>>,
+    pretty: bool,
+) -> Result<usize, anyhow::Error> {
+    if pretty {
+        write!(
+            out,
+            "{{\n  \"version\": {EXPORT_FORMAT_VERSION},\n  \"items\": ["
+        )?;
+    } else {
+        write!(out, r#"{{"version":{EXPORT_FORMAT_VERSION},"items":["#)?;
+    }
+
+    let mut count = 0;
+    while let Some(item) = items.next().await {
+        let item = item?;
+        if count > 0 {
+            write!(out, ",")?;
+        }
+        if pretty {
+            // JSON strings can not contain raw newlines, so this only indents.
+            let json = serde_json::to_string_pretty(&item)?;
+            write!(out, "\n    {}", json.replace('\n', "\n    "))?;
+        } else {
+            serde_json::to_writer(&mut *out, &item)?;
+        }
+        count += 1;
+    }
+
+    if pretty {
+        if count > 0 {
+            write!(out, "\n  ")?;
+        }
+        write!(out, "]\n}}")?;
+    } else {
+        write!(out, "]}}")?;
+    }
+    Ok(count)
+}
+
+/// Import data previously written by `export`.
+///
+/// Existing items are only overwritten by newer versions.
+#[derive(clap::Parser)]
+pub struct CmdImport {
+    #[clap(long, env = "DATA_DIR", default_value = "data")]
+    data_dir: PathBuf,
+
+    /// File to read from. Reads from stdin if not specified.
+    input: Option<PathBuf>,
+}
+
+impl CmdImport {
+    #[tokio::main]
+    pub async fn run(self) -> Result<(), anyhow::Error> {
+        init_stderr_tracing();
+
+        let ImportItems(items) = match &self.input {
+            Some(path) => {
+                let file = std::fs::File::open(path)
+                    .with_context(|| format!("could not open file '{}'", path.display()))?;
+                serde_json::from_reader(std::io::BufReader::new(file))
+            }
+            None => serde_json::from_reader(std::io::stdin().lock()),
+        }
+        .context("could not parse import data")?;
+
+        let store = FsStore::new(self.data_dir)?;
+        let stats = store.import(items).await?;
+
+        println!(
+            "Import complete: {} inserted, {} skipped",
+            stats.inserted, stats.skipped
+        );
+
+        Ok(())
+    }
+}
+
+/// Re-fetch all stored lists and their linked repos from the sources.
+///
+/// Stops early if the source rate limit is exceeded.
+#[derive(clap::Parser)]
+pub struct CmdRefresh {
+    #[clap(long, env = "DATA_DIR", default_value = "data")]
+    data_dir: PathBuf,
+
+    /// Github token to use for Github API requests.
+    ///
+    /// Can be repeated, or a comma-separated list, to rotate between multiple
+    /// tokens.
+    #[clap(long = "github-token", env = "GITHUB_TOKEN", value_delimiter = ',')]
+    github_tokens: Vec<String>,
+
+    /// Additional Github Enterprise hosts to support (eg: github.mycorp.com).
+    #[clap(long = "github-host", env = "GITHUB_HOSTS", value_delimiter = ',')]
+    github_hosts: Vec<String>,
+
+    /// Number of lists to refresh concurrently.
+    #[clap(long, default_value_t = 2)]
+    concurrency: usize,
+}
+
+impl CmdRefresh {
+    #[tokio::main]
+    pub async fn run(self) -> Result<(), anyhow::Error> {
+        init_stderr_tracing();
+
+        let ctx = awesomelify::server::CtxBuilder::new(self.data_dir)
+            .github_tokens(self.github_tokens)
+            .github_hosts(self.github_hosts)
+            .background_tasks(false)
+            .build()?;
+
+        let stats = ctx
+            .refresh_all(self.concurrency, |ident, outcome| match outcome {
+                RefreshOutcome::Refreshed { links } => {
+                    println!("{ident}: refreshed ({links} links)");
+                }
+                RefreshOutcome::Failed(err) => println!("{ident}: failed: {err:#}"),
+                RefreshOutcome::Skipped => println!("{ident}: skipped (rate limited)"),
+            })
+            .await?;
+
+        println!(
+            "Refresh complete: {} refreshed, {} failed, {} skipped",
+            stats.refreshed, stats.failed, stats.skipped
+        );
+
+        Ok(())
+    }
+}
+
+/// Check whether the README of a repo can be parsed as an awesome list.
+///
+/// Exits with a non-zero status if no repo links are found.
+#[derive(clap::Parser)]
+pub struct CmdValidate {
+    /// Repository URL or `<org>/<repo>`.
+    repo: String,
+
+    /// Github token to use for Github API requests.
+    #[clap(long = "github-token", env = "GITHUB_TOKEN", value_delimiter = ',')]
+    github_tokens: Vec<String>,
+
+    /// Additional Github Enterprise hosts to support (eg: github.mycorp.com).
+    #[clap(long = "github-host", env = "GITHUB_HOSTS", value_delimiter = ',')]
+    github_hosts: Vec<String>,
+}
+
+impl CmdValidate {
+    /// Returns `false` if the repo is not an awesome list.
+    #[tokio::main]
+    pub async fn run(self) -> Result<bool, anyhow::Error> {
+        init_stderr_tracing();
+
+        let report = awesomelify::validate::validate_readme(
+            &self.repo,
+            self.github_tokens,
+            self.github_hosts,
+        )
+        .await?;
+
+        println!("{}", report.ident);
+        println!("  links:        {}", report.total_links);
+        println!("  repo links:   {}", report.repo_links);
+        println!("  unique repos: {}", report.unique_repos);
+        println!("  categories:   {}", report.categories.len());
+        for category in &report.categories {
+            println!("    {category}");
+        }
+
+        if !report.is_awesome_list() {
+            eprintln!("No repo links found - not an awesome list");
+        }
+        Ok(report.is_awesome_list())
+    }
+}
+
+/// Combine the tokens from `--github-token` with `--github-token-file`.
+///
+/// If both are given they must specify the same single token.
+fn resolve_github_tokens(
+    tokens: Vec<String>,
+    file: Option<&Path>,
+) -> Result<Vec<String>, anyhow::Error> {
+    let Some(file) = file else {
+        return Ok(tokens);
+    };
+
+    let token = std::fs::read_to_string(file)
+        .with_context(|| format!("could not read Github token file '{}'", file.display()))?
+        .trim()
+        .to_string();
+    if token.is_empty() {
+        anyhow::bail!("Github token file '{}' is empty", file.display());
+    }
+    if !tokens.is_empty() && tokens != [token.as_str()] {
+        anyhow::bail!(
+            "the Github token in '{}' does not match --github-token/GITHUB_TOKEN - only set one of them",
+            file.display()
+        );
+    }
+
+    Ok(vec![token])
+}
+
+/// Parse a duration like `90`, `90s`, `15m`, `2h` or `5d`.
+///
+/// Plain numbers are seconds.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = value
+        .find(|c: char| !c.is_ascii_digit())
+        .map(|index| value.split_at(index))
+        .unwrap_or((value, "s"));
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{value}'"))?;
+    let factor = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => {
+            return Err(format!(
+                "invalid duration unit '{unit}' - expected s, m, h or d"
+            ))
+        }
+    };
+    let seconds = number
+        .checked_mul(factor)
+        .ok_or_else(|| format!("duration '{value}' is too large"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Lowest accepted request rate, one request every ~17 minutes.
+const MIN_RATE: f64 = 0.001;
+
+/// Parse a requests per second limit. `0` disables the limit.
+fn parse_rate(value: &str) -> Result<f64, String> {
+    let rate: f64 = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid rate '{value}'"))?;
+    if rate == 0.0 || (rate.is_finite() && rate >= MIN_RATE) {
+        Ok(rate)
+    } else {
+        Err(format!(
+            "invalid rate '{value}' - expected 0 or at least {MIN_RATE}"
+        ))
+    }
+}
+
+/// Log to stderr, to keep stdout free for command output.
+fn init_stderr_tracing() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or("info".parse().unwrap());
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("0"), Ok(Duration::ZERO));
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(
+            parse_duration("5d"),
+            Ok(Duration::from_secs(5 * 24 * 60 * 60))
+        );
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("18446744073709551615d").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_export() {
+        let item = |repo: &str| -> Item {
+            serde_json::from_value(serde_json::json!({
+                "Repo": { "NotFound": {
+                    "ident": { "source": "Github", "owner": "org", "repo": repo },
+                    "updated_at": time::OffsetDateTime::UNIX_EPOCH,
+                }}
+            }))
+            .unwrap()
+        };
+        let items = vec![item("a"), item("b")];
+
+        for pretty in [false, true] {
+            for items in [Vec::new(), items.clone()] {
+                let stream = futures::stream::iter(items.clone().into_iter().map(Ok)).boxed();
+                let mut out = Vec::new();
+                let count = write_export(&mut out, stream, pretty).await.unwrap();
+                assert_eq!(count, items.len());
+
+                let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+                assert_eq!(value["version"], EXPORT_FORMAT_VERSION);
+                let ImportItems(parsed) = serde_json::from_slice(&out).unwrap();
+                assert_eq!(parsed, items);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("0"), Ok(0.0));
+        assert_eq!(parse_rate("2.5"), Ok(2.5));
+        assert!(parse_rate("-1").is_err());
+        assert!(parse_rate("1e-300").is_err());
+        assert!(parse_rate("inf").is_err());
+        assert!(parse_rate("NaN").is_err());
+        assert!(parse_rate("fast").is_err());
+    }
+
+    #[test]
+    fn test_resolve_github_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("token");
+        std::fs::write(&file, "  secret\n").unwrap();
+        let tokens = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            resolve_github_tokens(tokens(&["a", "b"]), None).unwrap(),
+            tokens(&["a", "b"])
+        );
+        assert_eq!(
+            resolve_github_tokens(Vec::new(), Some(&file)).unwrap(),
+            tokens(&["secret"])
+        );
+        assert_eq!(
+            resolve_github_tokens(tokens(&["secret"]), Some(&file)).unwrap(),
+            tokens(&["secret"])
+        );
+
+        let err = resolve_github_tokens(tokens(&["other"]), Some(&file)).unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{err}");
+
+        let missing = dir.path().join("missing");
+        let err = resolve_github_tokens(Vec::new(), Some(&missing)).unwrap_err();
+        assert!(
+            err.to_string().contains(&missing.display().to_string()),
+            "{err}"
+        );
+    }
+}
@@ -3,3 +3,4 @@ mod markdown;
 pub mod server;
 mod source;
 pub mod storage;
+pub mod validate;
@@ -1,57 +1,118 @@
 use std::{
-    collections::{HashMap, VecDeque},
-    sync::Arc,
-    time::{Duration, SystemTime},
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt, StreamExt,
+};
 use time::OffsetDateTime;
 use tokio::sync::RwLock;
 
 use crate::{
     source::{
-        loader::SourceLoader, FullReadmeRepo, RateLimitError, ReadmeRepo, RepoDetailsItem,
-        RepoIdent,
+        loader::SourceLoader, FullReadmeRepo, ReadmeLocation, ReadmeRepo, RepoDetails,
+        RepoDetailsItem, RepoIdent, SourceError,
     },
-    storage::{Storage, Store},
+    storage::{Storage, Store, Task},
 };
 
-#[derive(PartialEq, Eq, Clone, Debug)]
-enum Task {
-    LoadRepoDetails(RepoIdent),
-    LoadReadmeRepo(RepoIdent),
-}
+/// Number of popped tasks after which the queue is persisted again.
+///
+/// Popped tasks that were not persisted yet just run again after a restart.
+const PERSIST_POP_INTERVAL: usize = 16;
 
 #[derive(Clone, Debug)]
 struct TaskQueue {
-    tasks: Arc<tokio::sync::Mutex<VecDeque<Task>>>,
+    state: Arc<tokio::sync::Mutex<QueueState>>,
+    store: Store,
+}
+
+#[derive(Default, Debug)]
+struct QueueState {
+    tasks: VecDeque<Task>,
+    /// The tasks in `tasks`, for fast duplicate checks.
+    queued: HashSet<Task>,
+    /// Number of tasks popped since the queue was last persisted.
+    unpersisted_pops: usize,
+}
+
+impl QueueState {
+    /// Queue a task, unless it is already queued.
+    ///
+    /// Returns `true` if the task was added.
+    fn push(&mut self, task: Task) -> bool {
+        if !self.queued.insert(task.clone()) {
+            return false;
+        }
+        self.tasks.push_back(task);
+        true
+    }
 }
 
 impl TaskQueue {
-    fn new() -> Self {
+    fn new(store: Store) -> Self {
         Self {
-            tasks: Arc::new(tokio::sync::Mutex::new(VecDeque::new())),
+            state: Arc::new(tokio::sync::Mutex::new(QueueState::default())),
+            store,
         }
     }
 
-    async fn push(&self, task: Task) {
-        let mut lock = self.tasks.lock().await;
-        if !lock.contains(&task) {
-            lock.push_back(task);
+    /// Load persisted tasks from storage.
+    async fn restore(&self) -> Result<(), anyhow::Error> {
+        let stored = self.store.tasks().await?;
+
+        let mut lock = self.state.lock().await;
+        for task in stored {
+            lock.push(task);
+        }
+        tracing::debug!("restored {} tasks", lock.tasks.len());
+
+        Ok(())
+    }
+
+    /// Write the current queue to storage.
+    async fn persist(&self, state: &mut QueueState) {
+        state.unpersisted_pops = 0;
+        let tasks = state.tasks.iter().cloned().collect();
+        if let Err(err) = self.store.tasks_replace(tasks).await {
+            tracing::warn!("failed to persist task queue: {}", err);
         }
     }
 
+    async fn push(&self, task: Task) {
+        self.push_many(vec![task]).await;
+    }
+
     async fn push_many(&self, tasks: Vec<Task>) {
-        let mut lock = self.tasks.lock().await;
+        let mut lock = self.state.lock().await;
+        let mut changed = false;
         for task in tasks {
-            if !lock.contains(&task) {
-                lock.push_back(task);
-            }
+            changed |= lock.push(task);
+        }
+        if changed {
+            self.persist(&mut lock).await;
         }
     }
 
+    /// Take the next task.
+    ///
+    /// The queue is only persisted every [`PERSIST_POP_INTERVAL`] pops, or
+    /// once it is empty.
     async fn pop(&self) -> Option<Task> {
-        let mut lock = self.tasks.lock().await;
-        lock.pop_front()
+        let mut lock = self.state.lock().await;
+        let task = lock.tasks.pop_front()?;
+        lock.queued.remove(&task);
+        lock.unpersisted_pops += 1;
+        if lock.unpersisted_pops >= PERSIST_POP_INTERVAL || lock.tasks.is_empty() {
+            self.persist(&mut lock).await;
+        }
+        Some(task)
     }
 
     async fn run_task_loop(queue: Self, loader: Loader) -> Result<(), anyhow::Error> {
@@ -85,6 +146,46 @@ impl TaskQueue {
     }
 }
 
+/// Refresh intervals of the [`Loader`].
+#[derive(Clone, Copy, Debug)]
+pub struct LoaderConfig {
+    /// How long loaded repos are kept in memory before reloading them from
+    /// storage.
+    pub memory_ttl: Duration,
+    /// Maximum number of lists kept in memory.
+    pub memory_capacity: usize,
+    /// Age after which a stored README is refreshed from the source.
+    pub readme_refresh: Duration,
+    /// Load all stored lists on startup. See [`Loader::start`].
+    pub warm_on_start: bool,
+    /// How long [`Loader::popular_repos`] results are cached.
+    pub popular_ttl: Duration,
+    /// Minimum time between manual refreshes of the same repo.
+    /// See [`Loader::start_manual_refresh`].
+    pub refresh_cooldown: Duration,
+    /// Age after which repos stored as not found are queried again.
+    pub not_found_ttl: Duration,
+    /// Only serve stored data. See [`ReadonlyError`].
+    pub readonly: bool,
+}
+
+impl Default for LoaderConfig {
+    fn default() -> Self {
+        Self {
+            memory_ttl: Duration::from_secs(60),
+            memory_capacity: 1000,
+            // 5 days
+            readme_refresh: Duration::from_secs(60 * 60 * 24 * 5),
+            warm_on_start: false,
+            popular_ttl: Duration::from_secs(60),
+            refresh_cooldown: Duration::from_secs(5 * 60),
+            // 7 days
+            not_found_ttl: Duration::from_secs(60 * 60 * 24 * 7),
+            readonly: false,
+        }
+    }
+}
+
 /// Serves as a bridge between the storage and the sources, and also caches
 /// data in memory.
 #[derive(Clone)]
@@ -92,31 +193,80 @@ pub struct Loader {
     store: Store,
     source: SourceLoader,
     cache: Cache,
+    in_flight: InFlight,
 
     tasks: TaskQueue,
 
     memory_update_time: Duration,
     readme_storage_refresh_time: Duration,
+    warm_on_start: bool,
+    popular_ttl: Duration,
+    refresh_cooldown: Duration,
+    not_found_ttl: Duration,
+    readonly: bool,
+    /// Time of the last manual refresh per repo.
+    manual_refreshes: Arc<std::sync::Mutex<HashMap<RepoIdent, Instant>>>,
+    /// Limits manual refreshes running at once across all repos.
+    manual_refresh_slots: Arc<tokio::sync::Semaphore>,
 }
 
+/// Maximum number of manual refreshes running at once.
+const MAX_MANUAL_REFRESHES: usize = 4;
+
 impl Loader {
-    pub fn new(store: Store, source: SourceLoader) -> Self {
+    pub fn new(store: Store, source: SourceLoader, config: LoaderConfig) -> Self {
         Self {
+            tasks: TaskQueue::new(store.clone()),
             store,
             source,
-            cache: Cache::new(),
-            tasks: TaskQueue::new(),
-            memory_update_time: Duration::from_secs(60),
-            // 5 days
-            readme_storage_refresh_time: Duration::from_secs(60 * 60 * 24 * 5),
+            cache: Cache::new(config.memory_capacity),
+            in_flight: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            memory_update_time: config.memory_ttl,
+            readme_storage_refresh_time: config.readme_refresh,
+            warm_on_start: config.warm_on_start,
+            popular_ttl: config.popular_ttl,
+            refresh_cooldown: config.refresh_cooldown,
+            not_found_ttl: config.not_found_ttl,
+            readonly: config.readonly,
+            manual_refreshes: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            manual_refresh_slots: Arc::new(tokio::sync::Semaphore::new(MAX_MANUAL_REFRESHES)),
         }
     }
 
-    pub fn start(store: Store, source: SourceLoader) -> Loader {
-        let s = Self::new(store, source);
+    /// Create a loader and start processing background tasks.
+    ///
+    /// With [`LoaderConfig::warm_on_start`], all stored lists are loaded
+    /// first. See [`Loader::warm`].
+    ///
+    /// In [`LoaderConfig::readonly`] mode, no background tasks are run.
+    pub fn start(store: Store, source: SourceLoader, config: LoaderConfig) -> Loader {
+        let s = Self::new(store, source, config);
+        if s.readonly {
+            if s.warm_on_start {
+                let s = s.clone();
+                tokio::spawn(async move {
+                    match s.warm().await {
+                        Ok(count) => tracing::info!("warmed {} lists", count),
+                        Err(err) => tracing::error!("failed to warm lists: {}", err),
+                    }
+                });
+            }
+            return s;
+        }
+
         tokio::spawn({
             let s = s.clone();
             async move {
+                if let Err(err) = s.tasks.restore().await {
+                    tracing::error!("failed to restore task queue: {}", err);
+                }
+                if s.warm_on_start {
+                    match s.warm().await {
+                        Ok(count) => tracing::info!("warmed {} lists", count),
+                        Err(err) => tracing::error!("failed to warm lists: {}", err),
+                    }
+                }
+
                 match TaskQueue::run_task_loop(s.tasks.clone(), s.clone()).await {
                     Ok(_) => {
                         tracing::debug!("task loop finished gracefully");
@@ -130,20 +280,109 @@ impl Loader {
         s
     }
 
+    /// Load all stored lists into the memory cache.
+    ///
+    /// Only reads from storage. Missing repo details and outdated READMEs
+    /// are queued as tasks, so source requests are paced by the task queue.
+    async fn warm(&self) -> Result<usize, anyhow::Error> {
+        let lists = self.store.readme_repo_list().await?;
+        let count = lists.len();
+        for list in lists {
+            let ident = list.details.ident;
+            if let Err(err) = self.load_full_readme_repo(ident.clone(), false).await {
+                tracing::warn!(%ident, "failed to warm list: {:#}", err);
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns `true` if the loader only serves stored data.
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    /// Fail with a [`ReadonlyError`] in readonly mode.
+    ///
+    /// Must be checked before every source request.
+    fn ensure_writable(&self, ident: &RepoIdent) -> Result<(), ReadonlyError> {
+        if self.readonly {
+            Err(ReadonlyError {
+                ident: ident.clone(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Queue background tasks, unless in readonly mode.
+    async fn queue_tasks(&self, tasks: Vec<Task>) {
+        if !self.readonly {
+            self.tasks.push_many(tasks).await;
+        }
+    }
+
     async fn source_load_repo_details(
         &self,
         ident: &RepoIdent,
     ) -> Result<RepoDetailsItem, anyhow::Error> {
+        self.ensure_writable(ident)?;
         let details = self.source.load_repo_details(ident).await?;
         self.store.repo_details_upsert(details.clone()).await?;
+        if let RepoDetailsItem::Found(details) = &details {
+            self.record_star_snapshot(details).await;
+
+            // The requested ident stays an alias, so links to the old
+            // location do not trigger a fetch every time.
+            if let Some(canonical) = &details.renamed_to {
+                tracing::debug!(%ident, %canonical, "repo was renamed");
+                let canonical = RepoDetails {
+                    ident: canonical.clone(),
+                    renamed_to: None,
+                    ..details.clone()
+                };
+                self.store
+                    .repo_details_upsert(RepoDetailsItem::Found(canonical))
+                    .await?;
+            }
+        }
         Ok(details)
     }
 
+    async fn record_star_snapshot(&self, details: &RepoDetails) {
+        let res = self
+            .store
+            .record_star_snapshot(
+                details.ident.clone(),
+                details.stargazer_count,
+                details.updated_at,
+            )
+            .await;
+        if let Err(err) = res {
+            tracing::warn!(ident=%details.ident, "failed to record star snapshot: {:#}", err);
+        }
+    }
+
     async fn load_repo_details(&self, ident: &RepoIdent) -> Result<RepoDetailsItem, anyhow::Error> {
-        if let Some(d) = self.store.repo_details(ident.clone()).await? {
-            Ok(d)
-        } else {
-            self.source_load_repo_details(ident).await
+        match self.store.repo_details(ident.clone()).await? {
+            Some(d) if !self.is_expired_not_found(&d) => Ok(d),
+            Some(_) => {
+                tracing::debug!(%ident, "not found repo expired, querying source again");
+                self.source_load_repo_details(ident).await
+            }
+            None => self.source_load_repo_details(ident).await,
+        }
+    }
+
+    /// Returns `true` for not found details older than
+    /// [`LoaderConfig::not_found_ttl`].
+    ///
+    /// The repo may have been temporarily unavailable, or created since.
+    fn is_expired_not_found(&self, details: &RepoDetailsItem) -> bool {
+        match details {
+            RepoDetailsItem::NotFound { updated_at, .. } => {
+                (OffsetDateTime::now_utc() - *updated_at) > self.not_found_ttl
+            }
+            RepoDetailsItem::Found(_) => false,
         }
     }
 
@@ -151,8 +390,34 @@ impl Loader {
         &self,
         ident: &RepoIdent,
     ) -> Result<ReadmeRepo, anyhow::Error> {
-        let repo = self.source.load_readme_repo(ident).await?;
+        self.source_load_readme_repo_from(ident, None, false).await
+    }
+
+    /// Load a readme repo from the source.
+    ///
+    /// Uses the given README location, or else the location of the stored
+    /// version.
+    ///
+    /// Unless `force` is set, an unchanged README is not parsed again.
+    async fn source_load_readme_repo_from(
+        &self,
+        ident: &RepoIdent,
+        location: Option<ReadmeLocation>,
+        force: bool,
+    ) -> Result<ReadmeRepo, anyhow::Error> {
+        self.ensure_writable(ident)?;
+        let stored = self.store.readme_repo(ident.clone()).await?;
+        let location = location
+            .or_else(|| stored.as_ref().map(|x| x.readme_location.clone()))
+            .unwrap_or_default();
+        let previous = stored.filter(|_| !force);
+        let repo = self
+            .source
+            .load_readme_repo(ident, location, previous)
+            .await?;
         self.store.readme_repo_upsert(repo.clone()).await?;
+        self.invalidate_popular_repos().await;
+        self.record_star_snapshot(&repo.details).await;
         Ok(repo)
     }
 
@@ -164,78 +429,40 @@ impl Loader {
         }
     }
 
+    /// Load a readme repo and the details of its links.
+    ///
+    /// With `allow_source_refresh`, details missing from storage are loaded
+    /// from the source right away, otherwise they are queued as tasks.
+    ///
+    /// In readonly mode, only stored data is used, and lists that are not
+    /// stored fail with a [`ReadonlyError`].
     pub async fn load_full_readme_repo(
         &self,
         ident: RepoIdent,
         allow_source_refresh: bool,
     ) -> Result<Arc<FullReadmeRepo>, anyhow::Error> {
         tracing::trace!("loading full readme repo for {}", ident);
-        let repo_opt = self
+        let allow_source_refresh = allow_source_refresh && !self.readonly;
+        let cached = self
             .cache
             .readme_repo(&ident)
             .await
             // Refresh if expired.
-            .filter(|x| x.inserted_at.elapsed().unwrap_or_default() > self.memory_update_time);
-
-        if repo_opt.is_none() {
-            let repo = self.load_readme_repo(&ident).await?;
-            let mut not_found_repos = Vec::new();
-
-            let mut links = Vec::new();
-            for link in &repo.repo_links {
-                // Ignore links to the same repo.
-                if link.ident == ident {
-                    continue;
-                }
+            .filter(|x| x.inserted_at.elapsed().unwrap_or_default() < self.memory_update_time)
+            // Retry partial results as soon as the rate limit cleared.
+            .filter(|x| !x.partial || self.source.rate_limited_until().is_some());
 
-                let details = if allow_source_refresh {
-                    self.load_repo_details(&link.ident).await
-                } else if let Some(x) = self.store.repo_details(ident.clone()).await? {
-                    Ok(x)
-                } else {
-                    continue;
-                };
-
-                match details {
-                    Ok(d) => {
-                        match d {
-                            RepoDetailsItem::Found(details) => {
-                                links.push(crate::source::FullRepoLink {
-                                    link: link.clone(),
-                                    details,
-                                });
-                            }
-                            RepoDetailsItem::NotFound { .. } => {
-                                // TODO: queue refresh?
-                                not_found_repos.push(link.ident.clone());
-                            }
-                        }
-                    }
-                    Err(e) if e.is::<RateLimitError>() => {
-                        tracing::warn!("rate limit exceeded: {}", e);
-                        break;
-                    }
-                    Err(e) => {
-                        tracing::warn!("failed to load repo details: {}", e);
-                    }
-                };
+        let repo = match cached {
+            Some(entry) => entry.data,
+            None => {
+                self.resolve_full_readme_repo_shared(ident.clone(), allow_source_refresh)
+                    .await?
             }
-
-            let full_repo = FullReadmeRepo {
-                repo,
-                links,
-                not_found: not_found_repos,
-            };
-            self.cache
-                .readme_repo_insert(ident.clone(), full_repo.clone())
-                .await;
-        }
-
-        let repo = self.cache.readme_repo(&ident).await.unwrap();
+        };
 
         // Queue tasks for missing repos.
         {
-            let missing_links = repo.data.missing_links();
+            let missing_links = repo.missing_links();
 
             tracing::trace!(?missing_links, "scheduling tasks for missing repos");
 
@@ -243,16 +470,277 @@ impl Loader {
                 .iter()
                 .map(|ident| Task::LoadRepoDetails((*ident).clone()))
                 .collect();
-            self.tasks.push_many(tasks).await;
+            self.queue_tasks(tasks).await;
         }
         // Queue task for readme refresh.
-        if (OffsetDateTime::now_utc() - repo.data.repo.updated_at)
-            > self.readme_storage_refresh_time
+        if !self.readonly
+            && (OffsetDateTime::now_utc() - repo.repo.updated_at) > self.readme_storage_refresh_time
         {
             self.tasks.push(Task::LoadReadmeRepo(ident.clone())).await;
         }
 
-        Ok(repo.data)
+        Ok(repo)
+    }
+
+    /// Load a readme repo from a specific README location.
+    ///
+    /// The location is stored with the repo, so later refreshes use the same
+    /// README. The README is re-fetched if the location changed.
+    ///
+    /// The location of an already stored repo is only changed if `replace`
+    /// is set, otherwise a [`ReadmeLocationConflictError`] is returned.
+    pub async fn load_full_readme_repo_from(
+        &self,
+        ident: RepoIdent,
+        location: ReadmeLocation,
+        replace: bool,
+    ) -> Result<Arc<FullReadmeRepo>, anyhow::Error> {
+        let stored = self.store.readme_repo(ident.clone()).await?;
+        let changed = match stored {
+            Some(stored) if stored.readme_location == location => false,
+            Some(_) if !replace => {
+                return Err(ReadmeLocationConflictError { ident }.into());
+            }
+            _ => true,
+        };
+        if changed {
+            self.source_load_readme_repo_from(&ident, Some(location), false)
+                .await?;
+            self.invalidate(&ident).await;
+        }
+
+        self.load_full_readme_repo(ident, true).await
+    }
+
+    /// Resolve a full readme repo, sharing a single load between concurrent
+    /// callers for the same repo.
+    async fn resolve_full_readme_repo_shared(
+        &self,
+        ident: RepoIdent,
+        allow_source_refresh: bool,
+    ) -> Result<Arc<FullReadmeRepo>, anyhow::Error> {
+        let load = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            in_flight
+                .entry(ident.clone())
+                .or_insert_with(|| {
+                    let s = self.clone();
+                    async move {
+                        let res = s
+                            .resolve_full_readme_repo(&ident, allow_source_refresh)
+                            .await
+                            .map_err(Arc::new);
+                        s.in_flight.lock().unwrap().remove(&ident);
+                        res
+                    }
+                    .boxed()
+                    .shared()
+                })
+                .clone()
+        };
+
+        load.await.map_err(|err| clone_shared_error(&err))
+    }
+
+    /// Resolve the links of a readme repo and insert the result into the cache.
+    async fn resolve_full_readme_repo(
+        &self,
+        ident: &RepoIdent,
+        allow_source_refresh: bool,
+    ) -> Result<Arc<FullReadmeRepo>, anyhow::Error> {
+        let repo = self.load_readme_repo(ident).await?;
+        let mut not_found_repos = Vec::new();
+        let mut retry_tasks = Vec::new();
+
+        let mut links = Vec::new();
+        let mut partial = false;
+        let mut rate_limit_reset_at = None;
+        for link in &repo.repo_links {
+            // Ignore links to the same repo.
+            if link.ident == *ident {
+                continue;
+            }
+
+            let details = if allow_source_refresh {
+                self.load_repo_details(&link.ident).await
+            } else if let Some(x) = self.store.repo_details(link.ident.clone()).await? {
+                Ok(x)
+            } else {
+                continue;
+            };
+
+            match details {
+                Ok(d) => {
+                    // Loading from the source already retried expired items.
+                    if !allow_source_refresh && self.is_expired_not_found(&d) {
+                        retry_tasks.push(Task::LoadRepoDetails(link.ident.clone()));
+                    }
+                    match d {
+                        RepoDetailsItem::Found(details) => {
+                            links.push(crate::source::FullRepoLink {
+                                link: link.clone(),
+                                details,
+                            });
+                        }
+                        RepoDetailsItem::NotFound { .. } => {
+                            not_found_repos.push(link.ident.clone());
+                        }
+                    }
+                }
+                Err(e) if is_rate_limited(&e) => {
+                    tracing::warn!("rate limit exceeded: {}", e);
+                    partial = true;
+                    rate_limit_reset_at =
+                        SourceError::find(&e).and_then(SourceError::rate_limit_reset_at);
+                    break;
+                }
+                Err(e) => {
+                    tracing::warn!("failed to load repo details: {}", e);
+                }
+            };
+        }
+
+        self.queue_tasks(retry_tasks).await;
+
+        let full_repo = FullReadmeRepo {
+            repo,
+            links,
+            not_found: not_found_repos,
+            rate_limit_reset_at,
+        };
+        Ok(self
+            .cache
+            .readme_repo_insert(ident.clone(), full_repo, partial)
+            .await)
+    }
+
+    /// Re-fetch a readme repo and the details of all its links from the source.
+    ///
+    /// The README is parsed again even if it did not change, so changed
+    /// parse options apply.
+    ///
+    /// Aborts with a [`SourceError::RateLimited`] if the source is rate limited.
+    pub async fn refresh_readme_repo(
+        &self,
+        ident: &RepoIdent,
+    ) -> Result<Arc<FullReadmeRepo>, anyhow::Error> {
+        let repo = self.source_load_readme_repo_from(ident, None, true).await?;
+
+        for link in &repo.repo_links {
+            if link.ident == *ident {
+                continue;
+            }
+            match self.source_load_repo_details(&link.ident).await {
+                Ok(_) => {}
+                Err(e) if is_rate_limited(&e) => return Err(e),
+                Err(e) => {
+                    tracing::warn!(ident=%link.ident, "failed to refresh repo details: {:#}", e);
+                }
+            }
+        }
+
+        self.resolve_full_readme_repo(ident, false).await
+    }
+
+    /// Start a user triggered refresh of a repo in the background.
+    ///
+    /// Fails if the repo was already refreshed within
+    /// [`LoaderConfig::refresh_cooldown`], or if too many manual refreshes
+    /// are running. The cooldown is released again if the refresh fails.
+    pub fn start_manual_refresh(&self, ident: &RepoIdent) -> Result<(), RefreshRejection> {
+        let permit = self
+            .manual_refresh_slots
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| RefreshRejection::Busy)?;
+        self.reserve_manual_refresh(ident)
+            .map_err(RefreshRejection::Cooldown)?;
+
+        let loader = self.clone();
+        let ident = ident.clone();
+        tokio::spawn(async move {
+            if let Err(err) = loader.refresh_readme_repo(&ident).await {
+                tracing::warn!(%ident, "manual refresh failed: {:#}", err);
+                loader.manual_refreshes.lock().unwrap().remove(&ident);
+            }
+            drop(permit);
+        });
+        Ok(())
+    }
+
+    /// Reserve a user triggered refresh of a repo.
+    ///
+    /// Returns the remaining wait time if the repo was already refreshed
+    /// within [`LoaderConfig::refresh_cooldown`].
+    fn reserve_manual_refresh(&self, ident: &RepoIdent) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut refreshes = self.manual_refreshes.lock().unwrap();
+        refreshes.retain(|_, at| now.duration_since(*at) < self.refresh_cooldown);
+        if let Some(at) = refreshes.get(ident) {
+            return Err(self.refresh_cooldown - now.duration_since(*at));
+        }
+        refreshes.insert(ident.clone(), now);
+        Ok(())
+    }
+
+    /// Refresh all stored readme repos.
+    ///
+    /// Up to `concurrency` repos are refreshed at once. Once the source is
+    /// rate limited, the remaining repos are skipped.
+    /// `on_result` is called after each repo.
+    pub async fn refresh_all(
+        &self,
+        concurrency: usize,
+        mut on_result: impl FnMut(&RepoIdent, &RefreshOutcome),
+    ) -> Result<RefreshStats, anyhow::Error> {
+        let idents = self
+            .store
+            .readme_repo_list()
+            .await?
+            .into_iter()
+            .map(|repo| repo.details.ident)
+            .collect::<Vec<_>>();
+
+        let rate_limited = std::sync::atomic::AtomicBool::new(false);
+        let rate_limited = &rate_limited;
+        let mut results = futures::stream::iter(idents)
+            .map(|ident| async move {
+                if rate_limited.load(std::sync::atomic::Ordering::Relaxed) {
+                    return (ident, RefreshOutcome::Skipped);
+                }
+                let outcome = match self.refresh_readme_repo(&ident).await {
+                    Ok(repo) => RefreshOutcome::Refreshed {
+                        links: repo.links.len(),
+                    },
+                    Err(err) => {
+                        if is_rate_limited(&err) {
+                            rate_limited.store(true, std::sync::atomic::Ordering::Relaxed);
+                        }
+                        RefreshOutcome::Failed(err)
+                    }
+                };
+                (ident, outcome)
+            })
+            .buffer_unordered(concurrency.max(1));
+
+        let mut stats = RefreshStats::default();
+        while let Some((ident, outcome)) = results.next().await {
+            match &outcome {
+                RefreshOutcome::Refreshed { .. } => stats.refreshed += 1,
+                RefreshOutcome::Failed(_) => stats.failed += 1,
+                RefreshOutcome::Skipped => stats.skipped += 1,
+            }
+            on_result(&ident, &outcome);
+        }
+
+        Ok(stats)
+    }
+
+    /// Remove a repo from the in-memory cache.
+    ///
+    /// Must be called after modifying storage outside of the loader.
+    pub async fn invalidate(&self, ident: &RepoIdent) {
+        self.cache.readme_repo_remove(ident).await;
     }
 
     #[tracing::instrument(skip_all)]
@@ -260,11 +748,20 @@ impl Loader {
         &self,
         count: usize,
     ) -> Result<Vec<Arc<FullReadmeRepo>>, anyhow::Error> {
-        tracing::trace!("loading populer repos");
-        // FIXME: add caching!
+        let cached = self
+            .cache
+            .popular_repos(count)
+            .await
+            .filter(|x| x.inserted_at.elapsed().unwrap_or_default() < self.popular_ttl);
+        if let Some(entry) = cached {
+            self.cache.popular_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(entry.data);
+        }
+        self.cache.popular_misses.fetch_add(1, Ordering::Relaxed);
 
+        tracing::trace!("loading populer repos");
         let mut repos = self.store.readme_repo_list().await?;
-        repos.sort_by_key(|r| r.details.stargazer_count);
+        repos.sort_by_key(|r| std::cmp::Reverse(r.details.stargazer_count));
         repos.truncate(count);
 
         let mut full_repos = Vec::new();
@@ -284,39 +781,860 @@ impl Loader {
 
         tracing::trace!("popular repos loaded ({})", full_repos.len());
 
+        self.cache
+            .popular_repos_insert(count, full_repos.clone())
+            .await;
         Ok(full_repos)
     }
+
+    /// Drop cached [`Self::popular_repos`] results.
+    ///
+    /// Must be called when lists are written to storage without going through
+    /// the loader.
+    pub async fn invalidate_popular_repos(&self) {
+        self.cache.popular_repos.write().await.clear();
+    }
+
+    /// Number of [`Self::popular_repos`] calls served from the cache.
+    pub fn popular_cache_hits(&self) -> u64 {
+        self.cache.popular_hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of [`Self::popular_repos`] calls that had to be computed.
+    pub fn popular_cache_misses(&self) -> u64 {
+        self.cache.popular_misses.load(Ordering::Relaxed)
+    }
+}
+
+/// Result of refreshing a single repo with [`Loader::refresh_all`].
+#[derive(Debug)]
+pub enum RefreshOutcome {
+    Refreshed {
+        links: usize,
+    },
+    Failed(anyhow::Error),
+    /// Skipped because the source is rate limited.
+    Skipped,
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct RefreshStats {
+    pub refreshed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+type SharedLoad = Shared<BoxFuture<'static, Result<Arc<FullReadmeRepo>, Arc<anyhow::Error>>>>;
+
+/// Loads currently in progress, keyed by the repo being loaded.
+type InFlight = Arc<std::sync::Mutex<HashMap<RepoIdent, SharedLoad>>>;
+
+/// Convert an error of a shared load back into an owned error.
+///
+/// Errors that callers check for are preserved, everything else is converted
+/// into a plain message.
+fn clone_shared_error(err: &anyhow::Error) -> anyhow::Error {
+    if let Some(e) = SourceError::find(err) {
+        e.clone_lossy().into()
+    } else if let Some(e) = err.downcast_ref::<ReadonlyError>() {
+        e.clone().into()
+    } else {
+        anyhow::anyhow!("{err:#}")
+    }
+}
+
+/// Why [`Loader::start_manual_refresh`] did not start a refresh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RefreshRejection {
+    /// The repo was refreshed recently. Holds the remaining wait time.
+    Cooldown(Duration),
+    /// Too many manual refreshes are running.
+    Busy,
+}
+
+/// Returned in readonly mode instead of loading data from the source.
+#[derive(Clone, Debug)]
+pub struct ReadonlyError {
+    pub ident: RepoIdent,
+}
+
+impl std::fmt::Display for ReadonlyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is not available - this is a read-only mirror that only serves imported lists",
+            self.ident
+        )
+    }
+}
+
+impl std::error::Error for ReadonlyError {}
+
+/// Returned when a README location is requested for a stored repo that uses
+/// a different location, and the caller may not change it.
+#[derive(Clone, Debug)]
+pub struct ReadmeLocationConflictError {
+    pub ident: RepoIdent,
+}
+
+impl std::fmt::Display for ReadmeLocationConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is already loaded from a different README location",
+            self.ident
+        )
+    }
+}
+
+impl std::error::Error for ReadmeLocationConflictError {}
+
+fn is_rate_limited(err: &anyhow::Error) -> bool {
+    SourceError::find(err).is_some_and(SourceError::is_rate_limited)
 }
 
 #[derive(Clone)]
 struct CacheEntry<T> {
     data: T,
     inserted_at: SystemTime,
+    /// Loading was interrupted by a rate limit, so the data is incomplete.
+    partial: bool,
+}
+
+type PopularRepos = Vec<Arc<FullReadmeRepo>>;
+
+struct ReadmeCacheEntry {
+    entry: CacheEntry<Arc<FullReadmeRepo>>,
+    /// Value of [`Cache::access_clock`] at the last access.
+    last_access: AtomicU64,
 }
 
 #[derive(Clone)]
 struct Cache {
-    readme_repos: Arc<RwLock<HashMap<RepoIdent, CacheEntry<Arc<FullReadmeRepo>>>>>,
+    /// Readme repos, evicted least-recently-accessed first once
+    /// `readme_capacity` is exceeded.
+    readme_repos: Arc<RwLock<HashMap<RepoIdent, ReadmeCacheEntry>>>,
+    readme_capacity: usize,
+    access_clock: Arc<AtomicU64>,
+    /// Popular repos, keyed by count.
+    popular_repos: Arc<RwLock<HashMap<usize, CacheEntry<PopularRepos>>>>,
+    popular_hits: Arc<AtomicU64>,
+    popular_misses: Arc<AtomicU64>,
 }
 
 impl Cache {
-    fn new() -> Self {
+    fn new(readme_capacity: usize) -> Self {
         Self {
             readme_repos: Arc::new(RwLock::new(HashMap::new())),
+            readme_capacity: readme_capacity.max(1),
+            access_clock: Arc::new(AtomicU64::new(0)),
+            popular_repos: Arc::new(RwLock::new(HashMap::new())),
+            popular_hits: Arc::new(AtomicU64::new(0)),
+            popular_misses: Arc::new(AtomicU64::new(0)),
         }
     }
 
-    async fn readme_repo(&self, ident: &RepoIdent) -> Option<CacheEntry<Arc<FullReadmeRepo>>> {
-        self.readme_repos.read().await.get(ident).cloned()
+    async fn popular_repos(&self, count: usize) -> Option<CacheEntry<PopularRepos>> {
+        self.popular_repos.read().await.get(&count).cloned()
     }
 
-    async fn readme_repo_insert(&self, ident: RepoIdent, data: FullReadmeRepo) {
-        self.readme_repos.write().await.insert(
-            ident,
+    async fn popular_repos_insert(&self, count: usize, data: PopularRepos) {
+        self.popular_repos.write().await.insert(
+            count,
             CacheEntry {
-                data: Arc::new(data),
+                data,
                 inserted_at: SystemTime::now(),
+                partial: false,
             },
         );
     }
+
+    fn tick(&self) -> u64 {
+        self.access_clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    async fn readme_repo(&self, ident: &RepoIdent) -> Option<CacheEntry<Arc<FullReadmeRepo>>> {
+        let repos = self.readme_repos.read().await;
+        let entry = repos.get(ident)?;
+        entry.last_access.store(self.tick(), Ordering::Relaxed);
+        Some(entry.entry.clone())
+    }
+
+    async fn readme_repo_remove(&self, ident: &RepoIdent) {
+        self.readme_repos.write().await.remove(ident);
+    }
+
+    async fn readme_repo_insert(
+        &self,
+        ident: RepoIdent,
+        data: FullReadmeRepo,
+        partial: bool,
+    ) -> Arc<FullReadmeRepo> {
+        let data = Arc::new(data);
+        let mut repos = self.readme_repos.write().await;
+        repos.insert(
+            ident,
+            ReadmeCacheEntry {
+                entry: CacheEntry {
+                    data: data.clone(),
+                    inserted_at: SystemTime::now(),
+                    partial,
+                },
+                last_access: AtomicU64::new(self.tick()),
+            },
+        );
+        while repos.len() > self.readme_capacity {
+            let oldest = repos
+                .iter()
+                .min_by_key(|(_, x)| x.last_access.load(Ordering::Relaxed))
+                .map(|(ident, _)| ident.clone());
+            if let Some(oldest) = oldest {
+                tracing::trace!(ident=%oldest, "evicting readme repo from memory cache");
+                repos.remove(&oldest);
+            }
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        source::{
+            gitea::mock::MockGitea,
+            github::{
+                mock::{MockGithub, MOCK_README_ETAG},
+                GithubClient,
+            },
+            Source,
+        },
+        storage::{fs::FsStore, mem::MemStore},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_loader_task_queue_persisted() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = Store::Fs(FsStore::new(dir.path().to_owned()).unwrap());
+        let source = SourceLoader::new(GithubClient::new(Vec::new()));
+
+        let a = Task::LoadRepoDetails(RepoIdent::new_github("org", "a"));
+        let b = Task::LoadReadmeRepo(RepoIdent::new_github("org", "b"));
+        let c = Task::LoadRepoDetails(RepoIdent::new_github("org", "c"));
+
+        let loader = Loader::new(store.clone(), source.clone(), LoaderConfig::default());
+        loader.tasks.push(a.clone()).await;
+        loader
+            .tasks
+            .push_many(vec![b.clone(), c.clone(), a.clone()])
+            .await;
+        // Single pops are not persisted right away, so the task runs again
+        // after a restart.
+        assert_eq!(loader.tasks.pop().await, Some(a.clone()));
+
+        let loader = Loader::new(store.clone(), source.clone(), LoaderConfig::default());
+        loader.tasks.restore().await.unwrap();
+        // Restoring twice must not duplicate tasks.
+        loader.tasks.restore().await.unwrap();
+
+        let tasks: Vec<_> = loader
+            .tasks
+            .state
+            .lock()
+            .await
+            .tasks
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(tasks, vec![a.clone(), b.clone(), c.clone()]);
+
+        // Popped tasks can be queued again.
+        assert_eq!(loader.tasks.pop().await, Some(a.clone()));
+        loader.tasks.push(a.clone()).await;
+        assert_eq!(loader.tasks.pop().await, Some(b));
+        assert_eq!(loader.tasks.pop().await, Some(c));
+        assert_eq!(loader.tasks.pop().await, Some(a));
+
+        // The empty queue is persisted.
+        let loader = Loader::new(store, source, LoaderConfig::default());
+        loader.tasks.restore().await.unwrap();
+        assert!(loader.tasks.state.lock().await.tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_loader_concurrent_loads_are_deduplicated() {
+        let store = Store::Mem(MemStore::new());
+        let github =
+            MockGithub::start("## Tools\n\n- [b](https://github.com/org/b) - desc\n").await;
+        let loader = Loader::new(
+            store,
+            SourceLoader::new(github.client()),
+            LoaderConfig::default(),
+        );
+
+        let ident = RepoIdent::new_github("org", "awesome");
+        let (a, b) = tokio::join!(
+            loader.load_full_readme_repo(ident.clone(), true),
+            loader.load_full_readme_repo(ident.clone(), true),
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(a.links.len(), 1);
+        let details = &a.links[0].details;
+        assert_eq!(details.license.as_deref(), Some("MIT"));
+        assert_eq!(details.topics, vec!["cli".to_string()]);
+        assert_eq!(github.readme_requests.load(Ordering::SeqCst), 1);
+        // Details of the list itself and of the linked repo.
+        assert_eq!(github.graphql_requests.load(Ordering::SeqCst), 2);
+        assert!(loader.in_flight.lock().unwrap().is_empty());
+
+        // Star counts of the list and the linked repo were recorded.
+        for ident in [ident, RepoIdent::new_github("org", "b")] {
+            let history = loader.store.star_history(ident).await.unwrap();
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].stars, 10);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_loader_codeberg_readme() {
+        let github = MockGithub::start("").await;
+        let gitea = MockGitea::start(
+            "- [a](https://codeberg.org/org/a)\n- [b](https://github.com/org/b)\n",
+        )
+        .await;
+        let loader = Loader::new(
+            Store::Mem(MemStore::new()),
+            SourceLoader::new(github.client()).with_gitea(gitea.client()),
+            LoaderConfig::default(),
+        );
+
+        let ident = RepoIdent::new(Source::Codeberg, "org", "awesome");
+        let repo = loader.load_full_readme_repo(ident, true).await.unwrap();
+
+        assert_eq!(gitea.readme_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(github.readme_requests.load(Ordering::SeqCst), 0);
+        assert_eq!(repo.repo.details.stargazer_count, 5);
+        let stars: Vec<_> = repo
+            .links
+            .iter()
+            .map(|link| (link.link.ident.source.clone(), link.details.stargazer_count))
+            .collect();
+        assert_eq!(stars, vec![(Source::Codeberg, 5), (Source::Github, 10)]);
+    }
+
+    #[tokio::test]
+    async fn test_loader_readme_location() {
+        let store = Store::Mem(MemStore::new());
+        let github = MockGithub::start("- [b](https://github.com/org/b)\n").await;
+        let loader = Loader::new(
+            store.clone(),
+            SourceLoader::new(github.client()),
+            LoaderConfig::default(),
+        );
+
+        let ident = RepoIdent::new_github("org", "awesome");
+        let location = ReadmeLocation {
+            path: Some("lists/README.md".to_string()),
+            git_ref: Some("dev".to_string()),
+        };
+        let repo = loader
+            .load_full_readme_repo_from(ident.clone(), location.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!(repo.repo.readme_location, location);
+        assert_eq!(github.contents_requests.load(Ordering::SeqCst), 1);
+
+        // Loading again with the same location uses the stored version.
+        loader
+            .load_full_readme_repo_from(ident.clone(), location.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!(github.contents_requests.load(Ordering::SeqCst), 1);
+
+        // Refreshes keep the location.
+        let repo = loader.refresh_readme_repo(&ident).await.unwrap();
+        assert_eq!(repo.repo.readme_location, location);
+        assert_eq!(github.contents_requests.load(Ordering::SeqCst), 2);
+        assert_eq!(github.readme_requests.load(Ordering::SeqCst), 2);
+
+        // A different location only replaces the stored one if allowed.
+        let other = ReadmeLocation {
+            path: Some("docs/README.md".to_string()),
+            git_ref: None,
+        };
+        let err = loader
+            .load_full_readme_repo_from(ident.clone(), other.clone(), false)
+            .await
+            .unwrap_err();
+        assert!(err.is::<ReadmeLocationConflictError>(), "{err:#}");
+        assert_eq!(github.contents_requests.load(Ordering::SeqCst), 2);
+
+        let repo = loader
+            .load_full_readme_repo_from(ident.clone(), other.clone(), true)
+            .await
+            .unwrap();
+        assert_eq!(repo.repo.readme_location, other);
+        assert_eq!(github.contents_requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cache_readme_lru() {
+        let cache = Cache::new(2);
+        let repo = |name: &str| {
+            let ident = RepoIdent::new_github("org", name);
+            let readme = ReadmeRepo::new_test(ident.clone(), vec![]);
+            (
+                ident,
+                FullReadmeRepo {
+                    repo: readme,
+                    links: Vec::new(),
+                    not_found: Vec::new(),
+                    rate_limit_reset_at: None,
+                },
+            )
+        };
+
+        let (a, a_repo) = repo("a");
+        let (b, b_repo) = repo("b");
+        let (c, c_repo) = repo("c");
+        cache.readme_repo_insert(a.clone(), a_repo, false).await;
+        cache.readme_repo_insert(b.clone(), b_repo, false).await;
+        // Accessing a makes b the least recently used entry.
+        assert!(cache.readme_repo(&a).await.is_some());
+        cache.readme_repo_insert(c.clone(), c_repo, false).await;
+
+        assert!(cache.readme_repo(&a).await.is_some());
+        assert!(cache.readme_repo(&b).await.is_none());
+        assert!(cache.readme_repo(&c).await.is_some());
+        assert_eq!(cache.readme_repos.read().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_loader_partial_resolve_retried() {
+        let store = Store::Mem(MemStore::new());
+        let list = RepoIdent::new_github("org", "awesome");
+        let links = vec![
+            RepoIdent::new_github("org", "a"),
+            RepoIdent::new_github("org", "b"),
+        ];
+        store
+            .readme_repo_upsert(ReadmeRepo::new_test(list.clone(), links))
+            .await
+            .unwrap();
+
+        let github = MockGithub::start("").await;
+        github.graphql_rate_limits.store(1, Ordering::SeqCst);
+        let client = github.client();
+        let loader = Loader::new(
+            store,
+            SourceLoader::new(client.clone()),
+            LoaderConfig::default(),
+        );
+
+        // The rate limit interrupts resolving the links.
+        let repo = loader
+            .load_full_readme_repo(list.clone(), true)
+            .await
+            .unwrap();
+        assert!(repo.links.is_empty());
+        assert_eq!(github.graphql_requests.load(Ordering::SeqCst), 1);
+
+        // The partial result is served while the rate limit lasts.
+        let repo = loader
+            .load_full_readme_repo(list.clone(), true)
+            .await
+            .unwrap();
+        assert!(repo.links.is_empty());
+        assert_eq!(github.graphql_requests.load(Ordering::SeqCst), 1);
+
+        // Once it cleared, the list is resolved again, despite the memory TTL.
+        client.reset_rate_limits();
+        let repo = loader
+            .load_full_readme_repo(list.clone(), true)
+            .await
+            .unwrap();
+        assert_eq!(repo.links.len(), 2);
+
+        // The full result is cached.
+        let requests = github.graphql_requests.load(Ordering::SeqCst);
+        loader.load_full_readme_repo(list, true).await.unwrap();
+        assert_eq!(github.graphql_requests.load(Ordering::SeqCst), requests);
+    }
+
+    #[tokio::test]
+    async fn test_loader_readonly() {
+        let store = Store::Mem(MemStore::new());
+        let list = RepoIdent::new_github("org", "awesome");
+        let mut stored =
+            ReadmeRepo::new_test(list.clone(), vec![RepoIdent::new_github("org", "a")]);
+        // Outdated, which would normally queue a refresh.
+        stored.updated_at -= time::Duration::days(30);
+        store.readme_repo_upsert(stored).await.unwrap();
+
+        let github = MockGithub::start("").await;
+        let loader = Loader::new(
+            store,
+            SourceLoader::new(github.client()),
+            LoaderConfig {
+                readonly: true,
+                ..Default::default()
+            },
+        );
+
+        // The stored list is served, without loading the missing link.
+        let repo = loader
+            .load_full_readme_repo(list.clone(), true)
+            .await
+            .unwrap();
+        assert!(repo.links.is_empty());
+
+        let err = loader
+            .load_full_readme_repo(RepoIdent::new_github("org", "other"), true)
+            .await
+            .unwrap_err();
+        assert!(err.is::<ReadonlyError>(), "{err:#}");
+        assert!(loader.refresh_readme_repo(&list).await.is_err());
+
+        assert_eq!(github.readme_requests.load(Ordering::SeqCst), 0);
+        assert_eq!(github.graphql_requests.load(Ordering::SeqCst), 0);
+        assert!(loader.tasks.state.lock().await.tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_loader_popular_repos_cached() {
+        let store = Store::Mem(MemStore::new());
+        let github = MockGithub::start("- [b](https://github.com/org/b)\n").await;
+        let loader = Loader::new(
+            store.clone(),
+            SourceLoader::new(github.client()),
+            LoaderConfig::default(),
+        );
+
+        let a = RepoIdent::new_github("org", "a");
+        store
+            .readme_repo_upsert(ReadmeRepo::new_test(a.clone(), vec![]))
+            .await
+            .unwrap();
+
+        assert_eq!(loader.popular_repos(10).await.unwrap().len(), 1);
+        assert_eq!(loader.popular_repos(10).await.unwrap().len(), 1);
+        assert_eq!(loader.popular_cache_hits(), 1);
+        assert_eq!(loader.popular_cache_misses(), 1);
+
+        // Different counts are cached separately.
+        loader.popular_repos(5).await.unwrap();
+        assert_eq!(loader.popular_cache_misses(), 2);
+
+        // Storage writes outside the loader are not visible until invalidated.
+        let b = RepoIdent::new_github("org", "b");
+        let mut readme_b = ReadmeRepo::new_test(b.clone(), vec![]);
+        readme_b.details.stargazer_count = 100;
+        store.readme_repo_upsert(readme_b).await.unwrap();
+        assert_eq!(loader.popular_repos(10).await.unwrap().len(), 1);
+        loader.invalidate_popular_repos().await;
+        let popular = loader.popular_repos(10).await.unwrap();
+        let idents: Vec<_> = popular.iter().map(|x| &x.repo.details.ident).collect();
+        assert_eq!(idents, vec![&b, &a]);
+        assert_eq!(loader.popular_cache_hits(), 2);
+        assert_eq!(loader.popular_cache_misses(), 3);
+
+        // The most starred lists are kept.
+        let popular = loader.popular_repos(1).await.unwrap();
+        assert_eq!(popular[0].repo.details.ident, b);
+        assert_eq!(loader.popular_cache_misses(), 4);
+
+        // Loading a list from the source invalidates the cache.
+        loader.source_load_readme_repo(&a).await.unwrap();
+        loader.popular_repos(10).await.unwrap();
+        assert_eq!(loader.popular_cache_misses(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_loader_readme_not_modified() {
+        let store = Store::Mem(MemStore::new());
+        let github = MockGithub::start("- [a](https://github.com/org/a)").await;
+        let loader = Loader::new(
+            store.clone(),
+            SourceLoader::new(github.client()),
+            LoaderConfig::default(),
+        );
+        let list = RepoIdent::new_github("org", "awesome");
+
+        let first = loader.source_load_readme_repo(&list).await.unwrap();
+        assert_eq!(first.readme_etag.as_deref(), Some(MOCK_README_ETAG));
+
+        // An unchanged README is not parsed again, but details are refreshed.
+        let mut stale = first.clone();
+        stale.repo_links.clear();
+        stale.details.stargazer_count = 0;
+        store.readme_repo_upsert(stale).await.unwrap();
+        let graphql_requests = github.graphql_requests.load(Ordering::SeqCst);
+
+        let repo = loader.source_load_readme_repo(&list).await.unwrap();
+        assert_eq!(github.not_modified_responses.load(Ordering::SeqCst), 1);
+        assert!(repo.repo_links.is_empty());
+        assert_eq!(repo.details.stargazer_count, 10);
+        assert_eq!(
+            github.graphql_requests.load(Ordering::SeqCst),
+            graphql_requests + 1
+        );
+        assert_eq!(store.star_history(list.clone()).await.unwrap().len(), 2);
+
+        // Forced refreshes parse the README again.
+        let full = loader.refresh_readme_repo(&list).await.unwrap();
+        assert_eq!(github.not_modified_responses.load(Ordering::SeqCst), 1);
+        assert_eq!(full.repo.repo_links.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_loader_warm() {
+        let store = Store::Mem(MemStore::new());
+        let github = MockGithub::start("").await;
+        let loader = Loader::new(
+            store.clone(),
+            SourceLoader::new(github.client()),
+            LoaderConfig::default(),
+        );
+
+        let list = RepoIdent::new_github("org", "awesome");
+        let known = RepoIdent::new_github("org", "known");
+        let missing = RepoIdent::new_github("org", "missing");
+        let mut readme = ReadmeRepo::new_test(list.clone(), vec![known.clone(), missing.clone()]);
+        readme.updated_at -= time::Duration::days(30);
+        store.readme_repo_upsert(readme).await.unwrap();
+        store
+            .repo_details_upsert(RepoDetailsItem::Found(RepoDetails::new_test(known)))
+            .await
+            .unwrap();
+
+        assert_eq!(loader.warm().await.unwrap(), 1);
+
+        assert!(loader.cache.readme_repo(&list).await.is_some());
+        let tasks: Vec<_> = loader
+            .tasks
+            .state
+            .lock()
+            .await
+            .tasks
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(
+            tasks,
+            vec![Task::LoadRepoDetails(missing), Task::LoadReadmeRepo(list)]
+        );
+        // Nothing was loaded from the source.
+        assert_eq!(github.readme_requests.load(Ordering::SeqCst), 0);
+        assert_eq!(github.graphql_requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_loader_refresh_all() {
+        let github = MockGithub::start("- [a](https://github.com/org/a)\n").await;
+        let store = Store::Mem(MemStore::new());
+        let loader = Loader::new(
+            store.clone(),
+            SourceLoader::new(github.client()),
+            LoaderConfig::default(),
+        );
+
+        let list = RepoIdent::new_github("org", "awesome");
+        store
+            .readme_repo_upsert(ReadmeRepo::new_test(list.clone(), vec![]))
+            .await
+            .unwrap();
+
+        let mut seen = Vec::new();
+        let stats = loader
+            .refresh_all(2, |ident, outcome| {
+                seen.push((ident.clone(), format!("{outcome:?}")))
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            stats,
+            RefreshStats {
+                refreshed: 1,
+                failed: 0,
+                skipped: 0
+            }
+        );
+        assert_eq!(
+            seen,
+            vec![(list.clone(), "Refreshed { links: 1 }".to_string())]
+        );
+
+        let stored = store.readme_repo(list).await.unwrap().unwrap();
+        assert_eq!(stored.repo_links.len(), 1);
+        assert!(store
+            .repo_details(RepoIdent::new_github("org", "a"))
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_loader_renamed_repo() {
+        use crate::source::github::mock::{RENAMED_REPO, RENAMED_REPO_TARGET};
+
+        let store = Store::Mem(MemStore::new());
+        let github = MockGithub::start("").await;
+        let loader = Loader::new(
+            store.clone(),
+            SourceLoader::new(github.client()),
+            LoaderConfig::default(),
+        );
+
+        let old = RepoIdent::new_github("org", RENAMED_REPO);
+        let new = RepoIdent::new_github(RENAMED_REPO_TARGET.0, RENAMED_REPO_TARGET.1);
+
+        let details = loader.load_repo_details(&old).await.unwrap();
+        assert_eq!(details.ident(), &old);
+
+        // Stored under both the old and the canonical ident.
+        let RepoDetailsItem::Found(alias) = store.repo_details(old.clone()).await.unwrap().unwrap()
+        else {
+            panic!("expected found details");
+        };
+        assert_eq!(alias.renamed_to, Some(new.clone()));
+        let RepoDetailsItem::Found(canonical) = store.repo_details(new).await.unwrap().unwrap()
+        else {
+            panic!("expected found details");
+        };
+        assert_eq!(canonical.renamed_to, None);
+
+        // The alias is served from storage.
+        loader.load_repo_details(&old).await.unwrap();
+        assert_eq!(github.graphql_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_loader_first_seen_stable() {
+        let dir = tempfile::tempdir().unwrap();
+        let github = MockGithub::start("").await;
+        let stores = [
+            Store::Mem(MemStore::new()),
+            Store::Fs(FsStore::new(dir.path().to_owned()).unwrap()),
+        ];
+
+        for store in stores {
+            let loader = Loader::new(
+                store.clone(),
+                SourceLoader::new(github.client()),
+                LoaderConfig::default(),
+            );
+            let ident = RepoIdent::new_github("org", "a");
+
+            let found = |item: RepoDetailsItem| match item {
+                RepoDetailsItem::Found(details) => details,
+                RepoDetailsItem::NotFound { .. } => panic!("expected found details"),
+            };
+
+            let first = found(loader.source_load_repo_details(&ident).await.unwrap());
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            loader.source_load_repo_details(&ident).await.unwrap();
+
+            let stored = found(store.repo_details(ident).await.unwrap().unwrap());
+            assert!(stored.updated_at > first.updated_at);
+            assert!(first.first_seen_at.is_some());
+            assert_eq!(stored.first_seen_at, first.first_seen_at);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_loader_not_found_expires() {
+        let store = Store::Mem(MemStore::new());
+        let github = MockGithub::start("").await;
+        let loader = Loader::new(
+            store.clone(),
+            SourceLoader::new(github.client()),
+            LoaderConfig::default(),
+        );
+
+        let fresh = RepoIdent::new_github("org", "fresh");
+        let expired = RepoIdent::new_github("org", "expired");
+        let now = OffsetDateTime::now_utc();
+        for (ident, updated_at) in [
+            (fresh.clone(), now),
+            (expired.clone(), now - time::Duration::days(8)),
+        ] {
+            store
+                .repo_details_upsert(RepoDetailsItem::NotFound { ident, updated_at })
+                .await
+                .unwrap();
+        }
+
+        // Lists loaded from storage queue a retry for expired items.
+        let list = RepoIdent::new_github("org", "awesome");
+        store
+            .readme_repo_upsert(ReadmeRepo::new_test(
+                list.clone(),
+                vec![fresh.clone(), expired.clone()],
+            ))
+            .await
+            .unwrap();
+        let repo = loader.load_full_readme_repo(list, false).await.unwrap();
+        assert_eq!(repo.not_found, vec![fresh.clone(), expired.clone()]);
+        let tasks: Vec<_> = loader
+            .tasks
+            .state
+            .lock()
+            .await
+            .tasks
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(tasks, vec![Task::LoadRepoDetails(expired.clone())]);
+
+        // A fresh not found item is served from storage.
+        assert!(loader
+            .load_repo_details(&fresh)
+            .await
+            .unwrap()
+            .is_not_found());
+        assert_eq!(github.graphql_requests.load(Ordering::SeqCst), 0);
+
+        // An expired one is fetched again.
+        assert!(loader.load_repo_details(&expired).await.unwrap().is_found());
+        assert_eq!(github.graphql_requests.load(Ordering::SeqCst), 1);
+        assert!(store
+            .repo_details(expired)
+            .await
+            .unwrap()
+            .unwrap()
+            .is_found());
+    }
+
+    #[tokio::test]
+    async fn test_loader_manual_refresh_limit() {
+        let github = MockGithub::start("- [a](https://github.com/org/a)\n").await;
+        let loader = Loader::new(
+            Store::Mem(MemStore::new()),
+            SourceLoader::new(github.client()),
+            LoaderConfig::default(),
+        );
+        let ident = RepoIdent::new_github("org", "awesome");
+
+        let busy = loader
+            .manual_refresh_slots
+            .clone()
+            .acquire_many_owned(MAX_MANUAL_REFRESHES as u32)
+            .await
+            .unwrap();
+        assert_eq!(
+            loader.start_manual_refresh(&ident),
+            Err(RefreshRejection::Busy)
+        );
+        drop(busy);
+
+        // The rejected attempt did not start the cooldown.
+        loader.start_manual_refresh(&ident).unwrap();
+        assert!(matches!(
+            loader.start_manual_refresh(&ident),
+            Err(RefreshRejection::Cooldown(_))
+        ));
+    }
 }
@@ -1,25 +1,155 @@
+use std::collections::HashSet;
+
 use anyhow::bail;
 use pulldown_cmark::{Event, HeadingLevel, Tag, TagEnd};
 
-use crate::source::{RepoIdent, RepoLink};
+use crate::source::{RepoIdent, RepoLink, Source};
+
+/// Options for [`parse_markdown`].
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    /// Additional Github Enterprise hosts to recognize in links.
+    pub github_hosts: Vec<String>,
+    /// Only keep the first link to each repo.
+    ///
+    /// Later occurrences are dropped, so a repo stays in the section where it
+    /// is first listed.
+    pub dedup: bool,
+}
 
-pub fn parse_markdown(input: &str) -> Result<Vec<RepoLink>, anyhow::Error> {
+/// Extract the links to repositories of all supported sources.
+///
+/// Hosts are recognized by [`RepoIdent::parse_url_with_hosts`].
+pub fn parse_markdown(input: &str, options: &ParseOptions) -> Result<Vec<RepoLink>, anyhow::Error> {
     let mut ctx = ParseContext {
+        options,
         section: Vec::new(),
+        items: Vec::new(),
         links: Vec::new(),
     };
 
     let mut iter = pulldown_cmark::TextMergeStream::new(pulldown_cmark::Parser::new(input));
     while let Some(()) = parse_event(&mut ctx, &mut iter)? {}
 
-    Ok(ctx.links)
+    let mut links = ctx.links;
+    if options.dedup {
+        let mut seen = HashSet::new();
+        links.retain(|link| seen.insert(link.ident.clone()));
+    }
+    Ok(links)
+}
+
+/// Count all links in a markdown document, regardless of their target.
+pub fn count_links(input: &str) -> usize {
+    pulldown_cmark::Parser::new(input)
+        .filter(|event| matches!(event, Event::Start(Tag::Link { .. })))
+        .count()
 }
 
-struct ParseContext {
-    section: Vec<String>,
+struct ParseContext<'o> {
+    options: &'o ParseOptions,
+    /// Stack of enclosing headings.
+    section: Vec<(HeadingLevel, String)>,
+    /// Stack of list items enclosing the current position.
+    items: Vec<ListItem>,
     links: Vec<RepoLink>,
 }
 
+impl ParseContext<'_> {
+    /// Section path for the current position.
+    ///
+    /// Consists of the headings, followed by the labels of all enclosing list
+    /// items that contain a nested list.
+    fn current_section(&self) -> Vec<String> {
+        let mut section: Vec<String> = self.section.iter().map(|(_, x)| x.clone()).collect();
+        section.extend(
+            self.items
+                .iter()
+                .filter(|item| item.has_sublist)
+                .map(|item| item.label())
+                .filter(|label| !label.is_empty()),
+        );
+        section
+    }
+
+    fn push_item_text(&mut self, text: &str) {
+        if let Some(item) = self.items.last_mut() {
+            if !item.has_sublist {
+                item.text.push_str(text);
+                if item.pending_link.is_some() && !item.in_html_anchor {
+                    item.link_description.push_str(text);
+                }
+            }
+        }
+    }
+
+    fn push_link(&mut self, link: RepoLink) {
+        self.links.push(link);
+        let index = self.links.len() - 1;
+
+        if self.items.last().is_some_and(|item| !item.has_sublist) {
+            self.finish_link_description();
+            if let Some(item) = self.items.last_mut() {
+                item.pending_link = Some(index);
+            }
+        }
+    }
+
+    /// Assign the text collected after the last link in the current item as
+    /// the description of that link.
+    fn finish_link_description(&mut self) {
+        let Some(item) = self.items.last_mut() else {
+            return;
+        };
+        let Some(index) = item.pending_link.take() else {
+            return;
+        };
+        let text = std::mem::take(&mut item.link_description);
+        self.links[index].description = clean_description(&text);
+    }
+}
+
+#[derive(Default)]
+struct ListItem {
+    /// Text content of the item, up to the start of a nested list.
+    text: String,
+    /// Set once a nested list starts inside the item.
+    has_sublist: bool,
+
+    /// Index of the last repo link in this item, waiting for its description.
+    pending_link: Option<usize>,
+    /// Text following the pending link.
+    link_description: String,
+    /// Set inside an inline HTML anchor, whose text is not part of the link
+    /// description.
+    in_html_anchor: bool,
+}
+
+impl ListItem {
+    fn label(&self) -> String {
+        self.text
+            .trim()
+            .trim_end_matches(':')
+            .trim_end()
+            .to_string()
+    }
+}
+
+/// Strips separators commonly placed between a link and its description.
+///
+/// eg: `[repo](url) - description`
+fn clean_description(text: &str) -> Option<String> {
+    let text = text
+        .trim_start_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '–' | '—' | ':'))
+        .trim();
+
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
 fn parse_event<'a, I>(ctx: &mut ParseContext, iter: &mut I) -> Result<Option<()>, anyhow::Error>
 where
     I: Iterator<Item = Event<'a>>,
@@ -30,19 +160,22 @@ where
 
     match ev {
         Event::Start(Tag::Heading { level, .. }) => {
-            let lvl = level as usize - 1;
-
             if level == HeadingLevel::H1 {
                 // Ignore h1
             } else {
-                let content = parse_content(TagEnd::Heading(level), iter)?;
+                let content = parse_content(TagEnd::Heading(level), iter)?.text;
 
-                if ctx.section.len() < lvl {
-                    ctx.section.push(content);
-                } else {
-                    ctx.section.truncate(lvl - 1);
-                    ctx.section.push(content);
+                // Pop headings of the same or a deeper level.
+                // Levels can be skipped, so the stack depth doesn't match
+                // the heading level.
+                while ctx
+                    .section
+                    .last()
+                    .is_some_and(|(parent, _)| *parent >= level)
+                {
+                    ctx.section.pop();
                 }
+                ctx.section.push((level, content));
             }
         }
         Event::Start(Tag::Link {
@@ -51,23 +184,102 @@ where
             title: _,
             id: _,
         }) => {
-            let _content = parse_content(TagEnd::Link, iter)?;
-            if let Ok(ident) = RepoIdent::parse_url(&dest_url) {
+            let content = parse_content(TagEnd::Link, iter)?;
+            // Links to other pages may still wrap a badge that references a repo.
+            let ident = RepoIdent::parse_url_with_hosts(&dest_url, &ctx.options.github_hosts)
+                .ok()
+                .or_else(|| {
+                    content
+                        .image_urls
+                        .iter()
+                        .find_map(|url| find_embedded_repo(url, ctx.options))
+                });
+            if let Some(ident) = ident {
                 let link = RepoLink {
                     ident,
-                    section: ctx.section.clone(),
+                    section: ctx.current_section(),
+                    description: None,
                 };
-                ctx.links.push(link);
+                // The link text is part of the item label, but not of the
+                // link description.
+                if let Some(item) = ctx.items.last_mut() {
+                    if !item.has_sublist {
+                        item.text.push_str(&content.text);
+                    }
+                }
+                ctx.push_link(link);
+            } else {
+                ctx.push_item_text(&content.text);
             }
         }
+        Event::Start(Tag::Image { dest_url, .. }) => {
+            // The alt text is not part of the item text.
+            let _ = parse_content(TagEnd::Image, iter)?;
+
+            if let Some(ident) = find_embedded_repo(&dest_url, ctx.options) {
+                // Badges are often placed next to a link to the same repo.
+                let is_duplicate = ctx
+                    .items
+                    .last()
+                    .and_then(|item| item.pending_link)
+                    .is_some_and(|index| ctx.links[index].ident == ident);
+                if !is_duplicate {
+                    ctx.push_link(RepoLink {
+                        ident,
+                        section: ctx.current_section(),
+                        description: None,
+                    });
+                }
+            }
+        }
+        Event::Start(Tag::List(_)) => {
+            ctx.finish_link_description();
+            if let Some(item) = ctx.items.last_mut() {
+                item.has_sublist = true;
+            }
+        }
+        Event::Start(Tag::Item) => {
+            ctx.items.push(ListItem::default());
+        }
+        Event::End(TagEnd::Item) => {
+            ctx.finish_link_description();
+            ctx.items.pop();
+        }
         Event::Start(_) => {}
         Event::End(_) => {}
-        Event::Text(_) => {}
-        Event::Code(_) => {}
+        Event::Text(text) => {
+            ctx.push_item_text(&text);
+        }
+        Event::Code(code) => {
+            ctx.push_item_text(&code);
+        }
         Event::InlineMath(_) => {}
         Event::DisplayMath(_) => {}
-        Event::Html(_) => {}
-        Event::InlineHtml(_) => {}
+        Event::Html(html) | Event::InlineHtml(html) => {
+            let mut found = false;
+            for href in html_hrefs(&html) {
+                if let Ok(ident) = RepoIdent::parse_url_with_hosts(href, &ctx.options.github_hosts)
+                {
+                    ctx.push_link(RepoLink {
+                        ident,
+                        section: ctx.current_section(),
+                        description: None,
+                    });
+                    found = true;
+                }
+            }
+
+            // The anchor text follows as regular text events, up to the
+            // closing tag.
+            let closes = html.to_ascii_lowercase().contains("</a");
+            if let Some(item) = ctx.items.last_mut() {
+                if closes {
+                    item.in_html_anchor = false;
+                } else if found {
+                    item.in_html_anchor = true;
+                }
+            }
+        }
         Event::FootnoteReference(_) => {}
         Event::SoftBreak => {}
         Event::HardBreak => {}
@@ -78,16 +290,112 @@ where
     Ok(Some(()))
 }
 
+/// Extract the values of all `href` attributes in a HTML snippet.
+fn html_hrefs(html: &str) -> Vec<&str> {
+    let mut hrefs = Vec::new();
+    let lower = html.to_ascii_lowercase();
+
+    for (index, _) in lower.match_indices("href") {
+        let rest = html[index + 4..].trim_start();
+        let Some(rest) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let rest = rest.trim_start();
+
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => rest[1..].split(quote).next(),
+            Some(_) => rest.split(|c: char| c.is_whitespace() || c == '>').next(),
+            None => None,
+        };
+        if let Some(value) = value.map(str::trim).filter(|x| !x.is_empty()) {
+            hrefs.push(value);
+        }
+    }
+
+    hrefs
+}
+
+/// Find a repository URL embedded in another URL.
+///
+/// eg: shields.io badges often reference the repo in a query parameter:
+/// `https://img.shields.io/badge/stars-1k-blue?link=https%3A%2F%2Fgithub.com%2Fowner%2Frepo`
+fn find_embedded_repo(url: &str, options: &ParseOptions) -> Option<RepoIdent> {
+    let mut candidates = vec![url.to_string()];
+    if let Ok(parsed) = url::Url::parse(url) {
+        // Query values are percent-decoded.
+        candidates.extend(parsed.query_pairs().map(|(_, value)| value.into_owned()));
+    }
+
+    let hosts: Vec<&str> = Source::ALL
+        .iter()
+        .map(|x| x.domain())
+        .chain(options.github_hosts.iter().map(|x| x.as_str()))
+        .collect();
+
+    candidates.iter().find_map(|candidate| {
+        hosts
+            .iter()
+            .find_map(|host| find_repo_path(candidate, host, options))
+    })
+}
+
+/// Find a `<host>/<owner>/<repo>` path in arbitrary text.
+fn find_repo_path(text: &str, host: &str, options: &ParseOptions) -> Option<RepoIdent> {
+    let needle = format!("{host}/");
+
+    for (index, _) in text.match_indices(&needle) {
+        // Must not be the suffix of a different host (eg: notgithub.com) or
+        // a subdomain (eg: gist.github.com), other than www.
+        let prefix = &text[..index];
+        let is_host_suffix = prefix
+            .strip_suffix("www.")
+            .unwrap_or(prefix)
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.'));
+        if is_host_suffix {
+            continue;
+        }
+
+        let mut segments = text[index + needle.len()..].split(|c: char| {
+            matches!(c, '/' | '?' | '#' | '&' | '"' | '\'' | ')') || c.is_whitespace()
+        });
+        let owner = segments.next().unwrap_or_default();
+        let repo = segments.next().unwrap_or_default().trim_end_matches(".git");
+        if owner.is_empty() || repo.is_empty() {
+            continue;
+        }
+
+        let url = format!("https://{host}/{owner}/{repo}");
+        if let Ok(ident) = RepoIdent::parse_url_with_hosts(&url, &options.github_hosts) {
+            return Some(ident);
+        }
+    }
+
+    None
+}
+
+/// Text content of an element.
+#[derive(Default)]
+struct Content {
+    text: String,
+    /// URLs of nested images.
+    image_urls: Vec<String>,
+}
+
 fn parse_content<'a>(
     tag: TagEnd,
     iter: &mut impl Iterator<Item = Event<'a>>,
-) -> Result<String, anyhow::Error> {
-    let mut buffer = String::new();
+) -> Result<Content, anyhow::Error> {
+    let mut content = Content::default();
 
     while let Some(ev) = iter.next() {
         match ev {
             Event::Start(tag) => {
                 // Skip
+                if let Tag::Image { dest_url, .. } = &tag {
+                    content.image_urls.push(dest_url.to_string());
+                }
 
                 let end = match tag {
                     Tag::Paragraph => TagEnd::Paragraph,
@@ -109,8 +417,9 @@ fn parse_content<'a>(
                     Tag::Image { .. } => TagEnd::Image,
                     Tag::MetadataBlock(kind) => TagEnd::MetadataBlock(kind),
                 };
-                // TODO: use inner content?
-                let _ = parse_content(end, iter)?;
+                // TODO: use inner text?
+                let inner = parse_content(end, iter)?;
+                content.image_urls.extend(inner.image_urls);
             }
             Event::End(t) => {
                 if t == tag {
@@ -120,10 +429,10 @@ fn parse_content<'a>(
                 }
             }
             Event::Text(text) => {
-                if !buffer.is_empty() {
-                    buffer.push(' ');
+                if !content.text.is_empty() {
+                    content.text.push(' ');
                 }
-                buffer.push_str(&text);
+                content.text.push_str(&text);
             }
             Event::Code(_code) => {}
             Event::InlineMath(_math) => {}
@@ -138,7 +447,7 @@ fn parse_content<'a>(
         }
     }
 
-    Ok(buffer)
+    Ok(content)
 }
 
 #[cfg(test)]
@@ -159,19 +468,254 @@ mod tests {
 [repo](https://github.com/a/b)
 
 "#;
-        let out = parse_markdown(input).unwrap();
+        let out = parse_markdown(input, &ParseOptions::default()).unwrap();
         assert_eq!(
             out,
             vec![
                 RepoLink {
                     ident: RepoIdent::new_github("a", "a"),
-                    section: vec!["hello".to_string()]
+                    section: vec!["hello".to_string()],
+                    description: None,
                 },
                 RepoLink {
                     ident: RepoIdent::new_github("a", "b"),
-                    section: vec!["world".to_string()]
+                    section: vec!["world".to_string()],
+                    description: None,
                 }
             ]
         );
     }
+
+    #[test]
+    fn test_parse_markdown_dedup() {
+        let input = r#"
+## first
+
+- [repo](https://github.com/a/a)
+- [other](https://github.com/a/b)
+
+## second
+
+- [repo again](https://github.com/a/a)
+"#;
+        let options = ParseOptions {
+            dedup: true,
+            ..Default::default()
+        };
+        let out = parse_markdown(input, &options).unwrap();
+        assert_eq!(
+            out,
+            vec![
+                RepoLink {
+                    ident: RepoIdent::new_github("a", "a"),
+                    section: vec!["first".to_string()],
+                    description: None,
+                },
+                RepoLink {
+                    ident: RepoIdent::new_github("a", "b"),
+                    section: vec!["first".to_string()],
+                    description: None,
+                },
+            ]
+        );
+
+        // Duplicates are kept by default.
+        let out = parse_markdown(input, &ParseOptions::default()).unwrap();
+        assert_eq!(out.len(), 3);
+    }
+
+    fn sections(input: &str) -> Vec<Vec<String>> {
+        parse_markdown(input, &ParseOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|x| x.section)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_markdown_skipped_heading_levels() {
+        let input = r#"
+## A
+#### B
+[repo](https://github.com/a/a)
+#### C
+[repo](https://github.com/a/b)
+## D
+[repo](https://github.com/a/c)
+"#;
+        assert_eq!(
+            sections(input),
+            vec![
+                vec!["A".to_string(), "B".to_string()],
+                vec!["A".to_string(), "C".to_string()],
+                vec!["D".to_string()],
+            ]
+        );
+
+        let input = r#"
+## A
+#### B
+[repo](https://github.com/a/a)
+### C
+[repo](https://github.com/a/b)
+"#;
+        assert_eq!(
+            sections(input),
+            vec![
+                vec!["A".to_string(), "B".to_string()],
+                vec!["A".to_string(), "C".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown_nested_lists() {
+        let input = r#"
+# main
+
+## Frameworks
+
+- Web
+  - **Backend**:
+    - [repo](https://github.com/a/a) - desc
+  - [repo](https://github.com/a/b)
+- [repo](https://github.com/a/c)
+"#;
+        let out = parse_markdown(input, &ParseOptions::default()).unwrap();
+        let sections: Vec<_> = out.iter().map(|x| x.section.clone()).collect();
+        assert_eq!(
+            sections,
+            vec![
+                vec![
+                    "Frameworks".to_string(),
+                    "Web".to_string(),
+                    "Backend".to_string()
+                ],
+                vec!["Frameworks".to_string(), "Web".to_string()],
+                vec!["Frameworks".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown_link_description() {
+        let input = r#"
+## Tools
+
+- [a](https://github.com/a/a) — A *fast* tool.
+- [b](https://github.com/a/b): Another tool.
+- [c](https://github.com/a/c)
+"#;
+        let out = parse_markdown(input, &ParseOptions::default()).unwrap();
+        let descriptions: Vec<_> = out.iter().map(|x| x.description.as_deref()).collect();
+        assert_eq!(
+            descriptions,
+            vec![Some("A fast tool."), Some("Another tool."), None]
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown_badge_images() {
+        let input = r#"
+## Tools
+
+- [![stars](https://img.shields.io/badge/stars-1k-blue?link=https%3A%2F%2Fgithub.com%2Fa%2Fbadge)](https://example.com) - Badge link.
+- ![ci](https://img.shields.io/badge/ci-passing-green?link=https://www.github.com/a/standalone)
+- [c](https://github.com/a/c) ![stars](https://img.shields.io/badge/x?link=https://github.com/a/c)
+- ![logo](https://notgithub.com/a/logo.png)
+- ![gist](https://img.shields.io/badge/x?link=https://gist.github.com/a/0123abcd)
+- ![screenshot](https://img.shields.io/badge/x?link=https://github.com/user-attachments/assets/0123)
+"#;
+        let out = parse_markdown(input, &ParseOptions::default()).unwrap();
+        let idents: Vec<_> = out.iter().map(|x| x.ident.clone()).collect();
+        assert_eq!(
+            idents,
+            vec![
+                RepoIdent::new_github("a", "badge"),
+                RepoIdent::new_github("a", "standalone"),
+                RepoIdent::new_github("a", "c"),
+            ]
+        );
+        assert_eq!(out[0].section, vec!["Tools".to_string()]);
+        assert_eq!(out[0].description.as_deref(), Some("Badge link."));
+    }
+
+    #[test]
+    fn test_parse_markdown_html_anchors() {
+        let input = r#"
+## Tools
+
+- <a href="https://github.com/a/inline">inline</a> - An inline anchor.
+- <a href="https://github.com/a/badge">badge</a> ![stars](https://img.shields.io/badge/stars-1k-blue?link=https%3A%2F%2Fgithub.com%2Fa%2Fbadge) - With a badge.
+
+<p>
+  <a class="x" href='https://github.com/a/block'>block</a>
+  <a href="https://example.com">other</a>
+</p>
+"#;
+        let out = parse_markdown(input, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            out,
+            vec![
+                RepoLink {
+                    ident: RepoIdent::new_github("a", "inline"),
+                    section: vec!["Tools".to_string()],
+                    description: Some("An inline anchor.".to_string()),
+                },
+                RepoLink {
+                    ident: RepoIdent::new_github("a", "badge"),
+                    section: vec!["Tools".to_string()],
+                    description: Some("With a badge.".to_string()),
+                },
+                RepoLink {
+                    ident: RepoIdent::new_github("a", "block"),
+                    section: vec!["Tools".to_string()],
+                    description: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_html_hrefs() {
+        assert_eq!(
+            html_hrefs(r#"<a HREF = "a" href='b' href=c>x</a><a hreflang="en">"#),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_count_links() {
+        let input = "[a](https://github.com/a/a) [b](https://example.com)\n\n![img](x.png)";
+        assert_eq!(count_links(input), 2);
+    }
+
+    #[test]
+    fn test_parse_markdown_mixed_hosts() {
+        let input = r#"
+- [public](https://github.com/a/a)
+- [enterprise](https://github.mycorp.com/b/b)
+- [unknown](https://git.example.com/c/c)
+- [![badge](https://img.shields.io/badge/x-1-blue?link=https%3A%2F%2Fgithub.mycorp.com%2Fd%2Fd)](https://example.com)
+"#;
+        let options = ParseOptions {
+            github_hosts: vec!["github.mycorp.com".to_string()],
+            ..Default::default()
+        };
+
+        let idents: Vec<_> = parse_markdown(input, &options)
+            .unwrap()
+            .into_iter()
+            .map(|link| link.ident)
+            .collect();
+        assert_eq!(
+            idents,
+            vec![
+                RepoIdent::new_github("a", "a"),
+                RepoIdent::new_github("b", "b").with_host("github.mycorp.com"),
+                RepoIdent::new_github("d", "d").with_host("github.mycorp.com"),
+            ]
+        );
+        assert!(idents.iter().all(|x| x.source == Source::Github));
+    }
 }
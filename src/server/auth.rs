@@ -0,0 +1,113 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use super::{ApiError, Ctx};
+
+/// Extractor that guards mutating API routes.
+///
+/// If an API token is configured, requests must send it as
+/// `Authorization: Bearer <token>`. Without a configured token all requests
+/// are allowed.
+pub(crate) struct RequireApiToken;
+
+#[async_trait]
+impl FromRequestParts<Ctx> for RequireApiToken {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, ctx: &Ctx) -> Result<Self, Self::Rejection> {
+        let Some(expected) = ctx.api_token.as_deref() else {
+            return Ok(Self);
+        };
+
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+
+        match token {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(Self),
+            Some(_) => {
+                Err(ApiError::msg("invalid API token", StatusCode::UNAUTHORIZED).into_response())
+            }
+            None => Err(ApiError::msg(
+                "missing API token - send 'Authorization: Bearer <token>'",
+                StatusCode::UNAUTHORIZED,
+            )
+            .into_response()),
+        }
+    }
+}
+
+/// Compare without short-circuiting, so the token can't be guessed from
+/// response timings.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use crate::{
+        server::{
+            build_router,
+            routes::{api_import::PATH_API_IMPORT, api_repo::PATH_API_REPO},
+            Ctx,
+        },
+        storage::{mem::MemStore, Store},
+    };
+
+    #[tokio::test]
+    async fn test_server_api_token() {
+        let mut ctx = Ctx::new(Store::Mem(MemStore::new()));
+        ctx.api_token = Some("secret".into());
+        let client = axum_test_helper::TestClient::new(build_router(ctx)).await;
+
+        let delete_path = PATH_API_REPO
+            .replace(":source", "github")
+            .replace(":owner", "org")
+            .replace(":repo", "repo");
+
+        let res = client.post(PATH_API_IMPORT).json(&[(); 0]).send().await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = client
+            .post(PATH_API_IMPORT)
+            .header("authorization", "Bearer wrong")
+            .json(&[(); 0])
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = client.delete(&delete_path).send().await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let res = client
+            .post(PATH_API_IMPORT)
+            .header("authorization", "Bearer secret")
+            .json(&[(); 0])
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .delete(&delete_path)
+            .header("authorization", "Bearer secret")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        // Read-only routes stay open.
+        let res = client.get("/health").send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,57 @@
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use super::ApiError;
+
+/// JSON body extractor that reports the path of invalid fields.
+///
+/// Like [`axum::Json`], but deserialization errors name the offending field
+/// (eg: `[3].Repo.Found.stargazer_count`), which matters for large bodies
+/// like imports.
+pub(crate) struct JsonBody<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: serde::de::DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_json = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .is_some_and(|x| x.starts_with("application/json"));
+        if !is_json {
+            return Err(ApiError::msg(
+                "expected 'Content-Type: application/json'",
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            )
+            .into_response());
+        }
+
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let de = &mut serde_json::Deserializer::from_slice(&body);
+        serde_path_to_error::deserialize(de)
+            .map(Self)
+            .map_err(|err| {
+                let path = err.path().to_string();
+                ApiError::msg(
+                    format!("invalid JSON at '{}': {}", path, err.inner()),
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                )
+                .with_path(path)
+                .into_response()
+            })
+    }
+}
@@ -1,7 +1,11 @@
+mod auth;
+mod extract;
+mod render_cache;
+mod request_id;
 mod routes;
 mod ui;
 
-use std::{net::SocketAddr, path::PathBuf, time::Duration};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use anyhow::Context;
 use axum::{
@@ -9,40 +13,234 @@ use axum::{
     routing::{get, post},
     Router,
 };
-use tower_http::trace::TraceLayer;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
 
+use self::render_cache::RenderCache;
+pub use crate::loader::{RefreshOutcome, RefreshStats};
 use crate::{
-    loader::Loader,
-    source::{github::GithubClient, loader::SourceLoader, RepoIdent},
-    storage::{fs::FsStore, Store},
+    loader::{Loader, LoaderConfig, ReadmeLocationConflictError, ReadonlyError},
+    source::{
+        github::{GithubClient, DEFAULT_MAX_ATTEMPTS, DEFAULT_REQUESTS_PER_SECOND},
+        loader::SourceLoader,
+        RepoIdent, SourceError,
+    },
+    storage::{fs::FsStore, mem::MemStore, StorageKind, Store},
 };
 
 pub struct CtxBuilder {
     pub data_dir: PathBuf,
-    pub github_token: Option<String>,
+    pub storage: StorageKind,
+    pub github_tokens: Vec<String>,
+    /// Additional Github Enterprise hosts.
+    pub github_hosts: Vec<String>,
+    /// Only keep the first link to each repo when parsing READMEs.
+    pub dedup_links: bool,
+    pub loader: LoaderConfig,
+    /// Token required for mutating API routes.
+    pub api_token: Option<String>,
+    /// Maximum Github API requests per second. Zero disables the limit.
+    pub github_requests_per_second: f64,
+    /// Attempts per Github request for transient errors.
+    pub github_max_attempts: u32,
+    /// `max-age` of the `Cache-Control` header of repo pages.
+    pub page_max_age: Duration,
+    /// Run the loader task loop.
+    pub background_tasks: bool,
+    /// Base URL of absolute links to the site.
+    pub public_url: Option<String>,
 }
 
+/// Default `max-age` of repo pages.
+pub const DEFAULT_PAGE_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
 impl CtxBuilder {
     pub fn new(data_dir: PathBuf) -> Self {
         Self {
             data_dir,
-            github_token: None,
+            storage: StorageKind::default(),
+            github_tokens: Vec::new(),
+            github_hosts: Vec::new(),
+            dedup_links: false,
+            loader: LoaderConfig::default(),
+            api_token: None,
+            github_requests_per_second: DEFAULT_REQUESTS_PER_SECOND,
+            github_max_attempts: DEFAULT_MAX_ATTEMPTS,
+            page_max_age: DEFAULT_PAGE_MAX_AGE,
+            background_tasks: true,
+            public_url: None,
         }
     }
 
+    /// Set the storage backend.
+    ///
+    /// The data directory is ignored for [`StorageKind::Memory`].
+    pub fn storage(mut self, storage: StorageKind) -> Self {
+        self.storage = storage;
+        self
+    }
+
     pub fn github_token(mut self, token: Option<String>) -> Self {
-        self.github_token = token;
+        self.github_tokens = token.into_iter().collect();
         self
     }
 
-    pub fn build(self) -> Result<Ctx, anyhow::Error> {
-        let github = GithubClient::new(self.github_token);
-        let sources = SourceLoader::new(github);
-        let store = Store::Fs(FsStore::new(self.data_dir)?);
+    /// Set multiple Github tokens.
+    ///
+    /// Requests are distributed across the tokens to extend rate limits.
+    pub fn github_tokens(mut self, tokens: Vec<String>) -> Self {
+        self.github_tokens = tokens;
+        self
+    }
 
-        let loader = Loader::start(store.clone(), sources);
+    /// Set additional Github Enterprise hosts (eg: `github.mycorp.com`).
+    ///
+    /// Public github.com is always supported.
+    pub fn github_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.github_hosts = hosts;
+        self
+    }
 
-        Ok(Ctx { store, loader })
+    /// Only keep the first link to each repo when parsing READMEs.
+    ///
+    /// Applies to READMEs loaded after startup; stored lists are unchanged
+    /// until they are refreshed.
+    pub fn dedup_links(mut self, dedup: bool) -> Self {
+        self.dedup_links = dedup;
+        self
+    }
+
+    /// How long loaded repos are cached in memory.
+    ///
+    /// Set to zero to always load from storage.
+    pub fn memory_ttl(mut self, ttl: Duration) -> Self {
+        self.loader.memory_ttl = ttl;
+        self
+    }
+
+    /// Age after which stored READMEs are refreshed from the source.
+    pub fn readme_refresh(mut self, interval: Duration) -> Self {
+        self.loader.readme_refresh = interval;
+        self
+    }
+
+    /// Maximum number of lists kept in memory.
+    pub fn memory_capacity(mut self, capacity: usize) -> Self {
+        self.loader.memory_capacity = capacity;
+        self
+    }
+
+    /// Minimum time between manual refreshes of the same list.
+    pub fn refresh_cooldown(mut self, cooldown: Duration) -> Self {
+        self.loader.refresh_cooldown = cooldown;
+        self
+    }
+
+    /// Age after which repos stored as not found are queried again.
+    pub fn not_found_ttl(mut self, ttl: Duration) -> Self {
+        self.loader.not_found_ttl = ttl;
+        self
+    }
+
+    /// How long the popular lists shown on the homepage are cached.
+    pub fn popular_ttl(mut self, ttl: Duration) -> Self {
+        self.loader.popular_ttl = ttl;
+        self
+    }
+
+    /// Load all stored lists in the background on startup.
+    pub fn warm_on_start(mut self, warm: bool) -> Self {
+        self.loader.warm_on_start = warm;
+        self
+    }
+
+    /// Only serve stored data, without any source requests, background
+    /// tasks or writes to the data directory.
+    pub fn readonly(mut self, readonly: bool) -> Self {
+        self.loader.readonly = readonly;
+        self
+    }
+
+    /// Require this token for mutating API routes, like import and delete.
+    ///
+    /// Without a token, these routes are open to everyone.
+    pub fn api_token(mut self, token: Option<String>) -> Self {
+        self.api_token = token.filter(|x| !x.trim().is_empty());
+        self
+    }
+
+    /// Limit Github API requests per second. Zero disables the limit.
+    pub fn github_requests_per_second(mut self, rate: f64) -> Self {
+        self.github_requests_per_second = rate;
+        self
+    }
+
+    /// Set how often Github requests are attempted on transient errors.
+    pub fn github_max_attempts(mut self, attempts: u32) -> Self {
+        self.github_max_attempts = attempts;
+        self
+    }
+
+    /// How long proxies and browsers may cache repo pages.
+    ///
+    /// Set to zero to disable caching.
+    pub fn page_max_age(mut self, max_age: Duration) -> Self {
+        self.page_max_age = max_age;
+        self
+    }
+
+    /// Public URL of the site (eg: `https://awesome.example.com`), used for
+    /// absolute links in page metadata and feed exports.
+    ///
+    /// Defaults to the awesomelify instance.
+    pub fn public_url(mut self, url: Option<String>) -> Self {
+        self.public_url = url
+            .map(|x| x.trim().trim_end_matches('/').to_string())
+            .filter(|x| !x.is_empty());
+        self
+    }
+
+    /// Run the loader task loop, which loads queued repos in the background.
+    ///
+    /// Disable for one-off commands that load what they need directly.
+    pub fn background_tasks(mut self, enabled: bool) -> Self {
+        self.background_tasks = enabled;
+        self
+    }
+
+    pub fn build(self) -> Result<Ctx, anyhow::Error> {
+        let github = GithubClient::new(self.github_tokens)
+            .with_requests_per_second(self.github_requests_per_second)
+            .with_max_attempts(self.github_max_attempts);
+        let sources = SourceLoader::new(github)
+            .with_github_hosts(self.github_hosts.clone())
+            .with_dedup_links(self.dedup_links);
+        let store = match self.storage {
+            StorageKind::Fs if self.loader.readonly => {
+                Store::Fs(FsStore::new_readonly(self.data_dir)?)
+            }
+            StorageKind::Fs => Store::Fs(FsStore::new(self.data_dir)?),
+            StorageKind::Memory => Store::Mem(MemStore::new()),
+        };
+
+        let loader = if self.background_tasks {
+            Loader::start(store.clone(), sources, self.loader)
+        } else {
+            Loader::new(store.clone(), sources, self.loader)
+        };
+
+        let render_cache = RenderCache::new(self.loader.memory_capacity);
+        Ok(Ctx {
+            store,
+            loader,
+            render_cache,
+            github_hosts: Arc::new(self.github_hosts),
+            api_token: self.api_token.map(Into::into),
+            page_max_age: self.page_max_age,
+            public_url: self.public_url.as_deref().unwrap_or(ui::SITE_URL).into(),
+        })
     }
 }
 
@@ -52,33 +250,145 @@ pub struct Ctx {
     #[allow(dead_code)]
     store: Store,
     loader: Loader,
+    render_cache: RenderCache,
+    github_hosts: Arc<Vec<String>>,
+    api_token: Option<Arc<str>>,
+    page_max_age: Duration,
+    /// Base URL of absolute links to the site, without a trailing slash.
+    public_url: Arc<str>,
 }
 
 impl Ctx {
     pub fn new(store: Store) -> Self {
         let github = GithubClient::from_env();
-        let sources = SourceLoader::new(github);
-        let loader = Loader::start(store.clone(), sources);
+        Self::with_source(store, SourceLoader::new(github))
+    }
+
+    fn with_source(store: Store, sources: SourceLoader) -> Self {
+        Self::with_loader_config(store, sources, LoaderConfig::default())
+    }
+
+    fn with_loader_config(store: Store, sources: SourceLoader, config: LoaderConfig) -> Self {
+        let loader = Loader::start(store.clone(), sources, config);
+
+        Ctx {
+            store,
+            loader,
+            render_cache: RenderCache::new(config.memory_capacity),
+            github_hosts: Arc::new(Vec::new()),
+            api_token: None,
+            page_max_age: DEFAULT_PAGE_MAX_AGE,
+            public_url: ui::SITE_URL.into(),
+        }
+    }
+
+    pub async fn run_server(self, addr: SocketAddr) -> Result<(), anyhow::Error> {
+        run_server(addr, self).await
+    }
+
+    /// Re-fetch all stored lists from their sources.
+    ///
+    /// See [`Loader::refresh_all`].
+    pub async fn refresh_all(
+        &self,
+        concurrency: usize,
+        on_result: impl FnMut(&RepoIdent, &RefreshOutcome),
+    ) -> Result<RefreshStats, anyhow::Error> {
+        self.loader.refresh_all(concurrency, on_result).await
+    }
+
+    /// Drop a repo from the loader and render caches.
+    ///
+    /// Must be called after modifying storage outside of the loader.
+    async fn invalidate(&self, ident: &RepoIdent) {
+        self.loader.invalidate(ident).await;
+        self.render_cache.remove(ident).await;
+    }
 
-        Ctx { store, loader }
+    /// Parse a user provided repo URL or ident.
+    fn parse_ident(&self, ident: &str) -> Result<RepoIdent, anyhow::Error> {
+        RepoIdent::parse_ident_with_hosts(ident, &self.github_hosts)
     }
 
-    pub async fn run_server(self, port: u16) -> Result<(), anyhow::Error> {
-        let ctx = Ctx::new(self.store);
-        let addr = SocketAddr::from(([0, 0, 0, 0], port));
-        run_server(addr, ctx).await
+    /// Build an ident from the `/:source/:owner/:repo` path segments.
+    fn ident_from_path(
+        &self,
+        (source, owner, repo): (String, String, String),
+    ) -> Result<RepoIdent, anyhow::Error> {
+        RepoIdent::from_path(&source, owner, repo, &self.github_hosts)
     }
 }
 
 pub const DEFAULT_PORT: u16 = 3333;
 
 fn build_router(ctx: Ctx) -> Router {
+    // API responses must not be cached by proxies.
+    let api = Router::new()
+        .route(
+            routes::api_export::PATH_API_EXPORT,
+            get(routes::api_export::handler_api_export),
+        )
+        .route(
+            routes::api_export::PATH_API_EXPORT_JSONL,
+            get(routes::api_export::handler_api_export_jsonl),
+        )
+        .route(
+            routes::api_import::PATH_API_IMPORT,
+            post(routes::api_import::handler_api_import),
+        )
+        .route(
+            routes::api_repo::PATH_API_REPO,
+            get(routes::api_repo::handler_api_repo)
+                .delete(routes::api_repo::handler_api_repo_delete),
+        )
+        .route(
+            routes::api_lists::PATH_API_LISTS,
+            get(routes::api_lists::handler_api_lists),
+        )
+        .route(
+            routes::api_opml::PATH_API_OPML,
+            get(routes::api_opml::handler_api_opml),
+        )
+        .route(
+            routes::api_search::PATH_API_SEARCH,
+            get(routes::api_search::handler_api_search),
+        )
+        .route(
+            routes::api_repo::PATH_API_REPO_STARS,
+            get(routes::api_repo::handler_api_repo_stars),
+        )
+        .layer(axum::middleware::map_response(no_store));
+
     Router::new()
         .route("/", get(routes::homepage::handler_homepage))
+        .route(
+            routes::metrics::PATH_METRICS,
+            get(routes::metrics::handler_metrics),
+        )
+        .route(
+            routes::static_files::PATH_STATIC,
+            get(routes::static_files::handler_static),
+        )
+        .route(
+            routes::static_files::PATH_FAVICON,
+            get(routes::static_files::handler_favicon),
+        )
+        .route(
+            routes::health::PATH_HEALTH,
+            get(routes::health::handler_health),
+        )
+        .route(
+            routes::health::PATH_HEALTH_READY,
+            get(routes::health::handler_health_ready),
+        )
         .route(
             routes::search::PATH_SEARCH,
             get(routes::search::handler_search),
         )
+        .route(
+            routes::compare::PATH_COMPARE,
+            get(routes::compare::handler_compare),
+        )
         .route(
             routes::repo_list::PATH_README_LIST,
             get(routes::repo_list::handler_readme_list),
@@ -87,30 +397,60 @@ fn build_router(ctx: Ctx) -> Router {
             "/repo/:source/:owner/:repo",
             get(routes::repo_page::handler_repo),
         )
-        // API
         .route(
-            routes::api_export::PATH_API_EXPORT,
-            get(routes::api_export::handler_api_export),
+            routes::repo_badge::PATH_REPO_BADGE,
+            get(routes::repo_badge::handler_repo_badge),
         )
         .route(
-            routes::api_import::PATH_API_IMPORT,
-            post(routes::api_import::handler_api_import),
+            routes::repo_broken::PATH_REPO_BROKEN,
+            get(routes::repo_broken::handler_repo_broken),
+        )
+        .route(
+            routes::repo_feed::PATH_REPO_FEED,
+            get(routes::repo_feed::handler_repo_feed),
+        )
+        .route(
+            routes::repo_export_csv::PATH_REPO_EXPORT_CSV,
+            get(routes::repo_export_csv::handler_repo_export_csv),
+        )
+        .route(
+            routes::repo_export_md::PATH_REPO_EXPORT_MD,
+            get(routes::repo_export_md::handler_repo_export_md),
         )
+        .route(
+            routes::repo_refresh::PATH_REPO_REFRESH,
+            post(routes::repo_refresh::handler_repo_refresh),
+        )
+        .merge(api)
+        .fallback(routes::not_found::handler_not_found)
         .with_state(ctx)
+        .layer(axum::middleware::from_fn(request_id::scope_request_id))
         .layer(
             TraceLayer::new_for_http()
-                .make_span_with(
-                    tower_http::trace::DefaultMakeSpan::new().level(tracing::Level::INFO),
-                )
+                .make_span_with(|req: &axum::http::Request<_>| {
+                    tracing::info_span!(
+                        "request",
+                        method = %req.method(),
+                        uri = %req.uri(),
+                        version = ?req.version(),
+                        request_id = request_id::from_request(req),
+                    )
+                })
                 .on_response(
                     tower_http::trace::DefaultOnResponse::new().level(tracing::Level::INFO),
                 ),
         )
+        // Echo the request id in the response.
+        .layer(PropagateRequestIdLayer::x_request_id())
+        // Use the request id sent by the client, or generate one.
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
         .layer(
             // Graceful shutdown will wait for outstanding requests to complete.
             // Add a timeout so requests don't hang forever.
             tower_http::timeout::TimeoutLayer::new(Duration::from_secs(30)),
         )
+        // Compresses responses when the client sends `Accept-Encoding`.
+        .layer(tower_http::compression::CompressionLayer::new())
 }
 
 async fn run_server(addr: SocketAddr, ctx: Ctx) -> Result<(), anyhow::Error> {
@@ -154,10 +494,29 @@ async fn shutdown_signal() {
     tracing::info!("received shutdown signal");
 }
 
+/// Response status for an error, based on the [`SourceError`] it wraps.
+fn error_status(err: &anyhow::Error) -> StatusCode {
+    if err.is::<ReadonlyError>() {
+        return StatusCode::NOT_FOUND;
+    }
+    if err.is::<ReadmeLocationConflictError>() {
+        return StatusCode::CONFLICT;
+    }
+    match SourceError::find(err) {
+        Some(SourceError::NotFound(_) | SourceError::NotAwesomeList(_)) => StatusCode::NOT_FOUND,
+        Some(SourceError::RateLimited(_)) => StatusCode::SERVICE_UNAVAILABLE,
+        Some(SourceError::UnsupportedEncoding(_) | SourceError::Other(_)) | None => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
 struct ApiError {
     message: String,
     status: StatusCode,
     source: Option<anyhow::Error>,
+    /// Path of the offending field in the request body.
+    path: Option<String>,
 }
 
 impl ApiError {
@@ -166,16 +525,23 @@ impl ApiError {
             message: message.into(),
             status,
             source: None,
+            path: None,
         }
     }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
 }
 
 impl From<anyhow::Error> for ApiError {
     fn from(source: anyhow::Error) -> Self {
         Self {
             message: source.to_string(),
-            status: StatusCode::INTERNAL_SERVER_ERROR,
+            status: error_status(&source),
             source: Some(source),
+            path: None,
         }
     }
 }
@@ -185,11 +551,13 @@ impl axum::response::IntoResponse for ApiError {
         let data = serde_json::json!({
             "error": {
                 "message": &self.message,
-                "source": self.source.as_ref().map(|x| format!("{:#?}", x))
+                "source": self.source.as_ref().map(|x| format!("{:#?}", x)),
+                "path": self.path,
+                "request_id": request_id::current(),
             }
         });
 
-        let body = serde_json::to_vec(&data).unwrap();
+        let body = serde_json::to_vec_pretty(&data).unwrap();
 
         axum::http::Response::builder()
             .status(self.status)
@@ -219,7 +587,7 @@ impl From<anyhow::Error> for HtmlError {
     fn from(source: anyhow::Error) -> Self {
         Self {
             message: source.to_string(),
-            status: StatusCode::INTERNAL_SERVER_ERROR,
+            status: error_status(&source),
             source: Some(source),
         }
     }
@@ -247,18 +615,45 @@ impl From<anyhow::Error> for HtmlErrorPage {
 
 impl axum::response::IntoResponse for HtmlErrorPage {
     fn into_response(self) -> axum::response::Response<axum::body::Body> {
-        let body = crate::server::ui::render_html_error_page(&self.0);
+        let not_awesome = match self.0.source.as_ref().and_then(SourceError::find) {
+            Some(SourceError::NotAwesomeList(err)) => Some(err),
+            _ => None,
+        };
+        let (status, body) = match not_awesome {
+            Some(err) => (
+                StatusCode::NOT_FOUND,
+                crate::server::ui::render_not_awesome_list_page(&err.ident),
+            ),
+            None => (
+                self.0.status,
+                crate::server::ui::render_html_error_page(&self.0),
+            ),
+        };
 
         axum::http::Response::builder()
-            .status(self.0.status)
+            .status(status)
             .header("content-type", "text/html")
             .body(axum::body::Body::from(body))
             .unwrap()
     }
 }
 
+/// Disallow caching of a response.
+async fn no_store(mut res: axum::response::Response) -> axum::response::Response {
+    res.headers_mut().insert(
+        axum::http::header::CACHE_CONTROL,
+        axum::http::HeaderValue::from_static("no-store"),
+    );
+    res
+}
+
 fn repo_page_uri(ident: &RepoIdent) -> String {
-    format!("/repo/{}/{}/{}", ident.source, ident.owner, ident.repo)
+    format!(
+        "/repo/{}/{}/{}",
+        ident.path_source(),
+        ident.owner,
+        ident.repo
+    )
 }
 
 #[cfg(test)]
@@ -268,6 +663,44 @@ async fn test_client_with_store(store: Store) -> axum_test_helper::TestClient {
     axum_test_helper::TestClient::new(app).await
 }
 
+/// Test client for a memory store containing `items`.
+#[cfg(test)]
+async fn test_client_with_items(items: Vec<crate::storage::Item>) -> axum_test_helper::TestClient {
+    use crate::storage::Storage;
+
+    let store = Store::Mem(crate::storage::mem::MemStore::new());
+    store
+        .import(items)
+        .await
+        .expect("could not import test items");
+    test_client_with_store(store).await
+}
+
+/// Response body with the `&#x2F;` escapes of rendered HTML reverted to `/`.
+#[cfg(test)]
+async fn unescaped_text(res: axum_test_helper::TestResponse) -> String {
+    res.text().await.replace("&#x2F;", "/")
+}
+
+#[cfg(test)]
+async fn test_client_with_source(
+    store: Store,
+    source: SourceLoader,
+) -> axum_test_helper::TestClient {
+    let app = build_router(Ctx::with_source(store, source));
+    axum_test_helper::TestClient::new(app).await
+}
+
+#[cfg(test)]
+async fn test_client_with_loader_config(
+    store: Store,
+    source: SourceLoader,
+    config: LoaderConfig,
+) -> axum_test_helper::TestClient {
+    let app = build_router(Ctx::with_loader_config(store, source, config));
+    axum_test_helper::TestClient::new(app).await
+}
+
 #[cfg(test)]
 async fn test_client() -> (axum_test_helper::TestClient, tempfile::TempDir) {
     let dir = tempfile::TempDir::new().expect("could not create tmp dir for storage");
@@ -278,3 +711,65 @@ async fn test_client() -> (axum_test_helper::TestClient, tempfile::TempDir) {
     let client = test_client_with_store(store).await;
     (client, dir)
 }
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use crate::storage::{mem::MemStore, Store};
+
+    #[tokio::test]
+    async fn test_server_compression() {
+        let client = super::test_client_with_store(Store::Mem(MemStore::new())).await;
+
+        let res = client
+            .get("/")
+            .header("accept-encoding", "gzip")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-encoding"], "gzip");
+
+        let res = client.get("/").header("accept-encoding", "br").send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-encoding"], "br");
+
+        let res = client.get("/").send().await;
+        assert!(!res.headers().contains_key("content-encoding"));
+    }
+
+    #[tokio::test]
+    async fn test_server_request_id() {
+        let client = super::test_client_with_store(Store::Mem(MemStore::new())).await;
+
+        let res = client
+            .get("/api/v1/repo/github/org/repo/stars.json")
+            .header("x-request-id", "abc-123")
+            .send()
+            .await;
+        assert_eq!(res.headers()["x-request-id"], "abc-123");
+
+        let res = client
+            .get("/api/v1/repo/nope/org/repo")
+            .header("x-request-id", "abc-123")
+            .send()
+            .await;
+        assert_eq!(res.headers()["x-request-id"], "abc-123");
+        let data = res.json::<serde_json::Value>().await;
+        assert_eq!(data["error"]["request_id"], "abc-123");
+
+        // Generated if the client does not send one.
+        let res = client.get("/").send().await;
+        let id = res.headers()["x-request-id"].to_str().unwrap();
+        assert_eq!(id.len(), 36);
+    }
+
+    #[tokio::test]
+    async fn test_server_theme_toggle() {
+        let client = super::test_client_with_store(Store::Mem(MemStore::new())).await;
+
+        let body = client.get("/").send().await.text().await;
+        assert!(body.contains("id='theme-toggle'"));
+        assert!(body.contains("localStorage.getItem('theme')"));
+    }
+}
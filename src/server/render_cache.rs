@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use tokio::sync::RwLock;
+
+use crate::source::{FullReadmeRepo, RepoIdent};
+
+/// Maximum number of cached pages per repo.
+///
+/// Queries contain free text (eg: the topic filter), so the number of
+/// variants must be bounded.
+const MAX_PAGES_PER_REPO: usize = 32;
+
+struct RepoPages {
+    /// The repo the pages were rendered from.
+    repo: Arc<FullReadmeRepo>,
+    /// Rendered pages, keyed by query string.
+    pages: HashMap<String, Arc<str>>,
+    /// Value of the access clock at the last access.
+    last_access: AtomicU64,
+}
+
+/// Cache for rendered repo pages.
+///
+/// Entries are tied to the [`FullReadmeRepo`] instance they were rendered
+/// from. The loader returns the same instance until the repo is refreshed,
+/// so pages expire together with the loader cache.
+///
+/// Repos are evicted least-recently-accessed first once `capacity` is
+/// exceeded.
+#[derive(Clone)]
+pub struct RenderCache {
+    repos: Arc<RwLock<HashMap<RepoIdent, RepoPages>>>,
+    capacity: usize,
+    access_clock: Arc<AtomicU64>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl RenderCache {
+    /// Create a cache holding the pages of up to `capacity` repos.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            repos: Arc::new(RwLock::new(HashMap::new())),
+            capacity: capacity.max(1),
+            access_clock: Arc::new(AtomicU64::new(0)),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.access_clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Drop all pages of a repo.
+    pub async fn remove(&self, ident: &RepoIdent) {
+        self.repos.write().await.remove(ident);
+    }
+
+    /// Return the cached page, or render and cache it.
+    pub async fn get_or_render(
+        &self,
+        repo: &Arc<FullReadmeRepo>,
+        query: String,
+        render: impl FnOnce() -> String,
+    ) -> Arc<str> {
+        let ident = &repo.repo.details.ident;
+
+        if let Some(entry) = self.repos.read().await.get(ident) {
+            if Arc::ptr_eq(&entry.repo, repo) {
+                if let Some(page) = entry.pages.get(&query) {
+                    entry.last_access.store(self.tick(), Ordering::Relaxed);
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return page.clone();
+                }
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let page: Arc<str> = render().into();
+
+        let mut repos = self.repos.write().await;
+        let entry = repos.entry(ident.clone()).or_insert_with(|| RepoPages {
+            repo: repo.clone(),
+            pages: HashMap::new(),
+            last_access: AtomicU64::new(0),
+        });
+        entry.last_access.store(self.tick(), Ordering::Relaxed);
+        if !Arc::ptr_eq(&entry.repo, repo) {
+            entry.repo = repo.clone();
+            entry.pages.clear();
+        }
+        if entry.pages.len() < MAX_PAGES_PER_REPO {
+            entry.pages.insert(query, page.clone());
+        }
+        while repos.len() > self.capacity {
+            let oldest = repos
+                .iter()
+                .min_by_key(|(_, x)| x.last_access.load(Ordering::Relaxed))
+                .map(|(ident, _)| ident.clone());
+            if let Some(oldest) = oldest {
+                repos.remove(&oldest);
+            }
+        }
+
+        page
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::{ReadmeRepo, RepoIdent};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_render_cache() {
+        let repo = |name| {
+            Arc::new(FullReadmeRepo {
+                repo: ReadmeRepo::new_test(RepoIdent::new_github("org", name), vec![]),
+                links: Vec::new(),
+                not_found: Vec::new(),
+                rate_limit_reset_at: None,
+            })
+        };
+        let cache = RenderCache::new(10);
+
+        let a = repo("a");
+        let page = cache.get_or_render(&a, "".into(), || "1".into()).await;
+        assert_eq!(&*page, "1");
+        let page = cache.get_or_render(&a, "".into(), || "2".into()).await;
+        assert_eq!(&*page, "1");
+        let page = cache.get_or_render(&a, "?x".into(), || "3".into()).await;
+        assert_eq!(&*page, "3");
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+
+        // A refreshed repo invalidates all pages.
+        let refreshed = repo("a");
+        let page = cache
+            .get_or_render(&refreshed, "".into(), || "4".into())
+            .await;
+        assert_eq!(&*page, "4");
+        let page = cache
+            .get_or_render(&refreshed, "?x".into(), || "5".into())
+            .await;
+        assert_eq!(&*page, "5");
+        assert_eq!((cache.hits(), cache.misses()), (1, 4));
+    }
+
+    #[tokio::test]
+    async fn test_render_cache_lru() {
+        let repo = |name| {
+            Arc::new(FullReadmeRepo {
+                repo: ReadmeRepo::new_test(RepoIdent::new_github("org", name), vec![]),
+                links: Vec::new(),
+                not_found: Vec::new(),
+                rate_limit_reset_at: None,
+            })
+        };
+        let cache = RenderCache::new(2);
+        let (a, b, c) = (repo("a"), repo("b"), repo("c"));
+
+        cache.get_or_render(&a, "".into(), || "a".into()).await;
+        cache.get_or_render(&b, "".into(), || "b".into()).await;
+        // Accessing `a` makes `b` the least recently used repo.
+        cache.get_or_render(&a, "".into(), || "x".into()).await;
+        cache.get_or_render(&c, "".into(), || "c".into()).await;
+        assert_eq!(cache.repos.read().await.len(), 2);
+
+        let page = cache.get_or_render(&a, "".into(), || "x".into()).await;
+        assert_eq!(&*page, "a");
+        let page = cache.get_or_render(&b, "".into(), || "b2".into()).await;
+        assert_eq!(&*page, "b2");
+
+        cache.remove(&b.repo.details.ident).await;
+        let page = cache.get_or_render(&b, "".into(), || "b3".into()).await;
+        assert_eq!(&*page, "b3");
+    }
+}
@@ -0,0 +1,32 @@
+//! Correlation ids for requests.
+//!
+//! Each request gets an `x-request-id`, either from the client or generated,
+//! which is added to the request span, echoed in the response and included in
+//! error responses.
+
+use axum::{extract::Request, middleware::Next, response::Response};
+
+pub(crate) const X_REQUEST_ID: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id of the request currently being handled.
+pub(crate) fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Extract the request id from the headers.
+pub(crate) fn from_request<B>(req: &axum::http::Request<B>) -> &str {
+    req.headers()
+        .get(X_REQUEST_ID)
+        .and_then(|x| x.to_str().ok())
+        .unwrap_or_default()
+}
+
+/// Middleware that makes the request id available via [`current`].
+pub(crate) async fn scope_request_id(req: Request, next: Next) -> Response {
+    let id = from_request(&req).to_string();
+    REQUEST_ID.scope(id, next.run(req)).await
+}
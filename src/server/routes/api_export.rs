@@ -1,14 +1,295 @@
-use axum::{extract::State, Json};
+use std::future;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use time::OffsetDateTime;
 
 use crate::{
-    server::{ApiError, Ctx},
-    storage::{Item, Storage},
+    server::{routes::repo_page::deserialize_empty_as_none, Ctx},
+    storage::{ExportFilter, Item, ItemKind, Storage, EXPORT_FORMAT_VERSION},
 };
 
 pub const PATH_API_EXPORT: &str = "/api/v1/export";
+/// Export as JSON Lines, one item per line.
+pub const PATH_API_EXPORT_JSONL: &str = "/api/v1/export.jsonl";
+
+#[derive(serde::Deserialize, Debug)]
+pub struct ExportQuery {
+    /// Only export items updated at or after this time.
+    ///
+    /// ISO-8601 format, eg: `2024-07-01T00:00:00Z`.
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub updated_since: Option<OffsetDateTime>,
+    /// Only export items of this kind.
+    pub kind: Option<ItemKind>,
+    /// Number of matching items to skip.
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub offset: Option<usize>,
+    /// Maximum number of items to export.
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub limit: Option<usize>,
+}
+
+impl ExportQuery {
+    fn filter(&self) -> ExportFilter {
+        ExportFilter {
+            kind: self.kind,
+            updated_since: self.updated_since,
+            offset: self.offset.unwrap_or_default(),
+            limit: self.limit,
+        }
+    }
+
+    fn is_paginated(&self) -> bool {
+        self.offset.is_some() || self.limit.is_some()
+    }
+}
+
+/// Stream the items matching the query from storage.
+///
+/// A page requested with `offset` or `limit` is loaded at once, in the
+/// stable order of [`Storage::export_slice`]. Otherwise all items are
+/// streamed in storage order.
+///
+/// Errors are logged, since they can only abort the response once it
+/// started.
+fn export_stream(
+    ctx: &Ctx,
+    query: &ExportQuery,
+) -> BoxStream<'static, Result<Item, anyhow::Error>> {
+    let filter = query.filter();
+    let items = if query.is_paginated() {
+        let store = ctx.store.clone();
+        futures::stream::once(async move {
+            let items = store.export_slice(filter).await?;
+            Ok::<_, anyhow::Error>(futures::stream::iter(items.into_iter().map(Ok)))
+        })
+        .try_flatten()
+        .boxed()
+    } else {
+        ctx.store
+            .export_stream()
+            .try_filter(move |item| future::ready(filter.matches(item)))
+            .boxed()
+    };
+    items
+        .inspect_err(|err| tracing::error!("export failed: {:#}", err))
+        .boxed()
+}
+
+/// Export all items as a JSON [`ExportEnvelope`](crate::storage::ExportEnvelope).
+///
+/// The body is streamed, so memory use does not depend on the number of
+/// items.
+pub async fn handler_api_export(
+    State(ctx): State<Ctx>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let items = export_stream(&ctx, &query)
+        .enumerate()
+        .map(|(index, item)| {
+            let mut chunk = if index == 0 { Vec::new() } else { vec![b','] };
+            serde_json::to_writer(&mut chunk, &item?)?;
+            Ok::<_, anyhow::Error>(Bytes::from(chunk))
+        });
+    let start = Bytes::from(format!(r#"{{"version":{EXPORT_FORMAT_VERSION},"items":["#));
+    let body = futures::stream::once(future::ready(Ok(start)))
+        .chain(items)
+        .chain(futures::stream::once(future::ready(Ok(
+            Bytes::from_static(b"]}"),
+        ))));
+
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// Export all items as JSON Lines.
+///
+/// Lines contain bare items, without the envelope.
+pub async fn handler_api_export_jsonl(
+    State(ctx): State<Ctx>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let lines = export_stream(&ctx, &query).map(|item| {
+        let mut line = serde_json::to_vec(&item?)?;
+        line.push(b'\n');
+        Ok::<_, anyhow::Error>(Bytes::from(line))
+    });
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use time::{macros::datetime, Duration};
+
+    use crate::{
+        server::{test_client_with_items, test_client_with_store},
+        source::{RepoDetailsItem, RepoIdent},
+        storage::{mem::MemStore, ExportEnvelope, Store},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_server_api_export_updated_since() {
+        let since = datetime!(2024-07-01 00:00 UTC);
+        let item = |repo: &str, updated_at| {
+            Item::Repo(RepoDetailsItem::NotFound {
+                ident: RepoIdent::new_github("org", repo),
+                updated_at,
+            })
+        };
+        let old = item("old", since - Duration::days(1));
+        let new = item("new", since + Duration::days(1));
+
+        let client = test_client_with_items(vec![old.clone(), new.clone()]).await;
+
+        let items = client
+            .get("/api/v1/export?updated_since=2024-07-01T00:00:00Z")
+            .send()
+            .await
+            .json::<ExportEnvelope>()
+            .await
+            .items;
+        assert_eq!(items, vec![new.clone()]);
+
+        let items = client
+            .get(PATH_API_EXPORT)
+            .send()
+            .await
+            .json::<ExportEnvelope>()
+            .await
+            .items;
+        assert_eq!(items, vec![new, old]);
+
+        let res = client
+            .get("/api/v1/export?updated_since=yesterday")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_server_api_export_empty() {
+        let client = test_client_with_store(Store::Mem(MemStore::new())).await;
+
+        let res = client.get(PATH_API_EXPORT).send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "application/json");
+        assert_eq!(res.text().await, r#"{"version":1,"items":[]}"#);
+
+        let res = client.get(PATH_API_EXPORT_JSONL).send().await;
+        assert_eq!(res.text().await, "");
+    }
+
+    #[tokio::test]
+    async fn test_server_api_export_jsonl() {
+        let item = |repo: &str, updated_at| {
+            Item::Repo(RepoDetailsItem::NotFound {
+                ident: RepoIdent::new_github("org", repo),
+                updated_at,
+            })
+        };
+        let since = datetime!(2024-07-01 00:00 UTC);
+        let old = item("old", since - Duration::days(1));
+        let new = item("new", since + Duration::days(1));
+
+        let client = test_client_with_items(vec![old.clone(), new.clone()]).await;
+
+        let res = client.get(PATH_API_EXPORT_JSONL).send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "application/x-ndjson");
+        let body = res.text().await;
+        assert!(body.ends_with('\n'));
+        let items = body
+            .lines()
+            .map(|line| serde_json::from_str::<Item>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(items, vec![new.clone(), old]);
+
+        let body = client
+            .get("/api/v1/export.jsonl?updated_since=2024-07-01T00:00:00Z")
+            .send()
+            .await
+            .text()
+            .await;
+        assert_eq!(body.lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_server_api_export_kind_and_page() {
+        use crate::source::ReadmeRepo;
+
+        let repos: Vec<_> = ["a", "b", "c"]
+            .into_iter()
+            .map(|repo| {
+                Item::Repo(RepoDetailsItem::NotFound {
+                    ident: RepoIdent::new_github("org", repo),
+                    updated_at: datetime!(2024-07-01 00:00 UTC),
+                })
+            })
+            .collect();
+        let readme = Item::ReadmeRepo(ReadmeRepo::new_test(
+            RepoIdent::new_github("org", "list"),
+            vec![],
+        ));
+        let mut items = repos.clone();
+        items.push(readme.clone());
+
+        let client = test_client_with_items(items).await;
+        let get = |url: &'static str| {
+            let client = &client;
+            async move {
+                client
+                    .get(url)
+                    .send()
+                    .await
+                    .json::<ExportEnvelope>()
+                    .await
+                    .items
+            }
+        };
+
+        assert_eq!(
+            get("/api/v1/export?kind=readme").await,
+            vec![readme.clone()]
+        );
+        assert_eq!(get("/api/v1/export?kind=repo").await.len(), 3);
+
+        assert_eq!(get("/api/v1/export?limit=2").await, repos[..2].to_vec());
+        assert_eq!(
+            get("/api/v1/export?offset=2&limit=2").await,
+            vec![repos[2].clone(), readme.clone()]
+        );
+        assert_eq!(
+            get("/api/v1/export?kind=repo&offset=1&limit=").await,
+            repos[1..].to_vec()
+        );
+        assert_eq!(get("/api/v1/export?offset=10").await, vec![]);
 
-pub async fn handler_api_export(State(ctx): State<Ctx>) -> Result<Json<Vec<Item>>, ApiError> {
-    let items = ctx.store.export().await?;
+        let body = client
+            .get("/api/v1/export.jsonl?kind=repo&limit=1")
+            .send()
+            .await
+            .text()
+            .await;
+        assert_eq!(body.lines().count(), 1);
 
-    Ok(Json(items))
+        let res = client.get("/api/v1/export?kind=other").send().await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
 }
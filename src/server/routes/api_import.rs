@@ -1,21 +1,51 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
 
 use crate::{
-    server::{ApiError, Ctx},
-    storage::{Item, Storage},
+    server::{auth::RequireApiToken, extract::JsonBody, ApiError, Ctx},
+    storage::{ImportItems, Storage},
 };
 
 pub const PATH_API_IMPORT: &str = "/api/v1/import";
 
-#[derive(serde::Serialize)]
-pub struct ImportResult {}
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct ImportQuery {
+    /// Only report what would be imported, without changing storage.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ImportResult {
+    pub inserted: usize,
+    pub skipped: usize,
+    /// `true` if nothing was written.
+    #[serde(default)]
+    pub dry_run: bool,
+}
 
 pub async fn handler_api_import(
     State(ctx): State<Ctx>,
-    input: Json<Vec<Item>>,
+    _auth: RequireApiToken,
+    Query(query): Query<ImportQuery>,
+    input: JsonBody<ImportItems>,
 ) -> Result<Json<ImportResult>, ApiError> {
-    ctx.store.import(input.0).await?;
-    Ok(Json(ImportResult {}))
+    let stats = if query.dry_run {
+        ctx.store.import_dry_run(input.0 .0).await?
+    } else {
+        let stats = ctx.store.import(input.0 .0).await?;
+        if stats.inserted > 0 {
+            ctx.loader.invalidate_popular_repos().await;
+        }
+        stats
+    };
+    Ok(Json(ImportResult {
+        inserted: stats.inserted,
+        skipped: stats.skipped,
+        dry_run: query.dry_run,
+    }))
 }
 
 #[cfg(test)]
@@ -24,8 +54,9 @@ mod tests {
     use time::OffsetDateTime;
 
     use crate::{
-        server::{routes::api_export::PATH_API_EXPORT, test_client},
+        server::{routes::api_export::PATH_API_EXPORT, test_client, test_client_with_store},
         source::{ReadmeRepo, RepoDetails, RepoIdent, RepoLink},
+        storage::{mem::MemStore, ExportEnvelope, Item, Store, EXPORT_FORMAT_VERSION},
     };
 
     use super::*;
@@ -33,7 +64,14 @@ mod tests {
     #[tokio::test]
     async fn test_server_api_import_export() {
         let (client, _dir) = test_client().await;
+        check_import_export(client).await;
 
+        // The in-memory store must behave the same.
+        let client = test_client_with_store(Store::Mem(MemStore::new())).await;
+        check_import_export(client).await;
+    }
+
+    async fn check_import_export(client: axum_test_helper::TestClient) {
         // Export should be empty.
         let res = client.get(PATH_API_EXPORT).send().await;
 
@@ -42,12 +80,12 @@ mod tests {
         dbg!(&body);
 
         assert_eq!(status.as_u16(), 200);
-        let items = serde_json::from_str::<Vec<Item>>(&body).unwrap();
-        assert_eq!(items, vec![]);
+        let export = serde_json::from_str::<ExportEnvelope>(&body).unwrap();
+        assert_eq!(export, ExportEnvelope::new(vec![]));
 
         let now = OffsetDateTime::now_utc();
 
-        // Now import something.
+        // Now import something, as a bare array like older exports.
         let items = vec![
             Item::Repo(crate::source::RepoDetailsItem::NotFound {
                 ident: RepoIdent::parse_ident("github.com/org1/repo1").unwrap(),
@@ -56,6 +94,7 @@ mod tests {
             Item::Repo(crate::source::RepoDetailsItem::Found(
                 crate::source::RepoDetails {
                     ident: RepoIdent::parse_ident("github.com/org2/repo2").unwrap(),
+                    renamed_to: None,
                     description: Some("description".to_string()),
                     last_pushed_at: Some(now),
                     total_pull_requests: 33,
@@ -65,12 +104,18 @@ mod tests {
                     last_pullrequest_merged_at: Some(now),
                     primary_language: Some("rust".to_string()),
                     languages: vec!["Rust".to_string(), "Typescript".to_string()],
+                    language_sizes: Vec::new(),
+                    archived: false,
+                    license: None,
+                    topics: Vec::new(),
+                    first_seen_at: None,
                     updated_at: now,
                 },
             )),
             Item::ReadmeRepo(ReadmeRepo {
                 details: RepoDetails {
                     ident: RepoIdent::parse_ident("github.com/org3/awesome1").unwrap(),
+                    renamed_to: None,
                     description: Some("awesome desc".to_string()),
                     last_pushed_at: Some(now),
                     total_pull_requests: 99,
@@ -80,17 +125,26 @@ mod tests {
                     last_pullrequest_merged_at: Some(now),
                     primary_language: Some("Markdown".to_string()),
                     languages: vec!["Markdown".to_string(), "text".to_string()],
+                    language_sizes: Vec::new(),
+                    archived: false,
+                    license: None,
+                    topics: Vec::new(),
+                    first_seen_at: None,
                     updated_at: now,
                 },
                 readme_content: "readme!".to_string(),
+                readme_etag: Some("\"etag\"".to_string()),
+                readme_location: Default::default(),
                 repo_links: vec![
                     RepoLink {
                         ident: RepoIdent::parse_ident("github.com/org1/repo1").unwrap(),
                         section: vec!["a".to_string(), "b".to_string()],
+                        description: None,
                     },
                     RepoLink {
                         ident: RepoIdent::parse_ident("github.com/org2/repo2").unwrap(),
                         section: vec!["b".to_string(), "c".to_string()],
+                        description: Some("desc".to_string()),
                     },
                 ],
                 updated_at: now,
@@ -99,13 +153,136 @@ mod tests {
 
         let res = client.post(PATH_API_IMPORT).json(&items).send().await;
         assert_eq!(res.status(), StatusCode::OK);
+        let result = res.json::<ImportResult>().await;
+        assert_eq!(result.inserted, 3);
+        assert_eq!(result.skipped, 0);
+
+        let export = client
+            .get(PATH_API_EXPORT)
+            .send()
+            .await
+            .json::<ExportEnvelope>()
+            .await;
+        assert_eq!(export.version, EXPORT_FORMAT_VERSION);
+        pretty_assertions::assert_eq!(export.items, items);
+
+        // Importing the exported envelope again should skip all items.
+        let result = client
+            .post(PATH_API_IMPORT)
+            .json(&export)
+            .send()
+            .await
+            .json::<ImportResult>()
+            .await;
+        assert_eq!(result.inserted, 0);
+        assert_eq!(result.skipped, 3);
+    }
+
+    #[tokio::test]
+    async fn test_server_api_import_dry_run() {
+        let (client, _dir) = test_client().await;
+
+        let existing = Item::Repo(crate::source::RepoDetailsItem::NotFound {
+            ident: RepoIdent::new_github("org", "a"),
+            updated_at: OffsetDateTime::now_utc(),
+        });
+        let res = client.post(PATH_API_IMPORT).json(&[&existing]).send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let new = Item::ReadmeRepo(ReadmeRepo::new_test(
+            RepoIdent::new_github("org", "awesome"),
+            vec![],
+        ));
+        let result = client
+            .post(&format!("{PATH_API_IMPORT}?dry_run=true"))
+            .json(&[&existing, &new])
+            .send()
+            .await
+            .json::<ImportResult>()
+            .await;
+        assert_eq!(result.inserted, 1);
+        assert_eq!(result.skipped, 1);
+        assert!(result.dry_run);
 
-        let items2 = client
+        // Storage is unchanged.
+        let export = client
             .get(PATH_API_EXPORT)
             .send()
             .await
-            .json::<Vec<Item>>()
+            .json::<ExportEnvelope>()
+            .await;
+        assert_eq!(export.items, vec![existing]);
+    }
+
+    #[tokio::test]
+    async fn test_server_api_import_invalid_path() {
+        let client = test_client_with_store(Store::Mem(MemStore::new())).await;
+
+        let valid = Item::Repo(crate::source::RepoDetailsItem::NotFound {
+            ident: RepoIdent::new_github("a", "b"),
+            updated_at: OffsetDateTime::now_utc(),
+        });
+        let body = serde_json::json!([
+            valid,
+            { "Repo": { "Found": { "ident": "not an ident" } } },
+        ]);
+        let res = client
+            .post(PATH_API_IMPORT)
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let data = res.json::<serde_json::Value>().await;
+        assert_eq!(data["error"]["path"], "[1].Repo.Found.ident");
+
+        // Paths in envelopes include the items field.
+        let body = serde_json::json!({ "version": 1, "items": body });
+        let res = client
+            .post(PATH_API_IMPORT)
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await;
+        let data = res.json::<serde_json::Value>().await;
+        assert_eq!(data["error"]["path"], "items[1].Repo.Found.ident");
+    }
+
+    #[tokio::test]
+    async fn test_server_api_import_unsupported_version() {
+        let client = test_client_with_store(Store::Mem(MemStore::new())).await;
+
+        let future = EXPORT_FORMAT_VERSION + 1;
+        for (version, body) in [
+            (0, r#"{"version": 0, "items": []}"#.to_string()),
+            // Items of future versions might not parse.
+            (
+                future,
+                format!(r#"{{"version": {future}, "items": [{{"NewKind": {{}}}}]}}"#),
+            ),
+            (future, format!(r#"{{"items": [], "version": {future}}}"#)),
+        ] {
+            let res = client
+                .post(PATH_API_IMPORT)
+                .header("content-type", "application/json")
+                .body(body)
+                .send()
+                .await;
+            assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+            let data = res.json::<serde_json::Value>().await;
+            let message = data["error"]["message"].as_str().unwrap();
+            assert!(
+                message.contains(&format!("unsupported export format version {version}")),
+                "{message}"
+            );
+        }
+
+        let res = client
+            .post(PATH_API_IMPORT)
+            .header("content-type", "application/json")
+            .body(r#"{"items": []}"#)
+            .send()
             .await;
-        pretty_assertions::assert_eq!(items2, items);
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 }
@@ -0,0 +1,83 @@
+use axum::{extract::State, Json};
+
+use crate::{
+    server::{ApiError, Ctx},
+    source::{ReadmeRepo, RepoIdent},
+    storage::Storage,
+};
+
+pub const PATH_API_LISTS: &str = "/api/v1/lists";
+
+/// Summary of a stored list, without the resolved links.
+#[derive(serde::Serialize, Debug)]
+pub struct ListSummary {
+    pub ident: RepoIdent,
+    pub name: String,
+    pub description: Option<String>,
+    pub stars: u32,
+    pub link_count: usize,
+}
+
+impl ListSummary {
+    fn from_repo(repo: ReadmeRepo) -> Self {
+        Self {
+            name: repo.details.ident.repo.clone(),
+            ident: repo.details.ident,
+            description: repo.details.description,
+            stars: repo.details.stargazer_count,
+            link_count: repo.repo_links.len(),
+        }
+    }
+}
+
+/// All stored lists, most starred first.
+pub async fn handler_api_lists(State(ctx): State<Ctx>) -> Result<Json<Vec<ListSummary>>, ApiError> {
+    let mut lists = ctx
+        .store
+        .readme_repo_list()
+        .await?
+        .into_iter()
+        .map(ListSummary::from_repo)
+        .collect::<Vec<_>>();
+    lists.sort_by_key(|x| std::cmp::Reverse(x.stars));
+
+    Ok(Json(lists))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::{server::test_client_with_items, storage::Item};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_server_api_lists() {
+        let mut small = ReadmeRepo::new_test(RepoIdent::new_github("org", "small"), vec![]);
+        small.details.stargazer_count = 1;
+        let mut big = ReadmeRepo::new_test(
+            RepoIdent::new_github("org", "big"),
+            vec![
+                RepoIdent::new_github("org", "a"),
+                RepoIdent::new_github("org", "b"),
+            ],
+        );
+        big.details.stargazer_count = 100;
+
+        let client =
+            test_client_with_items(vec![Item::ReadmeRepo(small), Item::ReadmeRepo(big)]).await;
+        let res = client.get("/api/v1/lists").send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let data = res.json::<serde_json::Value>().await;
+        let lists = data.as_array().unwrap();
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists[0]["name"], "big");
+        assert_eq!(lists[0]["stars"], 100);
+        assert_eq!(lists[0]["link_count"], 2);
+        assert_eq!(lists[0]["ident"]["owner"], "org");
+        assert!(lists[0].get("links").is_none());
+        assert_eq!(lists[1]["name"], "small");
+    }
+}
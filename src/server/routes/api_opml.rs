@@ -0,0 +1,105 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    server::{repo_page_uri, routes::repo_feed::escape_xml, ApiError, Ctx},
+    source::ReadmeRepo,
+    storage::Storage,
+};
+
+pub const PATH_API_OPML: &str = "/api/v1/opml";
+
+/// OPML document with the Atom feeds of all stored lists.
+pub async fn handler_api_opml(State(ctx): State<Ctx>) -> Result<Response, ApiError> {
+    let repos = ctx.store.readme_repo_list().await?;
+    let opml = render_opml(&repos, &ctx.public_url);
+
+    Ok(([(header::CONTENT_TYPE, "text/x-opml")], opml).into_response())
+}
+
+/// `public_url` is the base of the feed URLs.
+fn render_opml(repos: &[ReadmeRepo], public_url: &str) -> String {
+    let mut out = String::new();
+    out.push_str(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    out.push_str(r#"<opml version="2.0"><head><title>awesomelify lists</title></head><body>"#);
+
+    for repo in repos {
+        let ident = &repo.details.ident;
+        let feed_url = format!("{public_url}{}/feed.xml", repo_page_uri(ident));
+        out.push_str(&format!(
+            r#"<outline type="rss" text="{name}" title="{name}" xmlUrl="{}" htmlUrl="{}"/>"#,
+            escape_xml(&feed_url),
+            escape_xml(&ident.url()),
+            name = escape_xml(&ident.name()),
+        ));
+    }
+
+    out.push_str("</body></opml>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        server::{test_client_with_items, unescaped_text},
+        source::{ReadmeRepo, RepoIdent},
+        storage::{Item, Storage},
+    };
+
+    #[tokio::test]
+    async fn test_server_api_opml() {
+        let client = test_client_with_items(vec![
+            Item::ReadmeRepo(ReadmeRepo::new_test(
+                RepoIdent::new_github("org", "awesome"),
+                vec![],
+            )),
+            Item::ReadmeRepo(ReadmeRepo::new_test(
+                RepoIdent::new_github("other", "awesome-go"),
+                vec![],
+            )),
+        ])
+        .await;
+        let res = client.get("/api/v1/opml").send().await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["content-type"], "text/x-opml");
+
+        let body = res.text().await;
+        assert!(body.starts_with("<?xml"));
+        assert_eq!(body.matches("<outline ").count(), 2);
+        assert!(body.contains(
+            r#"<outline type="rss" text="org/awesome" title="org/awesome" xmlUrl="https://awesomelify.condacity.io/repo/github/org/awesome/feed.xml" htmlUrl="https://github.com/org/awesome"/>"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_server_api_opml_public_url() {
+        let ctx = crate::server::CtxBuilder::new("unused".into())
+            .storage(crate::storage::StorageKind::Memory)
+            .public_url(Some("https://awesome.example.com/".to_string()))
+            .background_tasks(false)
+            .build()
+            .unwrap();
+        ctx.store
+            .import(vec![Item::ReadmeRepo(ReadmeRepo::new_test(
+                RepoIdent::new_github("org", "awesome"),
+                vec![],
+            ))])
+            .await
+            .unwrap();
+        let client = axum_test_helper::TestClient::new(crate::server::build_router(ctx)).await;
+
+        let body = client.get("/api/v1/opml").send().await.text().await;
+        assert!(
+            body.contains(
+                r#"xmlUrl="https://awesome.example.com/repo/github/org/awesome/feed.xml""#
+            ),
+            "{body}"
+        );
+
+        let body = unescaped_text(client.get("/").send().await).await;
+        assert!(body.contains("<meta property='og:url' content='https://awesome.example.com'>"));
+    }
+}
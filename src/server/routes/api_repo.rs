@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+
+use crate::{
+    server::{auth::RequireApiToken, ApiError, Ctx},
+    source::{FullReadmeRepo, NotAwesomeListError, ReadmeLocation, RepoIdent, SourceError},
+    storage::{StarSnapshot, Storage},
+};
+
+pub const PATH_API_REPO: &str = "/api/v1/repo/:source/:owner/:repo";
+pub const PATH_API_REPO_STARS: &str = "/api/v1/repo/:source/:owner/:repo/stars.json";
+
+pub async fn handler_api_repo(
+    State(ctx): State<Ctx>,
+    Path(path): Path<(String, String, String)>,
+) -> Result<Json<Arc<FullReadmeRepo>>, ApiError> {
+    let ident = ctx.ident_from_path(path)?;
+    load_list(&ctx, ident, None, false).await.map(Json)
+}
+
+/// Load an awesome list, failing with a 404 if the README has no repo links.
+///
+/// The README is loaded from `location` if given. The location of a stored
+/// list is only changed if `replace_location` is set.
+pub(super) async fn load_list(
+    ctx: &Ctx,
+    ident: RepoIdent,
+    location: Option<ReadmeLocation>,
+    replace_location: bool,
+) -> Result<Arc<FullReadmeRepo>, ApiError> {
+    let res = match location {
+        Some(location) => {
+            ctx.loader
+                .load_full_readme_repo_from(ident.clone(), location, replace_location)
+                .await
+        }
+        None => ctx.loader.load_full_readme_repo(ident.clone(), true).await,
+    };
+    let repo = match res {
+        Ok(repo) => repo,
+        Err(err) => match SourceError::find(&err) {
+            Some(SourceError::NotAwesomeList(_) | SourceError::NotFound(_)) => {
+                return Err(ApiError::msg(err.to_string(), StatusCode::NOT_FOUND));
+            }
+            _ => return Err(err.into()),
+        },
+    };
+
+    if repo.repo.repo_links.is_empty() {
+        return Err(ApiError::msg(
+            NotAwesomeListError { ident }.to_string(),
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    Ok(repo)
+}
+
+/// Star count history of a repo, oldest first.
+pub async fn handler_api_repo_stars(
+    State(ctx): State<Ctx>,
+    Path(path): Path<(String, String, String)>,
+) -> Result<Json<Vec<StarSnapshot>>, ApiError> {
+    let ident = ctx.ident_from_path(path)?;
+    let history = ctx.store.star_history(ident).await?;
+    Ok(Json(history))
+}
+
+/// Delete all stored data for a repo, including its star history.
+pub async fn handler_api_repo_delete(
+    State(ctx): State<Ctx>,
+    _auth: RequireApiToken,
+    Path(path): Path<(String, String, String)>,
+) -> Result<StatusCode, ApiError> {
+    let ident = ctx.ident_from_path(path)?;
+
+    ctx.store.readme_repo_delete(ident.clone()).await?;
+    ctx.store.repo_details_delete(ident.clone()).await?;
+    ctx.store.star_history_delete(ident.clone()).await?;
+    ctx.invalidate(&ident).await;
+    // The list may be shown on the homepage.
+    ctx.loader.invalidate_popular_repos().await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        server::{test_client_with_items, test_client_with_store, unescaped_text},
+        source::{ReadmeRepo, RepoDetails, RepoDetailsItem, RepoIdent},
+        storage::{mem::MemStore, Item, Store},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_server_api_repo() {
+        let list = RepoIdent::new_github("org", "awesome");
+        let empty = RepoIdent::new_github("org", "empty");
+        let linked = RepoIdent::new_github("org", "repo");
+
+        let client = test_client_with_items(vec![
+            Item::ReadmeRepo(ReadmeRepo::new_test(list.clone(), vec![linked.clone()])),
+            Item::ReadmeRepo(ReadmeRepo::new_test(empty, vec![])),
+            Item::Repo(RepoDetailsItem::Found(RepoDetails::new_test(
+                linked.clone(),
+            ))),
+        ])
+        .await;
+
+        let res = client.get("/api/v1/repo/github/org/awesome").send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let data = res.json::<serde_json::Value>().await;
+        assert_eq!(data["links"][0]["link"]["ident"]["repo"], "repo");
+        assert_eq!(data["not_found"], serde_json::json!([]));
+
+        let res = client.get("/api/v1/repo/github/org/empty").send().await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_server_api_repo_delete() {
+        let store = Store::Mem(MemStore::new());
+
+        let list = RepoIdent::new_github("org", "awesome");
+        let linked = RepoIdent::new_github("org", "repo");
+        store
+            .import(vec![
+                Item::ReadmeRepo(ReadmeRepo::new_test(list.clone(), vec![linked.clone()])),
+                Item::Repo(RepoDetailsItem::Found(RepoDetails::new_test(
+                    linked.clone(),
+                ))),
+            ])
+            .await
+            .unwrap();
+
+        store
+            .record_star_snapshot(list.clone(), 5, time::OffsetDateTime::now_utc())
+            .await
+            .unwrap();
+
+        let client = test_client_with_store(store.clone()).await;
+
+        // Cache the list for the homepage.
+        let homepage = || async { unescaped_text(client.get("/").send().await).await };
+        assert!(homepage().await.contains("/repo/github/org/awesome"));
+
+        let res = client
+            .delete("/api/v1/repo/github/org/awesome")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(store.readme_repo(list.clone()).await.unwrap(), None);
+        assert_eq!(store.star_history(list).await.unwrap(), Vec::new());
+        assert!(!homepage().await.contains("/repo/github/org/awesome"));
+        // Other repos are untouched.
+        assert!(store.repo_details(linked.clone()).await.unwrap().is_some());
+
+        let res = client.delete("/api/v1/repo/github/org/repo").send().await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(store.repo_details(linked).await.unwrap(), None);
+
+        // Deleting missing repos succeeds.
+        let res = client.delete("/api/v1/repo/github/org/repo").send().await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_server_api_repo_stars() {
+        let store = Store::Mem(MemStore::new());
+        let ident = RepoIdent::new_github("org", "repo");
+        let at = time::macros::datetime!(2024-07-01 00:00 UTC);
+        store
+            .record_star_snapshot(ident.clone(), 5, at)
+            .await
+            .unwrap();
+        store
+            .record_star_snapshot(ident, 7, at + time::Duration::days(1))
+            .await
+            .unwrap();
+
+        let client = test_client_with_store(store).await;
+        let res = client
+            .get("/api/v1/repo/github/org/repo/stars.json")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let data = res.json::<serde_json::Value>().await;
+        assert_eq!(
+            data,
+            serde_json::json!([
+                { "at": "2024-07-01T00:00:00Z", "stars": 5 },
+                { "at": "2024-07-02T00:00:00Z", "stars": 7 },
+            ])
+        );
+    }
+}
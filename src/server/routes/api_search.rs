@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+
+use crate::{
+    server::{auth::RequireApiToken, routes::search::SearchQuery, ApiError, Ctx},
+    source::FullReadmeRepo,
+};
+
+pub const PATH_API_SEARCH: &str = "/api/v1/search";
+
+/// Resolve a repo URL or ident, loading the list if it is not known yet.
+///
+/// JSON counterpart of the HTML search.
+pub async fn handler_api_search(
+    State(ctx): State<Ctx>,
+    auth: Option<RequireApiToken>,
+    Query(q): Query<SearchQuery>,
+) -> Result<Json<Arc<FullReadmeRepo>>, ApiError> {
+    let ident = ctx.parse_ident(q.q.trim()).map_err(|err| {
+        ApiError::msg(
+            format!("Invalid url '{}': {}", q.q, err),
+            StatusCode::BAD_REQUEST,
+        )
+    })?;
+
+    let location = q
+        .readme_location()
+        .map_err(|err| ApiError::msg(err.to_string(), StatusCode::BAD_REQUEST))?;
+    super::api_repo::load_list(&ctx, ident, location, auth.is_some())
+        .await
+        .map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        server::test_client_with_items,
+        source::{ReadmeRepo, RepoDetails, RepoDetailsItem, RepoIdent},
+        storage::{mem::MemStore, Item, Storage, Store},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_server_api_search() {
+        let linked = RepoIdent::new_github("org", "repo");
+
+        let client = test_client_with_items(vec![
+            Item::ReadmeRepo(ReadmeRepo::new_test(
+                RepoIdent::new_github("org", "awesome"),
+                vec![linked.clone()],
+            )),
+            Item::ReadmeRepo(ReadmeRepo::new_test(
+                RepoIdent::new_github("org", "empty"),
+                vec![],
+            )),
+            Item::Repo(RepoDetailsItem::Found(RepoDetails::new_test(linked))),
+        ])
+        .await;
+
+        let res = client
+            .get("/api/v1/search?q=https://github.com/org/awesome")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let data = res.json::<serde_json::Value>().await;
+        assert_eq!(data["links"][0]["link"]["ident"]["repo"], "repo");
+
+        let res = client.get("/api/v1/search?q=not%20a%20url").send().await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let data = res.json::<serde_json::Value>().await;
+        assert!(data["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid url"));
+
+        let res = client.get("/api/v1/search?q=org/empty").send().await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_server_api_search_readme_location() {
+        use crate::{
+            server::{build_router, Ctx},
+            source::{github::mock::MockGithub, loader::SourceLoader},
+        };
+
+        let github = MockGithub::start("- [a](https://github.com/org/a)\n").await;
+        let store = Store::Mem(MemStore::new());
+        let mut ctx = Ctx::with_source(store.clone(), SourceLoader::new(github.client()));
+        ctx.api_token = Some("secret".into());
+        let client = axum_test_helper::TestClient::new(build_router(ctx)).await;
+
+        let res = client
+            .get("/api/v1/search?q=org/awesome&readme_path=../../user")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        // New lists can be added with any location.
+        let res = client
+            .get("/api/v1/search?q=org/awesome&readme_path=docs/README.md")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Changing the location of a stored list requires the API token.
+        let path = "/api/v1/search?q=org/awesome&readme_path=other/README.md";
+        let res = client.get(path).send().await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let stored = store
+            .readme_repo(RepoIdent::new_github("org", "awesome"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            stored.readme_location.path.as_deref(),
+            Some("docs/README.md")
+        );
+
+        let res = client
+            .get(path)
+            .header("authorization", "Bearer secret")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let data = res.json::<serde_json::Value>().await;
+        assert_eq!(data["repo"]["readme_location"]["path"], "other/README.md");
+    }
+}
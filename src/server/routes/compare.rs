@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Html,
+};
+
+use crate::{
+    server::{ui, Ctx, HtmlError},
+    source::FullReadmeRepo,
+};
+
+pub const PATH_COMPARE: &str = "/compare";
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct CompareQuery {
+    pub a: String,
+    pub b: String,
+}
+
+/// Compare the links of two lists.
+pub async fn handler_compare(
+    State(ctx): State<Ctx>,
+    Query(query): Query<CompareQuery>,
+) -> Html<String> {
+    let (a, b) = futures::join!(load_side(&ctx, &query.a), load_side(&ctx, &query.b));
+    Html(ui::render_compare_page(a, b))
+}
+
+/// Load one side of the comparison.
+///
+/// Errors are returned instead of failing the whole page, so the other side
+/// can still be shown.
+async fn load_side(ctx: &Ctx, query: &str) -> Result<Arc<FullReadmeRepo>, HtmlError> {
+    let ident = ctx.parse_ident(query.trim()).map_err(|err| {
+        HtmlError::msg(
+            format!("Invalid url '{}': {}", query, err),
+            StatusCode::BAD_REQUEST,
+        )
+    })?;
+
+    Ok(ctx.loader.load_full_readme_repo(ident, true).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use crate::{
+        server::{test_client_with_items, unescaped_text},
+        source::{ReadmeRepo, RepoDetails, RepoDetailsItem, RepoIdent},
+        storage::Item,
+    };
+
+    #[tokio::test]
+    async fn test_server_compare() {
+        let a = RepoIdent::new_github("org", "only-a");
+        let b = RepoIdent::new_github("org", "only-b");
+        let both = RepoIdent::new_github("org", "shared");
+
+        let client = test_client_with_items(vec![
+            Item::ReadmeRepo(ReadmeRepo::new_test(
+                RepoIdent::new_github("org", "list-a"),
+                vec![a.clone(), both.clone()],
+            )),
+            Item::ReadmeRepo(ReadmeRepo::new_test(
+                RepoIdent::new_github("org", "list-b"),
+                vec![b.clone(), both.clone()],
+            )),
+            Item::Repo(RepoDetailsItem::Found(RepoDetails::new_test(a))),
+            Item::Repo(RepoDetailsItem::Found(RepoDetails::new_test(b))),
+            Item::Repo(RepoDetailsItem::Found(RepoDetails::new_test(both))),
+        ])
+        .await;
+
+        let res = client
+            .get("/compare?a=org/list-a&b=org/list-b")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let html = unescaped_text(res).await;
+        let column = |id: &str| {
+            let start = html.find(&format!("id='{id}'")).unwrap();
+            html[start..].split("</table>").next().unwrap().to_string()
+        };
+        assert!(column("only-a").contains("org/only-a"));
+        assert!(!column("only-a").contains("org/shared"));
+        assert!(column("only-b").contains("org/only-b"));
+        assert!(column("in-both").contains("org/shared"));
+
+        // A failing side does not break the page.
+        let res = client.get("/compare?a=org/list-a&b=nope").send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let html = unescaped_text(res).await;
+        assert!(html.contains("Invalid url"));
+        assert!(html.contains("org/list-a"));
+        assert!(!html.contains("id='only-a'"));
+    }
+}
@@ -0,0 +1,81 @@
+use axum::{extract::State, http::StatusCode, Json};
+
+use crate::{
+    server::{ApiError, Ctx},
+    storage::Storage,
+};
+
+pub const PATH_HEALTH: &str = "/health";
+pub const PATH_HEALTH_READY: &str = "/health/ready";
+
+#[derive(serde::Serialize)]
+pub struct HealthStatus {
+    status: &'static str,
+}
+
+/// Liveness check.
+///
+/// Does not touch storage or any source, so it stays fast under load.
+pub async fn handler_health() -> Json<HealthStatus> {
+    Json(HealthStatus { status: "ok" })
+}
+
+/// Readiness check.
+///
+/// Verifies that the storage is writable, or readable in readonly mode.
+pub async fn handler_health_ready(State(ctx): State<Ctx>) -> Result<Json<HealthStatus>, ApiError> {
+    ctx.store.check_ready().await.map_err(|err| {
+        tracing::error!("readiness check failed: {:#}", err);
+        ApiError::msg(
+            format!("storage is not ready: {err:#}"),
+            StatusCode::SERVICE_UNAVAILABLE,
+        )
+    })?;
+
+    Ok(Json(HealthStatus { status: "ok" }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::test_client;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_server_health() {
+        let (client, _dir) = test_client().await;
+
+        for path in [PATH_HEALTH, PATH_HEALTH_READY] {
+            let res = client.get(path).send().await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let data = res.json::<serde_json::Value>().await;
+            assert_eq!(data, serde_json::json!({ "status": "ok" }));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_health_readonly_data_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        std::fs::create_dir(&root).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&root, std::fs::Permissions::from_mode(0o555)).unwrap();
+        }
+
+        let ctx = crate::server::CtxBuilder::new(root.clone())
+            .readonly(true)
+            .build()
+            .unwrap();
+        let client = axum_test_helper::TestClient::new(crate::server::build_router(ctx)).await;
+
+        let res = client.get(PATH_HEALTH_READY).send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = client.get("/").send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Nothing was written, even where permissions are not enforced.
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), 0);
+    }
+}
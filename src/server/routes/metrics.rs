@@ -0,0 +1,30 @@
+use axum::{extract::State, http::header, response::IntoResponse};
+
+use crate::server::Ctx;
+
+pub const PATH_METRICS: &str = "/metrics";
+
+/// Metrics in the Prometheus text format.
+pub async fn handler_metrics(State(ctx): State<Ctx>) -> impl IntoResponse {
+    let cache = &ctx.render_cache;
+    let body = format!(
+        "# HELP awesomelify_render_cache_hits_total Repo pages served from the render cache.\n\
+         # TYPE awesomelify_render_cache_hits_total counter\n\
+         awesomelify_render_cache_hits_total {}\n\
+         # HELP awesomelify_render_cache_misses_total Repo pages that had to be rendered.\n\
+         # TYPE awesomelify_render_cache_misses_total counter\n\
+         awesomelify_render_cache_misses_total {}\n\
+         # HELP awesomelify_popular_cache_hits_total Popular list lookups served from the cache.\n\
+         # TYPE awesomelify_popular_cache_hits_total counter\n\
+         awesomelify_popular_cache_hits_total {}\n\
+         # HELP awesomelify_popular_cache_misses_total Popular list lookups that had to be computed.\n\
+         # TYPE awesomelify_popular_cache_misses_total counter\n\
+         awesomelify_popular_cache_misses_total {}\n",
+        cache.hits(),
+        cache.misses(),
+        ctx.loader.popular_cache_hits(),
+        ctx.loader.popular_cache_misses(),
+    );
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
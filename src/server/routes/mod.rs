@@ -1,7 +1,22 @@
+pub mod compare;
+pub mod health;
 pub mod homepage;
+pub mod metrics;
+pub mod not_found;
+pub mod repo_badge;
+pub mod repo_broken;
+pub mod repo_export_csv;
+pub mod repo_export_md;
+pub mod repo_feed;
 pub mod repo_list;
 pub mod repo_page;
+pub mod repo_refresh;
 pub mod search;
+pub mod static_files;
 
 pub mod api_export;
 pub mod api_import;
+pub mod api_lists;
+pub mod api_opml;
+pub mod api_repo;
+pub mod api_search;
@@ -0,0 +1,51 @@
+use axum::{
+    http::{StatusCode, Uri},
+    response::{Html, IntoResponse, Response},
+};
+
+use crate::server::{ui, ApiError};
+
+/// Fallback for unknown paths.
+///
+/// API clients get a JSON error instead of the HTML page.
+pub async fn handler_not_found(uri: Uri) -> Response {
+    if uri.path().starts_with("/api/") {
+        return ApiError::msg(
+            format!("no route for '{}'", uri.path()),
+            StatusCode::NOT_FOUND,
+        )
+        .into_response();
+    }
+    (StatusCode::NOT_FOUND, Html(ui::render_not_found_page())).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::storage::{mem::MemStore, Store};
+
+    #[tokio::test]
+    async fn test_server_not_found() {
+        let client = crate::server::test_client_with_store(Store::Mem(MemStore::new())).await;
+
+        let res = client.get("/does/not/exist").send().await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+        let html = res.text().await;
+        assert!(html.contains("Page not found"));
+        // The search bar lets users recover.
+        assert!(html.contains("search-results"));
+
+        let res = client.get("/api/v1/does-not-exist").send().await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let data = res.json::<serde_json::Value>().await;
+        assert_eq!(
+            data["error"]["message"],
+            "no route for '/api/v1/does-not-exist'"
+        );
+    }
+}
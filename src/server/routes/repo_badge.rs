@@ -0,0 +1,139 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    server::{ApiError, Ctx},
+    source::FullReadmeRepo,
+};
+
+use super::repo_feed::escape_xml;
+
+pub const PATH_REPO_BADGE: &str = "/repo/:source/:owner/:repo/badge.svg";
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeMetric {
+    /// Stars of the list itself.
+    Stars,
+    /// Number of linked repos.
+    #[default]
+    Repos,
+    /// Number of linked repos that could not be found.
+    NotFound,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct BadgeQuery {
+    /// Text of the left side of the badge.
+    pub label: Option<String>,
+    #[serde(default)]
+    pub metric: BadgeMetric,
+}
+
+pub async fn handler_repo_badge(
+    State(ctx): State<Ctx>,
+    Path(path): Path<(String, String, String)>,
+    Query(query): Query<BadgeQuery>,
+) -> Result<Response, ApiError> {
+    let ident = ctx.ident_from_path(path)?;
+    let repo = ctx.loader.load_full_readme_repo(ident, true).await?;
+
+    let label = query.label.as_deref().unwrap_or("awesomelify");
+    let svg = render_badge(label, &badge_value(&repo, query.metric));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        svg,
+    )
+        .into_response())
+}
+
+fn badge_value(repo: &FullReadmeRepo, metric: BadgeMetric) -> String {
+    match metric {
+        BadgeMetric::Stars => format!("{} stars", repo.repo.details.stargazer_count),
+        BadgeMetric::Repos => format!("{} repos", repo.unique_link_count()),
+        BadgeMetric::NotFound => format!("{} not found", repo.not_found.len()),
+    }
+}
+
+/// Approximate rendered width of a text in the badge font.
+fn text_width(text: &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+/// Render a shields.io style badge.
+fn render_badge(label: &str, value: &str) -> String {
+    let label_width = text_width(label);
+    let value_width = text_width(value);
+    let width = label_width + value_width;
+    let label_x = label_width / 2;
+    let value_x = label_width + value_width / 2;
+    let label = escape_xml(label);
+    let value = escape_xml(value);
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {value}"><title>{label}: {value}</title><linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient><clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath><g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{value_width}" height="20" fill="#4c1"/><rect width="{width}" height="20" fill="url(#s)"/></g><g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{label_x}" y="14">{label}</text><text x="{value_x}" y="14">{value}</text></g></svg>"##
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use crate::{
+        server::test_client_with_items,
+        source::{ReadmeRepo, RepoDetails, RepoDetailsItem, RepoIdent},
+        storage::Item,
+    };
+
+    #[tokio::test]
+    async fn test_server_repo_badge() {
+        let list = RepoIdent::new_github("org", "awesome");
+        let a = RepoIdent::new_github("org", "a");
+        let b = RepoIdent::new_github("org", "b");
+
+        let client = test_client_with_items(vec![
+            Item::ReadmeRepo(ReadmeRepo::new_test(list, vec![a.clone(), b.clone()])),
+            Item::Repo(RepoDetailsItem::Found(RepoDetails::new_test(a))),
+            Item::Repo(RepoDetailsItem::NotFound {
+                ident: b,
+                updated_at: time::OffsetDateTime::now_utc(),
+            }),
+        ])
+        .await;
+
+        let res = client
+            .get("/repo/github/org/awesome/badge.svg")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "image/svg+xml");
+        assert!(res.headers().contains_key("cache-control"));
+        let body = res.text().await;
+        assert!(body.contains(">awesomelify</text>"));
+        assert!(body.contains(">1 repos</text>"));
+
+        let body = client
+            .get("/repo/github/org/awesome/badge.svg?label=a%26b&metric=notfound")
+            .send()
+            .await
+            .text()
+            .await;
+        assert!(body.contains(">a&amp;b</text>"));
+        assert!(body.contains(">1 not found</text>"));
+
+        let body = client
+            .get("/repo/github/org/awesome/badge.svg?metric=stars")
+            .send()
+            .await
+            .text()
+            .await;
+        assert!(body.contains(">10 stars</text>"));
+    }
+}
@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Path, State},
+    response::Html,
+};
+
+use crate::server::{ui, Ctx, HtmlErrorPage};
+
+pub const PATH_REPO_BROKEN: &str = "/repo/:source/:owner/:repo/broken";
+
+/// Report of links to repos that were not found or are archived.
+pub async fn handler_repo_broken(
+    State(ctx): State<Ctx>,
+    Path(path): Path<(String, String, String)>,
+) -> Result<Html<String>, HtmlErrorPage> {
+    let ident = ctx.ident_from_path(path)?;
+    let repo = ctx.loader.load_full_readme_repo(ident, true).await?;
+
+    Ok(Html(ui::render_broken_links_page(&repo)))
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use crate::{
+        server::{test_client_with_items, unescaped_text},
+        source::{ReadmeRepo, RepoDetails, RepoDetailsItem, RepoIdent},
+        storage::Item,
+    };
+
+    #[tokio::test]
+    async fn test_server_repo_broken() {
+        let list = RepoIdent::new_github("org", "awesome");
+        let ok = RepoIdent::new_github("org", "ok");
+        let archived = RepoIdent::new_github("org", "archived");
+        let gone = RepoIdent::new_github("org", "gone");
+
+        let client = test_client_with_items(vec![
+            Item::ReadmeRepo(ReadmeRepo::new_test(
+                list,
+                vec![ok.clone(), archived.clone(), gone.clone()],
+            )),
+            Item::Repo(RepoDetailsItem::Found(RepoDetails::new_test(ok))),
+            Item::Repo(RepoDetailsItem::Found(RepoDetails {
+                archived: true,
+                ..RepoDetails::new_test(archived)
+            })),
+            Item::Repo(RepoDetailsItem::NotFound {
+                ident: gone,
+                updated_at: time::OffsetDateTime::now_utc(),
+            }),
+        ])
+        .await;
+        let res = client.get("/repo/github/org/awesome/broken").send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Undo HTML escaping of slashes.
+        let body = unescaped_text(res).await;
+        assert!(
+            body.contains("- [org/archived](https://github.com/org/archived) (archived) - section")
+        );
+        assert!(body.contains("- [org/gone](https://github.com/org/gone) (not found) - section"));
+        assert!(!body.contains("org/ok"));
+    }
+}
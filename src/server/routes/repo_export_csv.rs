@@ -0,0 +1,109 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    server::{routes::repo_page::RepoPageQuery, ui, ApiError, Ctx},
+    source::FullRepoLink,
+};
+
+pub const PATH_REPO_EXPORT_CSV: &str = "/repo/:source/:owner/:repo/export.csv";
+
+/// Export the links of a list as CSV, with the filters and sort of the repo
+/// page applied.
+pub async fn handler_repo_export_csv(
+    State(ctx): State<Ctx>,
+    Path(path): Path<(String, String, String)>,
+    Query(query): Query<RepoPageQuery>,
+) -> Result<Response, ApiError> {
+    let ident = ctx.ident_from_path(path)?;
+    let repo = ctx.loader.load_full_readme_repo(ident, true).await?;
+
+    let links = ui::filtered_links((*repo).clone(), &query);
+    let csv = render_csv(&links)?;
+
+    Ok(([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], csv).into_response())
+}
+
+fn render_csv(links: &[FullRepoLink]) -> Result<Vec<u8>, anyhow::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "owner",
+        "repo",
+        "url",
+        "description",
+        "stars",
+        "forks",
+        "issues",
+        "language",
+        "last_activity",
+        "section",
+    ])?;
+
+    for link in links {
+        let ident = &link.link.ident;
+        let details = &link.details;
+        writer.write_record([
+            ident.owner.as_str(),
+            ident.repo.as_str(),
+            &ident.url(),
+            link.description().unwrap_or_default(),
+            &details.stargazer_count.to_string(),
+            &details.fork_count.to_string(),
+            &details.issues.to_string(),
+            details.primary_language.as_deref().unwrap_or_default(),
+            &details.last_activity_exact().unwrap_or_default(),
+            &link.link.section.join(" > "),
+        ])?;
+    }
+
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::{
+        server::test_client_with_items,
+        source::{ReadmeRepo, RepoDetails, RepoDetailsItem, RepoIdent},
+        storage::Item,
+    };
+
+    #[tokio::test]
+    async fn test_server_repo_export_csv() {
+        let linked = RepoIdent::new_github("org", "repo");
+        let mut details = RepoDetails::new_test(linked.clone());
+        details.description = Some("Fast, \"simple\" tool".to_string());
+        details.last_pushed_at = None;
+        details.last_pullrequest_merged_at = None;
+
+        let client = test_client_with_items(vec![
+            Item::ReadmeRepo(ReadmeRepo::new_test(
+                RepoIdent::new_github("org", "awesome"),
+                vec![linked],
+            )),
+            Item::Repo(RepoDetailsItem::Found(details)),
+        ])
+        .await;
+        let res = client
+            .get("/repo/github/org/awesome/export.csv")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "text/csv; charset=utf-8");
+        assert_eq!(
+            res.text().await,
+            "owner,repo,url,description,stars,forks,issues,language,last_activity,section\n\
+             org,repo,https://github.com/org/repo,\"Fast, \"\"simple\"\" tool\",10,2,3,Rust,,section\n"
+        );
+
+        let res = client
+            .get("/repo/github/org/awesome/export.csv?lang=go")
+            .send()
+            .await;
+        assert_eq!(res.text().await.lines().count(), 1);
+    }
+}
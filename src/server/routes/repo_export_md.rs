@@ -0,0 +1,71 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+
+use crate::server::{routes::repo_page::RepoPageQuery, ui, ApiError, Ctx};
+
+pub const PATH_REPO_EXPORT_MD: &str = "/repo/:source/:owner/:repo/export.md";
+
+/// Export the links of a list as markdown, with the filters and sort of the
+/// repo page applied.
+pub async fn handler_repo_export_md(
+    State(ctx): State<Ctx>,
+    Path(path): Path<(String, String, String)>,
+    Query(query): Query<RepoPageQuery>,
+) -> Result<Response, ApiError> {
+    let ident = ctx.ident_from_path(path)?;
+    let repo = ctx.loader.load_full_readme_repo(ident, true).await?;
+
+    let markdown = ui::render_repo_markdown((*repo).clone(), &query);
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+        markdown,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::{
+        server::test_client_with_items,
+        source::{ReadmeRepo, RepoDetails, RepoDetailsItem, RepoIdent},
+        storage::Item,
+    };
+
+    #[tokio::test]
+    async fn test_server_repo_export_md() {
+        let small = RepoIdent::new_github("org", "small");
+        let big = RepoIdent::new_github("org", "big");
+        let mut big_details = RepoDetails::new_test(big.clone());
+        big_details.stargazer_count = 500;
+
+        let client = test_client_with_items(vec![
+            Item::ReadmeRepo(ReadmeRepo::new_test(
+                RepoIdent::new_github("org", "awesome"),
+                vec![small.clone(), big],
+            )),
+            Item::Repo(RepoDetailsItem::Found(RepoDetails::new_test(small))),
+            Item::Repo(RepoDetailsItem::Found(big_details)),
+        ])
+        .await;
+        let res = client
+            .get("/repo/github/org/awesome/export.md?min_stars=100")
+            .send()
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()["content-type"],
+            "text/markdown; charset=utf-8"
+        );
+        assert_eq!(
+            res.text().await,
+            "# org/awesome\n\n## section\n\n\
+             - [org/big](https://github.com/org/big) — description (⭐500)\n"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{
+    server::{ApiError, Ctx},
+    source::{FullReadmeRepo, FullRepoLink},
+};
+
+pub const PATH_REPO_FEED: &str = "/repo/:source/:owner/:repo/feed.xml";
+
+/// Maximum number of entries in a feed.
+const FEED_ENTRY_LIMIT: usize = 100;
+
+pub async fn handler_repo_feed(
+    State(ctx): State<Ctx>,
+    Path(path): Path<(String, String, String)>,
+) -> Result<Response, ApiError> {
+    let ident = ctx.ident_from_path(path)?;
+    let repo = ctx.loader.load_full_readme_repo(ident, true).await?;
+
+    let xml = render_atom_feed(&repo)?;
+
+    Ok(([(header::CONTENT_TYPE, "application/atom+xml")], xml).into_response())
+}
+
+/// Render an Atom feed of the linked repos, most recently active first.
+fn render_atom_feed(repo: &FullReadmeRepo) -> Result<String, anyhow::Error> {
+    let mut seen = HashSet::new();
+    let mut links: Vec<&FullRepoLink> = repo
+        .links
+        .iter()
+        .filter(|link| seen.insert(&link.link.ident))
+        .collect();
+    links.sort_by(|a, b| b.details.last_activity().cmp(&a.details.last_activity()));
+    links.truncate(FEED_ENTRY_LIMIT);
+
+    let details = &repo.repo.details;
+    let feed_updated = links
+        .iter()
+        .filter_map(|link| link.details.last_activity().copied())
+        .max()
+        .unwrap_or(repo.repo.updated_at);
+
+    let mut out = String::new();
+    out.push_str(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    out.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    out.push_str(&format!(
+        "<id>{}</id><title>{}</title><link href=\"{}\"/><updated>{}</updated>",
+        escape_xml(&details.ident.url()),
+        escape_xml(&details.ident.name()),
+        escape_xml(&details.ident.url()),
+        format_time(feed_updated)?,
+    ));
+    if let Some(desc) = &details.description {
+        out.push_str(&format!("<subtitle>{}</subtitle>", escape_xml(desc)));
+    }
+
+    for link in links {
+        let url = link.link.ident.url();
+        let updated = link
+            .details
+            .last_activity()
+            .copied()
+            .unwrap_or(link.details.updated_at);
+
+        out.push_str("<entry>");
+        out.push_str(&format!(
+            "<id>{}</id><title>{}</title><link href=\"{}\"/><updated>{}</updated>",
+            escape_xml(&url),
+            escape_xml(&link.link.ident.name()),
+            escape_xml(&url),
+            format_time(updated)?,
+        ));
+        if let Some(desc) = link.description() {
+            out.push_str(&format!("<summary>{}</summary>", escape_xml(desc)));
+        }
+        out.push_str("</entry>");
+    }
+
+    out.push_str("</feed>");
+    Ok(out)
+}
+
+fn format_time(time: OffsetDateTime) -> Result<String, anyhow::Error> {
+    Ok(time.format(&Rfc3339)?)
+}
+
+pub(crate) fn escape_xml(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        server::test_client_with_items,
+        source::{ReadmeRepo, RepoDetails, RepoDetailsItem, RepoIdent},
+        storage::Item,
+    };
+
+    #[tokio::test]
+    async fn test_server_repo_feed() {
+        let list = RepoIdent::new_github("org", "awesome");
+        let linked = RepoIdent::new_github("org", "repo");
+        let mut details = RepoDetails::new_test(linked.clone());
+        details.description = Some("fast & small".to_string());
+
+        let client = test_client_with_items(vec![
+            Item::ReadmeRepo(ReadmeRepo::new_test(list, vec![linked])),
+            Item::Repo(RepoDetailsItem::Found(details)),
+        ])
+        .await;
+        let res = client.get("/repo/github/org/awesome/feed.xml").send().await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            res.headers().get("content-type").unwrap(),
+            "application/atom+xml"
+        );
+
+        let body = res.text().await;
+        assert!(body.contains("<entry><id>https://github.com/org/repo</id><title>org/repo</title>"));
+        assert!(body.contains("<summary>fast &amp; small</summary>"));
+    }
+}
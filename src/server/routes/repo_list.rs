@@ -1,13 +1,169 @@
-use axum::{extract::State, response::Html};
+use std::cmp::Reverse;
 
-use crate::server::{ui, Ctx, HtmlErrorPage};
+use axum::{
+    extract::{Query, State},
+    response::Html,
+};
+
+use crate::{
+    server::{routes::repo_page::deserialize_empty_as_none, ui, Ctx, HtmlErrorPage},
+    source::ReadmeRepo,
+    storage::Storage,
+};
 
 pub const PATH_README_LIST: &str = "/lists";
 
-pub async fn handler_readme_list(State(ctx): State<Ctx>) -> Result<Html<String>, HtmlErrorPage> {
-    let repos = ctx.loader.popular_repos(200).await?;
+/// Default number of lists per page.
+pub const LIST_PAGE_SIZE: usize = 24;
+
+/// Maximum number of lists per page.
+const MAX_LIST_PAGE_SIZE: usize = 100;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListSort {
+    Stars,
+    Links,
+    Updated,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct ListPageQuery {
+    pub sort: Option<ListSort>,
+    /// Page, starting at 1.
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub page: Option<usize>,
+    /// Lists per page. Defaults to [`LIST_PAGE_SIZE`].
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub size: Option<usize>,
+}
+
+impl ListPageQuery {
+    pub fn with_sort(self, sort: ListSort) -> Self {
+        Self {
+            sort: Some(sort),
+            page: None,
+            ..self
+        }
+    }
 
-    let html = ui::render_readme_list_page(repos);
+    pub fn with_page(self, page: usize) -> Self {
+        Self {
+            page: if page > 1 { Some(page) } else { None },
+            ..self
+        }
+    }
+
+    pub fn sort(&self) -> ListSort {
+        self.sort.unwrap_or(ListSort::Stars)
+    }
+
+    /// The current page, starting at 1.
+    pub fn page(&self) -> usize {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+            .unwrap_or(LIST_PAGE_SIZE)
+            .clamp(1, MAX_LIST_PAGE_SIZE)
+    }
+
+    pub fn to_query(&self) -> String {
+        format!("?{}", serde_urlencoded::to_string(self).unwrap())
+    }
+}
+
+/// Sort lists, breaking ties by ident so pages are stable.
+fn sort_lists(lists: &mut [ReadmeRepo], sort: ListSort) {
+    match sort {
+        ListSort::Stars => lists.sort_by_cached_key(|x| {
+            (
+                Reverse(x.details.stargazer_count),
+                x.details.ident.to_string(),
+            )
+        }),
+        ListSort::Links => {
+            lists.sort_by_cached_key(|x| (Reverse(x.repo_links.len()), x.details.ident.to_string()))
+        }
+        ListSort::Updated => lists.sort_by_cached_key(|x| {
+            (
+                Reverse(x.details.last_pushed_at),
+                x.details.ident.to_string(),
+            )
+        }),
+    }
+}
+
+pub async fn handler_readme_list(
+    State(ctx): State<Ctx>,
+    Query(query): Query<ListPageQuery>,
+) -> Result<Html<String>, HtmlErrorPage> {
+    let mut lists = ctx.store.readme_repo_list().await?;
+    sort_lists(&mut lists, query.sort());
+
+    let size = query.size();
+    let page_count = lists.len().div_ceil(size).max(1);
+    let page = query.page().min(page_count);
+
+    // Only the lists on the current page are resolved.
+    let repos = futures::future::try_join_all(
+        lists
+            .into_iter()
+            .skip((page - 1) * size)
+            .take(size)
+            .map(|list| ctx.loader.load_full_readme_repo(list.details.ident, false)),
+    )
+    .await?;
+
+    let html = ui::render_readme_list_page(repos, &query, page, page_count);
 
     Ok(Html(html))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::{
+        server::{test_client_with_items, unescaped_text},
+        source::{ReadmeRepo, RepoIdent},
+        storage::Item,
+    };
+
+    #[tokio::test]
+    async fn test_server_readme_list_paginated() {
+        let items = (0..5)
+            .map(|n| {
+                let links = (0..n)
+                    .map(|x| RepoIdent::new_github("org", format!("link{x}")))
+                    .collect();
+                let mut repo =
+                    ReadmeRepo::new_test(RepoIdent::new_github("org", format!("list{n}")), links);
+                repo.details.stargazer_count = 100 - n as u32;
+                Item::ReadmeRepo(repo)
+            })
+            .collect();
+        let client = test_client_with_items(items).await;
+
+        // Most starred first.
+        let res = client.get("/lists?size=2").send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let html = unescaped_text(res).await;
+        assert!(html.contains("org/list0"));
+        assert!(html.contains("org/list1"));
+        assert!(!html.contains("org/list2"));
+        assert!(html.contains("pagination-link is-current"));
+
+        // Most links first, on the last page.
+        let res = client.get("/lists?sort=links&size=2&page=3").send().await;
+        let html = unescaped_text(res).await;
+        assert!(html.contains("org/list0"));
+        assert!(!html.contains("org/list4"));
+
+        // Pages past the end show the last page.
+        let res = client.get("/lists?sort=links&size=2&page=10").send().await;
+        let html = unescaped_text(res).await;
+        assert!(html.contains("org/list0"));
+    }
+}
@@ -1,9 +1,12 @@
 use axum::{
     extract::{Path, Query, State},
-    response::Html,
+    http::{header, HeaderMap},
+    response::{Html, IntoResponse, Response},
 };
 
-use crate::{server::HtmlErrorPage, source::RepoIdent};
+use serde::Deserialize;
+
+use crate::{server::HtmlErrorPage, source::FullRepoLink};
 
 use crate::server::{ui, Ctx};
 
@@ -21,47 +24,653 @@ pub enum RepoSort {
     Title,
     Stars,
     Updated,
+    Forks,
+    Issues,
+}
+
+impl RepoSort {
+    /// Direction used if none is specified.
+    pub fn default_dir(self) -> SortDir {
+        match self {
+            RepoSort::Title => SortDir::Asc,
+            RepoSort::Stars | RepoSort::Updated | RepoSort::Forks | RepoSort::Issues => {
+                SortDir::Desc
+            }
+        }
+    }
+}
+
+/// How duplicate repos are removed.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepoDedup {
+    /// Each repo shows up at most once per category.
+    #[default]
+    Category,
+    /// Each repo only shows up in the first category that contains it.
+    Global,
+}
+
+/// Order of the top-level categories.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CategoryOrder {
+    /// Alphabetical.
+    #[default]
+    Alpha,
+    /// Order of first appearance in the README.
+    Readme,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortDir {
+    Asc,
+    Desc,
+}
+
+impl SortDir {
+    pub fn reversed(self) -> Self {
+        match self {
+            SortDir::Asc => SortDir::Desc,
+            SortDir::Desc => SortDir::Asc,
+        }
+    }
+
+    /// Apply the direction to an ascending ordering.
+    pub fn apply(self, ordering: std::cmp::Ordering) -> std::cmp::Ordering {
+        match self {
+            SortDir::Asc => ordering,
+            SortDir::Desc => ordering.reverse(),
+        }
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct RepoPageQuery {
     pub view: Option<RepoPageView>,
     pub sort: Option<RepoSort>,
+    /// Sort direction. Defaults to [`RepoSort::default_dir`].
+    pub dir: Option<SortDir>,
+    /// Only show repos with this primary language (case-insensitive).
+    pub lang: Option<String>,
+    /// Only show repos with at least this many stars.
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub min_stars: Option<u32>,
+    /// Only show repos with this topic (case-insensitive).
+    pub topic: Option<String>,
+    /// Hide archived repos.
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub hide_archived: Option<bool>,
+    /// Page of the single table view, starting at 1.
+    #[serde(default, deserialize_with = "deserialize_empty_as_none")]
+    pub page: Option<usize>,
+    /// How duplicate repos are removed. Defaults to [`RepoDedup::Category`].
+    pub dedup: Option<RepoDedup>,
+    /// Order of the categories. Defaults to [`CategoryOrder::Alpha`].
+    pub order: Option<CategoryOrder>,
+}
+
+/// Number of repos per page in the single table view.
+pub const REPO_PAGE_SIZE: usize = 100;
+
+/// Deserializes an optional value, treating an empty string as `None`.
+///
+/// Needed because HTML forms submit empty inputs as `key=`.
+pub(crate) fn deserialize_empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(v) => v.parse().map(Some).map_err(serde::de::Error::custom),
+    }
 }
 
 impl RepoPageQuery {
     pub fn with_view(self, view: RepoPageView) -> Self {
         Self {
             view: Some(view),
+            page: None,
             ..self
         }
     }
 
+    /// Set the sort, resetting the direction to the default of the new sort.
     pub fn with_sort(self, sort: RepoSort) -> Self {
         Self {
             sort: Some(sort),
+            dir: None,
+            page: None,
+            ..self
+        }
+    }
+
+    pub fn with_dir(self, dir: SortDir) -> Self {
+        Self {
+            dir: Some(dir),
+            page: None,
             ..self
         }
     }
 
+    /// Filter by a language.
+    pub fn with_lang(self, lang: &str) -> Self {
+        Self {
+            lang: Some(lang.to_string()),
+            page: None,
+            ..self
+        }
+    }
+
+    pub fn with_page(self, page: usize) -> Self {
+        Self {
+            page: if page > 1 { Some(page) } else { None },
+            ..self
+        }
+    }
+
+    /// The current page, starting at 1.
+    pub fn page(&self) -> usize {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn sort(&self) -> RepoSort {
+        self.sort.unwrap_or(RepoSort::Stars)
+    }
+
+    pub fn dedup(&self) -> RepoDedup {
+        self.dedup.unwrap_or_default()
+    }
+
+    pub fn category_order(&self) -> CategoryOrder {
+        self.order.unwrap_or_default()
+    }
+
+    pub fn with_category_order(self, order: CategoryOrder) -> Self {
+        Self {
+            order: Some(order),
+            ..self
+        }
+    }
+
+    pub fn sort_dir(&self) -> SortDir {
+        self.dir.unwrap_or(self.sort().default_dir())
+    }
+
     pub fn to_query(&self) -> String {
         format!("?{}", serde_urlencoded::to_string(self).unwrap())
     }
+
+    /// Returns the query parameters as key/value pairs, skipping the given keys.
+    ///
+    /// Used to carry over the current state as hidden form fields.
+    pub fn to_pairs_except(&self, exclude: &[&str]) -> Vec<(String, String)> {
+        let query = serde_urlencoded::to_string(self).unwrap();
+        url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .filter(|(key, _)| !exclude.contains(&key.as_str()))
+            .collect()
+    }
+
+    /// Language filter, if set to a non-empty value.
+    pub fn lang_filter(&self) -> Option<&str> {
+        self.lang
+            .as_deref()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+    }
+
+    /// Topic filter, if set to a non-empty value.
+    pub fn topic_filter(&self) -> Option<&str> {
+        self.topic
+            .as_deref()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+    }
+
+    /// Returns `true` if the link passes all filters of this query.
+    pub fn matches(&self, link: &FullRepoLink) -> bool {
+        if let Some(lang) = self.lang_filter() {
+            let lang_matches = link
+                .details
+                .primary_language
+                .as_deref()
+                .is_some_and(|x| x.eq_ignore_ascii_case(lang));
+            if !lang_matches {
+                return false;
+            }
+        }
+
+        if let Some(min_stars) = self.min_stars {
+            if link.details.stargazer_count < min_stars {
+                return false;
+            }
+        }
+
+        if self.hide_archived == Some(true) && link.details.archived {
+            return false;
+        }
+
+        if let Some(topic) = self.topic_filter() {
+            if !link.details.has_topic(topic) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Whether the client prefers JSON over HTML.
+///
+/// Only the order of the media types is considered, quality values are
+/// ignored.
+fn prefers_json(headers: &HeaderMap) -> bool {
+    let Some(accept) = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    for media_type in accept.split(',') {
+        let media_type = media_type.split(';').next().unwrap_or_default().trim();
+        match media_type {
+            "application/json" => return true,
+            "text/html" | "application/xhtml+xml" => return false,
+            _ => {}
+        }
+    }
+    false
 }
 
+/// Renders the repo page, or returns the JSON from the repo API if the client
+/// requests `application/json`.
 pub async fn handler_repo(
     State(ctx): State<Ctx>,
-    Path((source, owner, repo)): Path<(String, String, String)>,
+    Path(path): Path<(String, String, String)>,
     Query(query): Query<RepoPageQuery>,
-) -> Result<Html<String>, HtmlErrorPage> {
-    let ident = RepoIdent {
-        source: source.parse()?,
-        owner,
-        repo,
-    };
+    headers: HeaderMap,
+) -> Response {
+    // The same URL serves different representations.
+    let vary = [(header::VARY, "accept")];
+    if prefers_json(&headers) {
+        (
+            vary,
+            [(header::CACHE_CONTROL, "no-store")],
+            super::api_repo::handler_api_repo(State(ctx), Path(path)).await,
+        )
+            .into_response()
+    } else {
+        (vary, handler_repo_html(ctx, path, query).await).into_response()
+    }
+}
+
+/// Errors, like rate limits, are not cached. Neither are pages with links
+/// that are still loading, so clients see them once they are resolved.
+async fn handler_repo_html(
+    ctx: Ctx,
+    path: (String, String, String),
+    query: RepoPageQuery,
+) -> Result<Response, HtmlErrorPage> {
+    let ident = ctx.ident_from_path(path)?;
     let repo = ctx.loader.load_full_readme_repo(ident, true).await?;
 
-    let html = ui::render_repo_page(repo.as_ref().clone(), query);
+    // Only relevant while links are missing.
+    let retry_in = repo
+        .rate_limit_reset_at
+        .filter(|_| repo.has_missing_links())
+        .and_then(|at| at.duration_since(std::time::SystemTime::now()).ok());
+
+    // The notice changes with the remaining time, so it is part of the key.
+    let mut key = query.to_query();
+    if let Some(retry_in) = retry_in {
+        key.push_str(&format!("#retry={}", retry_in.as_secs() / 60));
+    }
+
+    let html = ctx
+        .render_cache
+        .get_or_render(&repo, key, || {
+            ui::render_repo_page(repo.as_ref().clone(), query, retry_in, &ctx.public_url)
+        })
+        .await;
+
+    let mut res = Html(html.to_string()).into_response();
+    let cache_control = if repo.has_missing_links() {
+        Some("no-store".to_string())
+    } else if !ctx.page_max_age.is_zero() {
+        Some(format!("public, max-age={}", ctx.page_max_age.as_secs()))
+    } else {
+        None
+    };
+    if let Some(value) = cache_control {
+        res.headers_mut()
+            .insert(header::CACHE_CONTROL, value.parse().unwrap());
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_page_query_empty_min_stars() {
+        let query: RepoPageQuery = serde_urlencoded::from_str("lang=&min_stars=").unwrap();
+        assert_eq!(query.min_stars, None);
+        assert_eq!(query.lang_filter(), None);
+
+        let query: RepoPageQuery = serde_urlencoded::from_str("min_stars=100").unwrap();
+        assert_eq!(query.min_stars, Some(100));
+        assert_eq!(query.to_query(), "?min_stars=100");
+    }
+
+    #[test]
+    fn test_repo_page_query_sort_roundtrip() {
+        for sort in [RepoSort::Forks, RepoSort::Issues] {
+            let query = RepoPageQuery {
+                view: None,
+                sort: None,
+                dir: None,
+                lang: None,
+                min_stars: None,
+                topic: None,
+                hide_archived: None,
+                page: None,
+                dedup: None,
+                order: None,
+            }
+            .with_sort(sort);
+            let parsed: RepoPageQuery =
+                serde_urlencoded::from_str(query.to_query().trim_start_matches('?')).unwrap();
+            assert_eq!(parsed.sort, Some(sort));
+        }
+    }
+
+    #[test]
+    fn test_repo_page_query_sort_dir() {
+        let query: RepoPageQuery = serde_urlencoded::from_str("sort=title").unwrap();
+        assert_eq!(query.sort_dir(), SortDir::Asc);
+
+        let query: RepoPageQuery = serde_urlencoded::from_str("").unwrap();
+        assert_eq!(query.sort(), RepoSort::Stars);
+        assert_eq!(query.sort_dir(), SortDir::Desc);
+
+        let query = query.with_dir(SortDir::Asc);
+        assert_eq!(query.to_query(), "?dir=asc");
+        assert_eq!(query.sort_dir(), SortDir::Asc);
+
+        // Changing the sort resets the direction.
+        let query = query.with_sort(RepoSort::Forks);
+        assert_eq!(query.dir, None);
+        assert_eq!(query.sort_dir(), SortDir::Desc);
+    }
+
+    #[test]
+    fn test_repo_page_query_hide_archived() {
+        let link = |archived| {
+            let mut link =
+                FullRepoLink::new_test(crate::source::RepoIdent::new_github("a", "b"), &[]);
+            link.details.archived = archived;
+            link
+        };
+
+        let query: RepoPageQuery = serde_urlencoded::from_str("").unwrap();
+        assert!(query.matches(&link(true)));
+
+        let query: RepoPageQuery = serde_urlencoded::from_str("hide_archived=true").unwrap();
+        assert!(query.matches(&link(false)));
+        assert!(!query.matches(&link(true)));
+    }
+
+    #[test]
+    fn test_repo_page_query_topic() {
+        let mut link = FullRepoLink::new_test(crate::source::RepoIdent::new_github("a", "b"), &[]);
+        link.details.topics = vec!["Web-Framework".to_string()];
+
+        let query: RepoPageQuery = serde_urlencoded::from_str("topic=").unwrap();
+        assert!(query.matches(&link));
+
+        let query: RepoPageQuery = serde_urlencoded::from_str("topic=web-framework").unwrap();
+        assert!(query.matches(&link));
+
+        let query: RepoPageQuery = serde_urlencoded::from_str("topic=cli").unwrap();
+        assert!(!query.matches(&link));
+    }
 
-    Ok(Html(html))
+    #[tokio::test]
+    async fn test_server_repo_page_opengraph() {
+        use crate::{
+            source::{ReadmeRepo, RepoIdent},
+            storage::Item,
+        };
+
+        let ident = RepoIdent::new_github("org", "awesome");
+        let client = crate::server::test_client_with_items(vec![Item::ReadmeRepo(
+            ReadmeRepo::new_test(ident, vec![]),
+        )])
+        .await;
+
+        let body =
+            crate::server::unescaped_text(client.get("/repo/github/org/awesome").send().await)
+                .await;
+        assert!(body.contains("<meta property='og:title' content='org/awesome'>"));
+        assert!(body.contains("<meta property='og:description' content='description'>"));
+        assert!(body.contains("<meta property='og:type' content='article'>"));
+        assert!(body.contains(
+            "<meta property='og:url' content='https://awesomelify.condacity.io/repo/github/org/awesome'>"
+        ));
+        assert!(body.contains("<meta name='twitter:card' content='summary'>"));
+
+        let body = client.get("/").send().await.text().await;
+        assert!(body.contains("<meta property='og:type' content='website'>"));
+    }
+
+    #[tokio::test]
+    async fn test_server_repo_page_live_filter() {
+        use crate::{
+            source::{ReadmeRepo, RepoIdent},
+            storage::Item,
+        };
+
+        let ident = RepoIdent::new_github("org", "awesome");
+        let client = crate::server::test_client_with_items(vec![Item::ReadmeRepo(
+            ReadmeRepo::new_test(ident, vec![]),
+        )])
+        .await;
+
+        let body = client
+            .get("/repo/github/org/awesome")
+            .send()
+            .await
+            .text()
+            .await;
+        assert!(body.contains("id='live-filter'"));
+        assert!(body.contains("document.querySelector('#live-filter')"));
+    }
+
+    #[tokio::test]
+    async fn test_server_repo_page_not_awesome_list() {
+        use crate::{
+            source::{github::mock::MockGithub, loader::SourceLoader},
+            storage::{mem::MemStore, Store},
+        };
+
+        let github = MockGithub::start("# Just a project\n\nNo links here.\n").await;
+        let client = crate::server::test_client_with_source(
+            Store::Mem(MemStore::new()),
+            SourceLoader::new(github.client()),
+        )
+        .await;
+
+        let res = client.get("/repo/github/org/project").send().await;
+        assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+        let html = crate::server::unescaped_text(res).await;
+        assert!(html.contains("Not an awesome list"));
+        assert!(html.contains("href='https://github.com/org/project'"));
+        assert!(html.contains("Search for another list"));
+    }
+
+    #[tokio::test]
+    async fn test_server_repo_page_content_negotiation() {
+        use crate::{
+            source::{
+                github::mock::MockGithub, loader::SourceLoader, ReadmeRepo, RepoDetails,
+                RepoDetailsItem, RepoIdent,
+            },
+            storage::{mem::MemStore, Item, Storage, Store},
+        };
+
+        let github = MockGithub::start("").await;
+        let store = Store::Mem(MemStore::new());
+        let ident = RepoIdent::new_github("org", "awesome");
+        let link = RepoIdent::new_github("org", "a");
+        store
+            .import(vec![
+                Item::ReadmeRepo(ReadmeRepo::new_test(ident.clone(), vec![link.clone()])),
+                Item::Repo(RepoDetailsItem::Found(RepoDetails::new_test(link))),
+            ])
+            .await
+            .unwrap();
+        let client =
+            crate::server::test_client_with_source(store, SourceLoader::new(github.client())).await;
+
+        let res = client
+            .get("/repo/github/org/awesome")
+            .header("accept", "application/json")
+            .send()
+            .await;
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "application/json");
+        let repo: serde_json::Value = res.json().await;
+        assert_eq!(
+            repo["repo"]["details"]["ident"],
+            serde_json::to_value(&ident).unwrap()
+        );
+
+        let res = client
+            .get("/repo/github/org/awesome")
+            .header(
+                "accept",
+                "text/html,application/xhtml+xml,application/json;q=0.9,*/*;q=0.8",
+            )
+            .send()
+            .await;
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        assert!(res.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+    }
+
+    #[test]
+    fn test_repo_page_query_page() {
+        let query: RepoPageQuery = serde_urlencoded::from_str("page=").unwrap();
+        assert_eq!(query.page(), 1);
+
+        let query: RepoPageQuery = serde_urlencoded::from_str("page=0").unwrap();
+        assert_eq!(query.page(), 1);
+
+        let query: RepoPageQuery = serde_urlencoded::from_str("sort=forks&page=3").unwrap();
+        assert_eq!(query.page(), 3);
+        assert_eq!(query.clone().with_page(4).to_query(), "?sort=forks&page=4");
+        assert_eq!(query.clone().with_page(1).to_query(), "?sort=forks");
+
+        // Changing the order starts at the first page.
+        assert_eq!(query.with_sort(RepoSort::Stars).page, None);
+    }
+
+    #[tokio::test]
+    async fn test_server_repo_page_renamed() {
+        use crate::{
+            source::{ReadmeRepo, RepoDetails, RepoDetailsItem, RepoIdent},
+            storage::Item,
+        };
+
+        let list = RepoIdent::new_github("org", "awesome");
+        let link = RepoIdent::new_github("org", "old");
+        let mut readme = ReadmeRepo::new_test(list, vec![link.clone()]);
+        readme.details.renamed_to = Some(RepoIdent::new_github("neworg", "awesome"));
+        let client = crate::server::test_client_with_items(vec![
+            Item::ReadmeRepo(readme),
+            Item::Repo(RepoDetailsItem::Found(RepoDetails {
+                renamed_to: Some(RepoIdent::new_github("org", "new")),
+                ..RepoDetails::new_test(link)
+            })),
+        ])
+        .await;
+
+        let body =
+            crate::server::unescaped_text(client.get("/repo/github/org/awesome").send().await)
+                .await;
+        assert!(
+            body.contains("renamed to <a href='/repo/github/neworg/awesome'>neworg/awesome</a>")
+        );
+        assert!(body.contains("title='Renamed to org/new'"));
+        assert!(body.contains("href='https://github.com/org/new'"));
+    }
+
+    #[tokio::test]
+    async fn test_server_repo_page_cache_control() {
+        use crate::{
+            source::{ReadmeRepo, RepoIdent},
+            storage::{mem::MemStore, Item, Storage, Store},
+        };
+
+        let store = Store::Mem(MemStore::new());
+        let ident = RepoIdent::new_github("org", "awesome");
+        store
+            .import(vec![Item::ReadmeRepo(ReadmeRepo::new_test(ident, vec![]))])
+            .await
+            .unwrap();
+        let client = crate::server::test_client_with_store(store).await;
+
+        let res = client.get("/repo/github/org/awesome").send().await;
+        assert_eq!(res.headers()["cache-control"], "public, max-age=300");
+
+        let res = client
+            .get("/repo/github/org/awesome")
+            .header("accept", "application/json")
+            .send()
+            .await;
+        assert_eq!(res.headers()["cache-control"], "no-store");
+
+        let res = client.get("/api/v1/export").send().await;
+        assert_eq!(res.headers()["cache-control"], "no-store");
+    }
+
+    #[tokio::test]
+    async fn test_server_repo_page_cache_control_missing_links() {
+        use crate::{
+            source::{github::mock::MockGithub, loader::SourceLoader, ReadmeRepo, RepoIdent},
+            storage::{mem::MemStore, Item, Storage, Store},
+        };
+
+        let github = MockGithub::start("").await;
+        // The details of the link can not be loaded yet.
+        github
+            .graphql_rate_limits
+            .store(1, std::sync::atomic::Ordering::SeqCst);
+        let store = Store::Mem(MemStore::new());
+        let ident = RepoIdent::new_github("org", "awesome");
+        store
+            .import(vec![Item::ReadmeRepo(ReadmeRepo::new_test(
+                ident,
+                vec![RepoIdent::new_github("org", "a")],
+            ))])
+            .await
+            .unwrap();
+        let client =
+            crate::server::test_client_with_source(store, SourceLoader::new(github.client())).await;
+
+        let res = client.get("/repo/github/org/awesome").send().await;
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        assert_eq!(res.headers()["cache-control"], "no-store");
+        // The reset time of the rate limit is shown.
+        assert!(res.text().await.contains("Retrying in"));
+    }
 }
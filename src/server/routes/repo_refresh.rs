@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Redirect,
+};
+
+use crate::{
+    loader::RefreshRejection,
+    server::{repo_page_uri, Ctx, HtmlError},
+    storage::Storage,
+};
+
+pub const PATH_REPO_REFRESH: &str = "/repo/:source/:owner/:repo/refresh";
+
+/// Start re-fetching a stored list and the details of its links from the
+/// source, then redirect to the repo page.
+///
+/// The refresh runs in the background. Each list can only be refreshed once
+/// per cooldown period.
+pub async fn handler_repo_refresh(
+    State(ctx): State<Ctx>,
+    Path(path): Path<(String, String, String)>,
+) -> Result<Redirect, HtmlError> {
+    let ident = ctx.ident_from_path(path)?;
+
+    if ctx.loader.is_readonly() {
+        return Err(HtmlError::msg(
+            "Refreshing is disabled - this is a read-only mirror",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if ctx.store.readme_repo(ident.clone()).await?.is_none() {
+        return Err(HtmlError::msg(
+            format!("{ident} is not a known list"),
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    match ctx.loader.start_manual_refresh(&ident) {
+        Ok(()) => {}
+        Err(RefreshRejection::Cooldown(wait)) => {
+            return Err(HtmlError::msg(
+                format!(
+                    "{} was refreshed recently - try again in {} seconds",
+                    ident,
+                    wait.as_secs().max(1)
+                ),
+                StatusCode::TOO_MANY_REQUESTS,
+            ));
+        }
+        Err(RefreshRejection::Busy) => {
+            return Err(HtmlError::msg(
+                "Too many lists are being refreshed - try again later",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ));
+        }
+    }
+
+    Ok(Redirect::to(&repo_page_uri(&ident)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use axum::http::StatusCode;
+
+    use crate::{
+        loader::LoaderConfig,
+        server::{test_client_with_loader_config, test_client_with_source},
+        source::{github::mock::MockGithub, loader::SourceLoader, ReadmeRepo, RepoIdent},
+        storage::{mem::MemStore, Storage, Store},
+    };
+
+    #[tokio::test]
+    async fn test_server_repo_refresh() {
+        let github = MockGithub::start("- [a](https://github.com/org/a)\n").await;
+        let store = Store::Mem(MemStore::new());
+        let list = RepoIdent::new_github("org", "awesome");
+        store
+            .readme_repo_upsert(ReadmeRepo::new_test(list.clone(), vec![]))
+            .await
+            .unwrap();
+
+        let client =
+            test_client_with_source(store.clone(), SourceLoader::new(github.client())).await;
+
+        let res = client.post("/repo/github/org/awesome/refresh").send().await;
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        assert_eq!(res.headers()["location"], "/repo/github/org/awesome");
+
+        // The stored list is replaced with the fresh README in the
+        // background.
+        let mut links = 0;
+        for _ in 0..100 {
+            links = store
+                .readme_repo(list.clone())
+                .await
+                .unwrap()
+                .unwrap()
+                .repo_links
+                .len();
+            if links == 1 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(links, 1);
+        assert_eq!(github.readme_requests.load(Ordering::SeqCst), 1);
+
+        // Refreshing again right away is rejected.
+        let res = client.post("/repo/github/org/awesome/refresh").send().await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(github.readme_requests.load(Ordering::SeqCst), 1);
+
+        // Unknown lists are not loaded.
+        let res = client.post("/repo/github/org/other/refresh").send().await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(github.readme_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_server_repo_refresh_readonly() {
+        let github = MockGithub::start("- [a](https://github.com/org/a)\n").await;
+        let store = Store::Mem(MemStore::new());
+        let list = RepoIdent::new_github("org", "awesome");
+        store
+            .readme_repo_upsert(ReadmeRepo::new_test(list.clone(), vec![]))
+            .await
+            .unwrap();
+
+        let client = test_client_with_loader_config(
+            store,
+            SourceLoader::new(github.client()),
+            LoaderConfig {
+                readonly: true,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let res = client.post("/repo/github/org/awesome/refresh").send().await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(github.readme_requests.load(Ordering::SeqCst), 0);
+    }
+}
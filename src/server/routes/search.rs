@@ -5,22 +5,40 @@ use axum::{
 };
 
 use crate::{
-    server::{repo_page_uri, Ctx, HtmlError},
-    source::RepoIdent,
+    server::{auth::RequireApiToken, repo_page_uri, ui, Ctx, HtmlError},
+    source::{ReadmeLocation, ReadmeRepo, RepoIdent, Source},
+    storage::Storage,
 };
 
 pub const PATH_SEARCH: &str = "/search";
 
+/// Maximum number of suggestions returned by a search.
+const MAX_RESULTS: usize = 10;
+
 #[derive(serde::Deserialize, Debug, Clone)]
 pub struct SearchQuery {
+    #[serde(default)]
     pub q: String,
+    /// Path of the README file, if not the one detected by the source.
+    pub readme_path: Option<String>,
+    /// Branch, tag or commit to load the README from.
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+}
+
+impl SearchQuery {
+    /// The README location, if one was requested.
+    pub fn readme_location(&self) -> Result<Option<ReadmeLocation>, anyhow::Error> {
+        ReadmeLocation::from_input(self.readme_path.as_deref(), self.git_ref.as_deref())
+    }
 }
 
 pub async fn handler_search(
     State(ctx): State<Ctx>,
+    auth: Option<RequireApiToken>,
     Query(q): Query<SearchQuery>,
 ) -> Result<Response, HtmlError> {
-    match search(&ctx, &q).await {
+    match search(&ctx, &q, auth.is_some()).await {
         Ok(res) => Ok(res),
         Err(err) => {
             let mut res = err.into_response();
@@ -31,20 +49,52 @@ pub async fn handler_search(
     }
 }
 
-async fn search(ctx: &Ctx, query: &SearchQuery) -> Result<Response, HtmlError> {
+/// Only callers with API access may change the README location of a stored
+/// list (`replace_location`).
+async fn search(
+    ctx: &Ctx,
+    query: &SearchQuery,
+    replace_location: bool,
+) -> Result<Response, HtmlError> {
+    // HTMX searches on every keyup, so an empty input is common and not an
+    // error. Clear the results.
+    if query.q.trim().is_empty() {
+        return Ok(Html(String::new()).into_response());
+    }
+
     // Parse the query.
 
-    let ident = match RepoIdent::parse_ident(query.q.trim()) {
+    let ident = match ctx.parse_ident(query.q.trim()) {
         Ok(url) => url,
         Err(err) => {
+            // An owner URL, so list all known lists of the owner.
+            if let Some((host, owner)) = parse_owner(query.q.trim(), &ctx.github_hosts) {
+                return search_by_owner(ctx, host, &owner).await;
+            }
+
+            // Not a URL, so try to find a known list by name instead.
+            if let Some(res) = search_by_name(ctx, &query.q).await? {
+                return Ok(res);
+            }
+
             return Err(HtmlError::msg(
-                format!("Invvalid url '{}': {}", query.q, err),
+                format!("Invalid url '{}': {}", query.q, err),
                 StatusCode::BAD_REQUEST,
             ));
         }
     };
 
-    let readme = ctx.loader.load_full_readme_repo(ident, true).await?;
+    let location = query
+        .readme_location()
+        .map_err(|err| HtmlError::msg(err.to_string(), StatusCode::BAD_REQUEST))?;
+    let readme = match location {
+        Some(location) => {
+            ctx.loader
+                .load_full_readme_repo_from(ident, location, replace_location)
+                .await?
+        }
+        None => ctx.loader.load_full_readme_repo(ident, true).await?,
+    };
 
     if readme.repo.repo_links.is_empty() {
         return Err(HtmlError::msg(
@@ -61,3 +111,271 @@ async fn search(ctx: &Ctx, query: &SearchQuery) -> Result<Response, HtmlError> {
 
     Ok(res)
 }
+
+/// Search stored lists by name, owner and description.
+///
+/// Returns `None` if nothing matches.
+async fn search_by_name(ctx: &Ctx, query: &str) -> Result<Option<Response>, HtmlError> {
+    let terms = search_terms(query);
+    if terms.is_empty() {
+        return Ok(None);
+    }
+
+    let mut matches = ctx
+        .store
+        .readme_repo_list()
+        .await?
+        .into_iter()
+        .filter_map(|repo| match_score(&repo, &terms).map(|score| (score, repo)))
+        .collect::<Vec<_>>();
+    if matches.is_empty() {
+        return Ok(None);
+    }
+    matches.sort_by(|(score_a, a), (score_b, b)| {
+        score_b
+            .cmp(score_a)
+            .then(b.details.stargazer_count.cmp(&a.details.stargazer_count))
+    });
+
+    let repos = matches.into_iter().map(|(_, repo)| repo).collect();
+    render_results(ctx, repos).await.map(Some)
+}
+
+/// List all stored lists of an owner.
+async fn search_by_owner(
+    ctx: &Ctx,
+    host: Option<String>,
+    owner: &str,
+) -> Result<Response, HtmlError> {
+    let mut repos = ctx
+        .store
+        .readme_repo_list()
+        .await?
+        .into_iter()
+        .filter(|repo| {
+            let ident = &repo.details.ident;
+            ident.host == host && ident.owner.eq_ignore_ascii_case(owner)
+        })
+        .collect::<Vec<_>>();
+    if repos.is_empty() {
+        return Err(HtmlError::msg(
+            format!("No known lists for '{owner}' - enter the full repository URL"),
+            StatusCode::NOT_FOUND,
+        ));
+    }
+    repos.sort_by_key(|repo| std::cmp::Reverse(repo.details.stargazer_count));
+
+    render_results(ctx, repos).await
+}
+
+/// Render the lists as search suggestions.
+async fn render_results(ctx: &Ctx, mut repos: Vec<ReadmeRepo>) -> Result<Response, HtmlError> {
+    repos.truncate(MAX_RESULTS);
+
+    let mut full_repos = Vec::new();
+    for repo in repos {
+        full_repos.push(
+            ctx.loader
+                .load_full_readme_repo(repo.details.ident, false)
+                .await?,
+        );
+    }
+
+    Ok(Html(ui::render_search_results(full_repos)).into_response())
+}
+
+/// Parse a Github owner URL without a repo, like `github.com/rust-unofficial`.
+///
+/// Returns the custom host, if any, and the owner.
+fn parse_owner(query: &str, github_hosts: &[String]) -> Option<(Option<String>, String)> {
+    match RepoIdent::parse_owner_url(query, github_hosts).ok()? {
+        (Source::Github, host, owner) => Some((host, owner)),
+        _ => None,
+    }
+}
+
+/// Split a query into lowercase terms.
+///
+/// Separators commonly used in repo names are treated like whitespace, so
+/// "awesome rust" matches "awesome-rust".
+fn search_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| c.is_whitespace() || matches!(c, '-' | '_' | '/' | '.'))
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_lowercase())
+        .collect()
+}
+
+/// Score a list against the search terms.
+///
+/// All terms must be contained in the name or the description. Terms found in
+/// the name score higher.
+fn match_score(repo: &ReadmeRepo, terms: &[String]) -> Option<usize> {
+    let name = repo.details.ident.name().to_lowercase();
+    let description = repo
+        .details
+        .description
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let mut score = 0;
+    for term in terms {
+        if name.contains(term.as_str()) {
+            score += 2;
+        } else if description.contains(term.as_str()) {
+            score += 1;
+        } else {
+            return None;
+        }
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        server::{test_client_with_items, test_client_with_store, unescaped_text},
+        source::{ReadmeRepo, RepoIdent},
+        storage::{mem::MemStore, Item, Storage, Store},
+    };
+
+    fn list(owner: &str, repo: &str, description: &str) -> Item {
+        let mut readme = ReadmeRepo::new_test(RepoIdent::new_github(owner, repo), vec![]);
+        readme.details.description = Some(description.to_string());
+        Item::ReadmeRepo(readme)
+    }
+
+    #[tokio::test]
+    async fn test_server_search_by_name() {
+        let client = test_client_with_items(vec![
+            list(
+                "rust-unofficial",
+                "awesome-rust",
+                "A curated list of Rust code",
+            ),
+            list("avelino", "awesome-go", "A curated list of Go frameworks"),
+        ])
+        .await;
+
+        let body = unescaped_text(client.get("/search?q=awesome%20rust").send().await).await;
+        assert!(body.contains("/repo/github/rust-unofficial/awesome-rust"));
+        assert!(!body.contains("awesome-go"));
+
+        // Matches the description.
+        let body = unescaped_text(client.get("/search?q=go%20frameworks").send().await).await;
+        assert!(body.contains("/repo/github/avelino/awesome-go"));
+
+        let body = client.get("/search?q=python").send().await.text().await;
+        assert!(body.contains("Invalid url"));
+    }
+
+    #[tokio::test]
+    async fn test_server_search_empty_query() {
+        let client = test_client_with_store(Store::Mem(MemStore::new())).await;
+
+        for url in ["/search?q=", "/search?q=%20%20%09", "/search"] {
+            let res = client.get(url).send().await;
+            assert_eq!(res.status(), reqwest::StatusCode::OK, "{url}");
+            assert_eq!(res.text().await, "", "{url}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_search_by_owner() {
+        let client = test_client_with_items(vec![
+            list("rust-unofficial", "awesome-rust", "Rust"),
+            list("rust-unofficial", "patterns", "Rust patterns"),
+            list("avelino", "awesome-go", "Go"),
+        ])
+        .await;
+
+        let body = unescaped_text(
+            client
+                .get("/search?q=https%3A%2F%2Fgithub.com%2Frust-unofficial%2F")
+                .send()
+                .await,
+        )
+        .await;
+        assert!(body.contains("/repo/github/rust-unofficial/awesome-rust"));
+        assert!(body.contains("/repo/github/rust-unofficial/patterns"));
+        assert!(!body.contains("awesome-go"));
+
+        let res = client.get("/search?q=github.com%2Funknown").send().await;
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        assert!(res
+            .text()
+            .await
+            .contains("No known lists for &#x27;unknown&#x27;"));
+    }
+
+    #[test]
+    fn test_parse_owner() {
+        let hosts = vec!["github.example.com".to_string()];
+        assert_eq!(
+            super::parse_owner("github.com/rust-unofficial", &hosts),
+            Some((None, "rust-unofficial".to_string()))
+        );
+        assert_eq!(
+            super::parse_owner("https://github.example.com/team/", &hosts),
+            Some((Some("github.example.com".to_string()), "team".to_string()))
+        );
+        assert_eq!(super::parse_owner("github.com/a/b", &hosts), None);
+        assert_eq!(super::parse_owner("rust-unofficial", &hosts), None);
+        assert_eq!(
+            super::parse_owner("github.com/rust unofficial", &hosts),
+            None
+        );
+        assert_eq!(super::parse_owner("codeberg.org/forgejo", &hosts), None);
+    }
+
+    #[tokio::test]
+    async fn test_server_search_readonly() {
+        use std::sync::atomic::Ordering;
+
+        use crate::{
+            loader::LoaderConfig,
+            server::test_client_with_loader_config,
+            source::{github::mock::MockGithub, loader::SourceLoader},
+        };
+
+        let github = MockGithub::start("- [a](https://github.com/org/a)\n").await;
+        let store = Store::Mem(MemStore::new());
+        let stored = ReadmeRepo::new_test(
+            RepoIdent::new_github("org", "awesome"),
+            vec![RepoIdent::new_github("org", "a")],
+        );
+        store.readme_repo_upsert(stored).await.unwrap();
+        let client = test_client_with_loader_config(
+            store,
+            SourceLoader::new(github.client()),
+            LoaderConfig {
+                readonly: true,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        // Stored lists are served.
+        let res = client
+            .get("/search?q=https://github.com/org/awesome")
+            .send()
+            .await;
+        assert_eq!(res.headers()["HX-Redirect"], "/repo/github/org/awesome");
+
+        // New lists are not loaded.
+        let res = client
+            .get("/search?q=https://github.com/org/other")
+            .send()
+            .await;
+        assert!(res.headers().get("HX-Redirect").is_none());
+        assert!(res.text().await.contains("read-only mirror"));
+
+        let res = client.get("/repo/github/org/other").send().await;
+        assert_eq!(res.status(), axum::http::StatusCode::NOT_FOUND);
+        assert!(res.text().await.contains("read-only mirror"));
+
+        assert_eq!(github.readme_requests.load(Ordering::SeqCst), 0);
+        assert_eq!(github.graphql_requests.load(Ordering::SeqCst), 0);
+    }
+}
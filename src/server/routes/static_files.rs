@@ -0,0 +1,75 @@
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+pub const PATH_STATIC: &str = "/static/*path";
+
+pub const PATH_FAVICON: &str = "/favicon.ico";
+
+/// Files from the `static/` directory, embedded into the binary so the
+/// server does not depend on the working directory.
+const FILES: &[(&str, &str, &[u8])] = &[
+    (
+        "style.css",
+        "text/css; charset=utf-8",
+        include_bytes!("../../../static/style.css"),
+    ),
+    (
+        "favicon.ico",
+        "image/x-icon",
+        include_bytes!("../../../static/favicon.ico"),
+    ),
+];
+
+pub async fn handler_static(Path(path): Path<String>) -> Response {
+    static_file(&path)
+}
+
+/// Browsers request the favicon from the root by default.
+pub async fn handler_favicon() -> Response {
+    static_file("favicon.ico")
+}
+
+fn static_file(path: &str) -> Response {
+    let Some((_, content_type, content)) = FILES.iter().find(|(name, _, _)| *name == path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, *content_type),
+            (header::CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        *content,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use crate::storage::{mem::MemStore, Store};
+
+    #[tokio::test]
+    async fn test_server_static() {
+        let client = crate::server::test_client_with_store(Store::Mem(MemStore::new())).await;
+
+        let res = client.get("/static/style.css").send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/css"));
+
+        let res = client.get("/static/missing.css").send().await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = client.get("/favicon.ico").send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "image/x-icon");
+        assert!(!res.bytes().await.is_empty());
+    }
+}
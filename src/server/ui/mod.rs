@@ -1,11 +1,17 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use cercis::prelude::*;
 
-use crate::source::{FullReadmeRepo, FullRepoLink, Source};
+use crate::source::{BrokenLink, FullReadmeRepo, FullRepoLink, RepoIdent, Source};
 
 use crate::server::routes::{
-    repo_page::{RepoPageQuery, RepoPageView, RepoSort},
+    repo_list::{ListPageQuery, ListSort},
+    repo_page::{
+        CategoryOrder, RepoDedup, RepoPageQuery, RepoPageView, RepoSort, SortDir, REPO_PAGE_SIZE,
+    },
     search::PATH_SEARCH,
 };
 
@@ -13,16 +19,77 @@ use super::HtmlError;
 
 const SOURCE_REPO: &str = "https://github.com/theduke/awesomelify";
 const FA_GITHUB: &str = "fa-brands fa-github";
+const FA_GIT: &str = "fa-brands fa-git-alt";
 const FA_STAR: &str = "fa-solid fa-star has-text-warning";
 
+/// Repos without activity for this many days are shown as stale.
+const STALE_DAYS: i64 = 365;
+/// Repos first seen within this many days are marked as new.
+const NEW_DAYS: i64 = 30;
+
+/// Default public URL of the site, used for absolute links in page metadata
+/// and exports. See [`crate::server::CtxBuilder::public_url`].
+pub(crate) const SITE_URL: &str = "https://awesomelify.condacity.io";
+const SITE_DESCRIPTION: &str =
+    "Browse awesome- lists as sortable tables with stars, activity and languages.";
+
+/// OpenGraph metadata used for link previews.
+pub struct PageMeta {
+    pub title: String,
+    pub description: String,
+    /// The `og:type`.
+    pub kind: &'static str,
+    /// Absolute canonical URL of the page, if known.
+    pub url: Option<String>,
+}
+
+impl PageMeta {
+    fn site() -> Self {
+        Self {
+            title: "awesomelify".to_string(),
+            description: SITE_DESCRIPTION.to_string(),
+            kind: "website",
+            url: None,
+        }
+    }
+
+    fn homepage(public_url: &str) -> Self {
+        Self {
+            url: Some(public_url.to_string()),
+            ..Self::site()
+        }
+    }
+
+    fn repo_page(repo: &FullReadmeRepo, public_url: &str) -> Self {
+        let details = &repo.repo.details;
+        let description = details
+            .description
+            .as_deref()
+            .filter(|x| !x.trim().is_empty())
+            .map(|x| x.to_string())
+            .unwrap_or_else(|| format!("{} links in {}", repo.links.len(), details.ident));
+
+        Self {
+            title: details.ident.name(),
+            description,
+            kind: "article",
+            url: Some(format!(
+                "{public_url}{}",
+                super::repo_page_uri(&details.ident)
+            )),
+        }
+    }
+}
+
 fn source_icon_class(source: &Source) -> &'static str {
     match source {
         Source::Github => FA_GITHUB,
+        Source::Codeberg => FA_GIT,
     }
 }
 
-fn pretty_number(n: u32) -> String {
-    let n = n as f64;
+fn pretty_number(n: impl Into<u64>) -> String {
+    let n = n.into() as f64;
 
     if n < 1_000.0 {
         format!("{:.0}", n)
@@ -39,6 +106,8 @@ struct LinkTree {
 
     links: Vec<FullRepoLink>,
     categories: Vec<(String, LinkTree)>,
+    /// Position of each category in `categories`, by name.
+    category_index: HashMap<String, usize>,
 }
 
 impl LinkTree {
@@ -59,6 +128,7 @@ impl LinkTree {
             section: Vec::new(),
             links: Vec::new(),
             categories: Vec::new(),
+            category_index: HashMap::new(),
         }
     }
 
@@ -78,8 +148,8 @@ impl LinkTree {
     }
 
     fn category_mut(&mut self, name: &str) -> &mut LinkTree {
-        if let Some(index) = self.categories.iter().position(|(n, _)| n == name) {
-            return &mut self.categories[index].1;
+        if let Some(index) = self.category_index.get(name) {
+            return &mut self.categories[*index].1;
         }
 
         let mut section = self.section.clone();
@@ -87,10 +157,23 @@ impl LinkTree {
 
         let mut tree = LinkTree::new_child(&section);
         tree.name = Some(name.to_string());
+        self.category_index
+            .insert(name.to_string(), self.categories.len());
         self.categories.push((name.to_string(), tree));
         &mut self.categories.last_mut().unwrap().1
     }
 
+    /// Sort the direct sub-categories.
+    fn sort_categories_by_key<K: Ord>(&mut self, mut key: impl FnMut(&str) -> K) {
+        self.categories.sort_by_cached_key(|(name, _)| key(name));
+        self.category_index = self
+            .categories
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _))| (name.clone(), index))
+            .collect();
+    }
+
     fn category_mut_nested(&mut self, names: &[String]) -> &mut LinkTree {
         let mut tree = self;
         for name in names {
@@ -109,6 +192,28 @@ impl LinkTree {
         }
     }
 
+    /// Number of links in this category, including all sub-categories.
+    fn total_link_count(&self) -> usize {
+        self.links.len()
+            + self
+                .categories
+                .iter()
+                .map(|(_, tree)| tree.total_link_count())
+                .sum::<usize>()
+    }
+
+    /// Remove links that already showed up in an earlier category.
+    ///
+    /// Categories are visited in page order: the links of a category come
+    /// before those of its sub-categories.
+    fn dedup_global(&mut self, seen: &mut HashSet<RepoIdent>) {
+        self.links
+            .retain(|link| seen.insert(link.link.ident.clone()));
+        for (_, tree) in &mut self.categories {
+            tree.dedup_global(seen);
+        }
+    }
+
     // fn sort_categories(&mut self) {
     //     self.categories.sort_by(|(a, _), (b, _)| a.cmp(b));
     //     for (_, tree) in &mut self.categories {
@@ -127,6 +232,10 @@ impl LinkTree {
     }
 }
 
+/// Group links into a tree by their section.
+///
+/// Categories are in order of their first link. Top-level categories are
+/// sorted alphabetically, see [`order_categories`].
 fn group_links_by_category(links: &[FullRepoLink]) -> LinkTree {
     let mut root = LinkTree::new_root();
 
@@ -135,14 +244,35 @@ fn group_links_by_category(links: &[FullRepoLink]) -> LinkTree {
         category.links.push(link.clone());
     }
 
-    root.categories.sort_by(|(a, _), (b, _)| a.cmp(b));
+    root.sort_categories_by_key(str::to_string);
 
     root
 }
 
+/// Sort the top-level categories of a tree built from the links of `repo`.
+///
+/// The README order is taken from all links of the README, so it does not
+/// change when links are filtered out.
+fn order_categories(tree: &mut LinkTree, repo: &FullReadmeRepo, order: CategoryOrder) {
+    match order {
+        CategoryOrder::Alpha => tree.sort_categories_by_key(str::to_string),
+        CategoryOrder::Readme => {
+            let mut positions = HashMap::new();
+            for link in &repo.repo.repo_links {
+                if let Some(name) = link.section.first() {
+                    let next = positions.len();
+                    positions.entry(name.as_str()).or_insert(next);
+                }
+            }
+            tree.sort_categories_by_key(|name| positions.get(name).copied().unwrap_or(usize::MAX));
+        }
+    }
+}
+
 #[component]
 fn LinkTreeIndex<'a>(tree: &'a LinkTree) -> Element {
     let content = if let (Some(name), Some(id)) = (&tree.name, &tree.id) {
+        let count = tree.total_link_count();
         rsx! {
             div {
                 class: "mb-2",
@@ -150,6 +280,10 @@ fn LinkTreeIndex<'a>(tree: &'a LinkTree) -> Element {
                     href: "#{id}",
                     "{name}"
                 }
+                span {
+                    class: "tag is-light ml-2",
+                    "{count}"
+                }
             }
         }
     } else {
@@ -252,10 +386,15 @@ fn LinkTreeView<'a>(tree: &'a LinkTree) -> Element {
 
     let heading = if !tree.section.is_empty() {
         let full_name = tree.section.join(" > ");
+        let count = tree.total_link_count();
         rsx! {
             h4 {
                 class: "title is-4",
                 "{full_name}"
+                span {
+                    class: "tag is-light ml-2",
+                    "{count}"
+                }
             }
         }
     } else {
@@ -330,17 +469,73 @@ fn AddonFieldButton<'a>(url: String, icon: &'a str, name: &'a str, is_active: bo
     }
 }
 
+/// Aggregates of the linked repos, shown in the header of the repo page.
+pub struct LinkStats {
+    total_stars: u64,
+    /// Archived or stale repos.
+    inactive: usize,
+    /// Relative time of the median last activity, empty if unknown.
+    median_activity: String,
+}
+
+impl LinkStats {
+    fn new(repo: &FullReadmeRepo) -> Self {
+        Self {
+            total_stars: repo.total_link_stars(),
+            inactive: repo.inactive_link_count(STALE_DAYS),
+            median_activity: repo
+                .median_link_activity()
+                .map(|time| match crate::source::relative_time(&time).as_str() {
+                    x @ ("today" | "yesterday") => x.to_string(),
+                    x => format!("{x} ago"),
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Maximum number of languages shown in the header of the repo page.
+const MAX_LANGUAGE_TAGS: usize = 10;
+
 #[component]
 pub fn ReadmeRepoPage<'a>(
     repo: &'a FullReadmeRepo,
     tree: &'a LinkTree,
     query: RepoPageQuery,
+    languages: &'a [String],
+    /// See [`FullReadmeRepo::language_histogram`].
+    language_histogram: &'a [(String, usize)],
+    link_stats: &'a LinkStats,
+    /// Time until the rate limit resets, empty if unknown.
+    retry_in: &'a str,
 ) -> Element {
     let details = &repo.repo.details;
     let name = format!("{}/{}", details.ident.owner, details.ident.repo);
 
+    let current_lang = query.lang_filter().unwrap_or_default();
+    let language_tags = language_histogram
+        .iter()
+        .take(MAX_LANGUAGE_TAGS)
+        .map(|(lang, count)| {
+            let class = if lang.eq_ignore_ascii_case(current_lang) {
+                "tag is-info"
+            } else {
+                "tag is-light"
+            };
+            (lang, count, class, query.clone().with_lang(lang).to_query())
+        })
+        .collect::<Vec<_>>();
+
     let missing_repos = repo.missing_links_count();
-    let repo_mismatch_warning = if missing_repos > 0 {
+    let repo_mismatch_warning = if repo.has_missing_links() && !retry_in.is_empty() {
+        rsx! {
+            div {
+                class: "notification is-warning",
+                "Some repos have not been loaded yet due to API rate limiting. Retrying in {retry_in}."
+                " ({missing_repos} missing)"
+            }
+        }
+    } else if repo.has_missing_links() {
         rsx! {
             div {
                 class: "notification is-warning",
@@ -352,36 +547,48 @@ pub fn ReadmeRepoPage<'a>(
         rsx! {}
     };
 
+    let renamed_note = if let Some(renamed) = &details.renamed_to {
+        let uri = super::repo_page_uri(renamed);
+        let renamed_name = renamed.name();
+        rsx! {
+            div {
+                class: "notification is-info is-light",
+                "This repository was renamed to "
+                a {
+                    href: "{uri}",
+                    "{renamed_name}"
+                }
+                "."
+            }
+        }
+    } else {
+        rsx! {}
+    };
+
     let icon = source_icon_class(&repo.repo.details.ident.source);
 
     let view = query.view.unwrap_or(RepoPageView::TablePerCategory);
-    let sort = query.sort.unwrap_or(RepoSort::Stars);
-
-    let link_view_single_table = {
-        let mut query = query.clone();
-        query.view = Some(RepoPageView::SingleTable);
-        query.to_query()
-    };
-    let link_view_multi_table = {
-        let mut query = query.clone();
-        query.view = Some(RepoPageView::TablePerCategory);
-        query.to_query()
-    };
-
-    let link_sort_title = {
-        let mut query = query.clone();
-        query.sort = Some(RepoSort::Title);
-        query.to_query()
-    };
-    let link_sort_stars = {
-        let mut query = query.clone();
-        query.sort = Some(RepoSort::Stars);
-        query.to_query()
-    };
-    let link_sort_updated = {
-        let mut query = query.clone();
-        query.sort = Some(RepoSort::Updated);
-        query.to_query()
+    let sort = query.sort();
+    let dir = query.sort_dir();
+
+    let link_view_single_table = query
+        .clone()
+        .with_view(RepoPageView::SingleTable)
+        .to_query();
+    let link_view_multi_table = query
+        .clone()
+        .with_view(RepoPageView::TablePerCategory)
+        .to_query();
+
+    let link_sort_title = query.clone().with_sort(RepoSort::Title).to_query();
+    let link_sort_stars = query.clone().with_sort(RepoSort::Stars).to_query();
+    let link_sort_updated = query.clone().with_sort(RepoSort::Updated).to_query();
+    let link_sort_forks = query.clone().with_sort(RepoSort::Forks).to_query();
+    let link_sort_issues = query.clone().with_sort(RepoSort::Issues).to_query();
+    let link_sort_dir = query.clone().with_dir(dir.reversed()).to_query();
+    let (dir_icon, dir_title) = match dir {
+        SortDir::Asc => ("fa-solid fa-arrow-up-short-wide", "Ascending"),
+        SortDir::Desc => ("fa-solid fa-arrow-down-wide-short", "Descending"),
     };
 
     let view_selector = rsx! {
@@ -456,8 +663,96 @@ pub fn ReadmeRepoPage<'a>(
                         icon: "fa-solid fa-clock",
                         name: "Updated",
                     }
+
+                    AddonFieldButton {
+                        url: link_sort_forks,
+                        is_active: sort == RepoSort::Forks,
+                        icon: "fa-solid fa-code-fork",
+                        name: "Forks",
+                    }
+
+                    AddonFieldButton {
+                        url: link_sort_issues,
+                        is_active: sort == RepoSort::Issues,
+                        icon: "fa-solid fa-circle-exclamation",
+                        name: "Issues",
+                    }
+                }
+            }
+
+            div {
+                a {
+                    class: "button",
+                    "hx-boost": "true",
+                    href: "{link_sort_dir}",
+                    title: "{dir_title} (click to reverse)",
+                    span {
+                        class: "icon",
+                        i {
+                            class: "{dir_icon}",
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    let order = query.category_order();
+    let link_order_alpha = query
+        .clone()
+        .with_category_order(CategoryOrder::Alpha)
+        .to_query();
+    let link_order_readme = query
+        .clone()
+        .with_category_order(CategoryOrder::Readme)
+        .to_query();
+
+    let order_selector = rsx! {
+        div {
+            class: "is-flex",
+            style: "gap: 2rem",
+
+            div {
+                b {
+                    "Categories: "
+                }
+            }
+
+            div {
+                AddonField {
+                    AddonFieldButton {
+                        url: link_order_alpha,
+                        icon: "fa-solid fa-arrow-down-a-z",
+                        name: "A-Z",
+                        is_active: order == CategoryOrder::Alpha,
+                    }
+
+                    AddonFieldButton {
+                        url: link_order_readme,
+                        icon: "fa-solid fa-book-open",
+                        name: "README",
+                        is_active: order == CategoryOrder::Readme,
+                    }
+                }
+            }
+        }
+    };
+
+    let filter_selector = rsx! {
+        div {
+            class: "is-flex",
+            style: "gap: 2rem",
+
+            div {
+                b {
+                    "Filter: "
                 }
             }
+
+            FilterForm {
+                query: query,
+                languages: languages,
+            }
         }
     };
 
@@ -469,6 +764,10 @@ pub fn ReadmeRepoPage<'a>(
             view_selector
 
             sort_selector
+
+            order_selector
+
+            filter_selector
         }
     };
 
@@ -536,23 +835,80 @@ pub fn ReadmeRepoPage<'a>(
                     }
                 }
             }
+
+            div {
+                class: "is-flex is-flex-wrap-wrap mt-3",
+                style: "gap: 1.5rem",
+
+                span {
+                    title: "Total stars of all linked repos",
+                    i {
+                        class: "{FA_STAR} pr-1",
+                    }
+                    "{pretty_number(link_stats.total_stars)} stars total"
+                }
+
+                span {
+                    title: "Archived, or no activity for more than a year",
+                    i {
+                        class: "fa-solid fa-box-archive pr-1",
+                    }
+                    "{link_stats.inactive} archived or stale"
+                }
+
+                if !link_stats.median_activity.is_empty() {
+                    span {
+                        title: "Median last activity of all linked repos",
+                        i {
+                            class: "fa-solid fa-clock pr-1",
+                        }
+                        "median activity: {link_stats.median_activity}"
+                    }
+                } else {}
+            }
+
+            div {
+                class: "tags mt-3",
+
+                for (lang, count, class, link) in language_tags.iter() {
+                    a {
+                        class: "{class}",
+                        href: "{link}",
+                        title: "Only show {lang} repos",
+                        "{lang} {count}"
+                    }
+                }
+            }
         }
 
+        renamed_note
+
         repo_mismatch_warning
     };
 
     let content = match view {
         RepoPageView::SingleTable => {
-            // Prevent duplicates.
+            let page_count = repo.links.len().div_ceil(REPO_PAGE_SIZE).max(1);
+            let page_links = (1..=page_count)
+                .map(|n| query.clone().with_page(n).to_query())
+                .collect::<Vec<_>>();
+            let page = query.page().min(page_count);
+            let start = (page - 1) * REPO_PAGE_SIZE;
+            let end = (start + REPO_PAGE_SIZE).min(repo.links.len());
 
             rsx! {
                 div {
                     class: "box",
 
                     LinksTable {
-                        links: &repo.links,
+                        links: &repo.links[start..end],
                         show_category: true,
                     }
+
+                    Pagination {
+                        page: page,
+                        page_links: page_links,
+                    }
                 }
             }
         }
@@ -572,74 +928,149 @@ pub fn ReadmeRepoPage<'a>(
 
             controls
 
+            LiveFilter {}
+
             content
         }
     }
 }
 
+/// Text input that hides table rows not matching the entered text.
+///
+/// Runs client side only and matches against the repo name and description.
 #[component]
-fn LinksTable<'a>(links: &'a [FullRepoLink], show_category: bool) -> Element {
-    rsx! {
-        table {
-            class: "table",
-            style: "width: 100%",
-            thead {
-                tr {
-                    th {
-                        "Repo"
-                    }
-                    th {
-                        "Description"
-                    }
-                    th {
-                        i {
-                            class: "{FA_STAR}",
-                            title: "Star count"
-                        }
-                    }
-                    th {
-                        "Updated"
-                    }
-                    th {
-                        "Lang"
-                    }
+fn LiveFilter() -> Element {
+    let script = UnescapedHtml(
+        r#"
+(function() {
+    const input = document.querySelector('#live-filter');
 
-                    if *show_category {
-                        th {
-                            "Category"
-                        }
-                    } else {
+    input.addEventListener('input', function() {
+        const needle = input.value.trim().toLowerCase();
+        for (const row of document.querySelectorAll('tr.repo-link')) {
+            const text = (row.cells[0].textContent + ' ' + row.cells[1].textContent).toLowerCase();
+            row.classList.toggle('is-hidden', needle !== '' && !text.includes(needle));
+        }
+    });
+})()
+"#
+        .to_string(),
+    );
+
+    rsx! {
+        div {
+            class: "field",
+            p {
+                class: "control has-icons-left",
+                input {
+                    id: "live-filter",
+                    class: "input",
+                    r#type: "search",
+                    placeholder: "Filter by name or description...",
+                    autocomplete: "off",
+                }
+                span {
+                    class: "icon is-left",
+                    i {
+                        class: "fa-solid fa-filter",
                     }
                 }
             }
-            tbody {
-                for link in links.iter() {
-                    tr {
-                        td {
-                            a {
-                                href: "{link.link.ident.url()}",
-                                target: "_blank",
-                                "{link.link.ident.owner}/{link.link.ident.repo}"
-                            }
-                        }
-                        td {
-                            "{link.details.description.as_deref().unwrap_or_default()}"
-                        }
-                        td {
-                            "{pretty_number(link.details.stargazer_count)}"
-                        }
-                        td {
-                            "{link.details.last_activity_relative_time().unwrap_or_default()}"
-                        }
-                        td {
-                            "{link.details.primary_language.as_deref().unwrap_or_default()}"
-                        }
+        }
 
-                        if *show_category {
-                            td {
-                                "{link.link.section.join(\">\")}"
+        script {
+            script
+        }
+    }
+}
+
+/// Page numbers shown in pagination controls.
+///
+/// Always includes the first, last and neighbouring pages of the current
+/// page. `None` marks an ellipsis.
+fn pagination_items(page: usize, page_count: usize) -> Vec<Option<usize>> {
+    let mut items = Vec::new();
+    for n in 1..=page_count {
+        if n == 1 || n == page_count || n.abs_diff(page) <= 1 {
+            items.push(Some(n));
+        } else if items.last() != Some(&None) {
+            items.push(None);
+        }
+    }
+    items
+}
+
+/// Bulma pagination controls.
+///
+/// `page_links` holds the link of each page, starting with page 1.
+/// Renders nothing if there is only a single page.
+#[component]
+fn Pagination(page: usize, page_links: Vec<String>) -> Element {
+    let page_count = page_links.len();
+    if page_count <= 1 {
+        return rsx! {};
+    }
+    let link = |n: usize| page_links[n - 1].clone();
+
+    let (prev_class, prev_link) = if *page > 1 {
+        ("pagination-previous", link(page - 1))
+    } else {
+        ("pagination-previous is-disabled", "#".to_string())
+    };
+    let (next_class, next_link) = if *page < page_count {
+        ("pagination-next", link(page + 1))
+    } else {
+        ("pagination-next is-disabled", "#".to_string())
+    };
+
+    let items = pagination_items(*page, page_count)
+        .into_iter()
+        .map(|item| {
+            item.map(|n| {
+                let class = if n == *page {
+                    "pagination-link is-current"
+                } else {
+                    "pagination-link"
+                };
+                (n, class, link(n))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    rsx! {
+        nav {
+            class: "pagination is-centered mt-4",
+            role: "navigation",
+            aria_label: "pagination",
+
+            a {
+                class: "{prev_class}",
+                href: "{prev_link}",
+                "Previous"
+            }
+            a {
+                class: "{next_class}",
+                href: "{next_link}",
+                "Next"
+            }
+
+            ul {
+                class: "pagination-list",
+
+                for item in items.iter() {
+                    li {
+                        if let Some((n, class, link)) = item {
+                            a {
+                                class: "{class}",
+                                href: "{link}",
+                                aria_label: "Go to page {n}",
+                                "{n}"
                             }
                         } else {
+                            span {
+                                class: "pagination-ellipsis",
+                                "…"
+                            }
                         }
                     }
                 }
@@ -648,19 +1079,341 @@ fn LinksTable<'a>(links: &'a [FullRepoLink], show_category: bool) -> Element {
     }
 }
 
+/// Form for the link filters.
+///
+/// Other query parameters are carried over as hidden fields.
 #[component]
-pub fn PageLayout<'a>(title: &'a str, children: Element<'a>) -> Element {
+fn FilterForm<'a>(query: &'a RepoPageQuery, languages: &'a [String]) -> Element {
+    // The page is dropped, since the number of results changes.
+    let hidden = query.to_pairs_except(&["lang", "min_stars", "topic", "hide_archived", "page"]);
+    let topic = query.topic_filter().unwrap_or_default();
+    let hide_archived = query.hide_archived == Some(true);
+    let current_lang = query.lang_filter().unwrap_or_default();
+    let min_stars = query.min_stars.map(|x| x.to_string()).unwrap_or_default();
+
     rsx! {
-        doctype {}
-        html {
+        form {
+            method: "get",
+            class: "is-flex",
+            style: "gap: 1rem",
+            "hx-boost": "true",
+
+            for (name, value) in hidden.iter() {
+                input {
+                    r#type: "hidden",
+                    name: "{name}",
+                    value: "{value}",
+                }
+            }
+
+            div {
+                class: "select",
+
+                select {
+                    name: "lang",
+                    title: "Filter by language",
+                    onchange: "this.form.requestSubmit()",
+
+                    option {
+                        value: "",
+                        "All languages"
+                    }
+
+                    for lang in languages.iter() {
+                        if lang.eq_ignore_ascii_case(current_lang) {
+                            option {
+                                value: "{lang}",
+                                selected: "selected",
+                                "{lang}"
+                            }
+                        } else {
+                            option {
+                                value: "{lang}",
+                                "{lang}"
+                            }
+                        }
+                    }
+                }
+            }
+
+            div {
+                class: "field has-addons",
+
+                p {
+                    class: "control",
+                    input {
+                        class: "input",
+                        r#type: "number",
+                        name: "min_stars",
+                        min: "0",
+                        placeholder: "Min. stars",
+                        title: "Minimum star count",
+                        value: "{min_stars}",
+                    }
+                }
+
+                p {
+                    class: "control",
+                    button {
+                        class: "button",
+                        r#type: "submit",
+                        span {
+                            class: "icon",
+                            i {
+                                class: "{FA_STAR}",
+                            }
+                        }
+                    }
+                }
+            }
+
+            div {
+                class: "control",
+                input {
+                    class: "input",
+                    r#type: "text",
+                    name: "topic",
+                    placeholder: "Topic",
+                    title: "Filter by topic",
+                    value: "{topic}",
+                }
+            }
+
+            label {
+                class: "checkbox is-flex is-align-items-center",
+                style: "gap: 0.5rem",
+
+                if hide_archived {
+                    input {
+                        r#type: "checkbox",
+                        name: "hide_archived",
+                        value: "true",
+                        checked: "checked",
+                        onchange: "this.form.requestSubmit()",
+                    }
+                } else {
+                    input {
+                        r#type: "checkbox",
+                        name: "hide_archived",
+                        value: "true",
+                        onchange: "this.form.requestSubmit()",
+                    }
+                }
+                "Hide archived"
+            }
+        }
+    }
+}
+
+fn link_row_class(link: &FullRepoLink) -> &'static str {
+    if link.details.is_stale(STALE_DAYS) {
+        "repo-link is-stale"
+    } else {
+        "repo-link"
+    }
+}
+
+#[component]
+fn LinksTable<'a>(links: &'a [FullRepoLink], show_category: bool) -> Element {
+    rsx! {
+        table {
+            class: "table",
+            style: "width: 100%",
+            thead {
+                tr {
+                    th {
+                        "Repo"
+                    }
+                    th {
+                        "Description"
+                    }
+                    th {
+                        i {
+                            class: "{FA_STAR}",
+                            title: "Star count"
+                        }
+                    }
+                    th {
+                        "Updated"
+                    }
+                    th {
+                        "Lang"
+                    }
+                    th {
+                        "License"
+                    }
+
+                    if *show_category {
+                        th {
+                            "Category"
+                        }
+                    } else {
+                    }
+                }
+            }
+            tbody {
+                for link in links.iter() {
+                    tr {
+                        class: "{link_row_class(link)}",
+                        td {
+                            a {
+                                href: "{link.link.ident.url()}",
+                                target: "_blank",
+                                "{link.link.ident.owner}/{link.link.ident.repo}"
+                            }
+                            if let Some(renamed) = &link.details.renamed_to {
+                                a {
+                                    class: "tag is-info is-light ml-1",
+                                    href: "{renamed.url()}",
+                                    target: "_blank",
+                                    title: "Renamed to {renamed.name()}",
+                                    "renamed"
+                                }
+                            } else {
+                            }
+                            if link.details.archived {
+                                span {
+                                    class: "tag is-warning is-light ml-1",
+                                    title: "This repository is archived",
+                                    "archived"
+                                }
+                            } else if link.details.is_stale(STALE_DAYS) {
+                                span {
+                                    class: "tag is-light ml-1",
+                                    title: "No activity in over a year",
+                                    "stale"
+                                }
+                            } else {
+                            }
+                            if link.details.is_new(NEW_DAYS) {
+                                span {
+                                    class: "tag is-success is-light ml-1",
+                                    title: "First seen in the last {NEW_DAYS} days",
+                                    "new"
+                                }
+                            } else {
+                            }
+                        }
+                        td {
+                            "{link.description().unwrap_or_default()}"
+                            if link.details.topics.is_empty() {
+                            } else {
+                                div {
+                                    class: "tags mt-1",
+                                    for topic in link.details.topics.iter() {
+                                        span {
+                                            class: "tag is-info is-light",
+                                            "{topic}"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        td {
+                            "{pretty_number(link.details.stargazer_count)}"
+                        }
+                        td {
+                            if let (Some(exact), Some(relative)) = (
+                                link.details.last_activity_exact(),
+                                link.details.last_activity_relative_time(),
+                            ) {
+                                span {
+                                    title: "{exact}",
+                                    "{relative}"
+                                }
+                            } else {
+                            }
+                        }
+                        td {
+                            "{link.details.primary_language.as_deref().unwrap_or_default()}"
+                        }
+                        td {
+                            "{link.details.license.as_deref().unwrap_or_default()}"
+                        }
+
+                        if *show_category {
+                            td {
+                                "{link.link.section.join(\">\")}"
+                            }
+                        } else {
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+pub fn PageLayout<'a>(
+    title: &'a str,
+    meta: Option<&'a PageMeta>,
+    children: Element<'a>,
+) -> Element {
+    let default_meta;
+    let meta = match meta {
+        Some(meta) => meta,
+        None => {
+            default_meta = PageMeta::site();
+            &default_meta
+        }
+    };
+
+    let theme_script = UnescapedHtml(
+        r#"
+(function() {
+    const theme = localStorage.getItem('theme');
+    if (theme) {
+        document.documentElement.setAttribute('data-theme', theme);
+    }
+})()
+"#
+        .to_string(),
+    );
+
+    rsx! {
+        doctype {}
+        html {
             head {
                 meta {
                     charset: "utf-8",
                 }
+                // Must run before the body is rendered to avoid a flash of the
+                // wrong theme.
+                script {
+                    theme_script
+                }
                 meta {
                     name: "viewport",
                     content: "width=device-width, initial-scale=1",
                 }
+                meta {
+                    name: "description",
+                    content: "{meta.description}",
+                }
+                meta {
+                    property: "og:title",
+                    content: "{meta.title}",
+                }
+                meta {
+                    property: "og:description",
+                    content: "{meta.description}",
+                }
+                meta {
+                    property: "og:type",
+                    content: "{meta.kind}",
+                }
+                if let Some(url) = &meta.url {
+                    meta {
+                        property: "og:url",
+                        content: "{url}",
+                    }
+                } else {
+                }
+                meta {
+                    name: "twitter:card",
+                    content: "summary",
+                }
                 link {
                     rel: "stylesheet",
                     href: "https://cdn.jsdelivr.net/npm/bulma@1.0.1/css/bulma.min.css",
@@ -669,6 +1422,10 @@ pub fn PageLayout<'a>(title: &'a str, children: Element<'a>) -> Element {
                     rel: "stylesheet",
                     href: "/static/style.css",
                 }
+                link {
+                    rel: "icon",
+                    href: "/favicon.ico",
+                }
                 link {
                     rel: "stylesheet",
                     href: "https://cdnjs.cloudflare.com/ajax/libs/font-awesome/6.6.0/css/all.min.css",
@@ -718,6 +1475,26 @@ pub fn PageLayout<'a>(title: &'a str, children: Element<'a>) -> Element {
 
 #[component]
 fn NavBar() -> Element {
+    // JS for the theme toggle. Without a stored choice, Bulma follows the
+    // system preference.
+    let script = UnescapedHtml(
+        r#"
+(function() {
+    const button = document.querySelector('#theme-toggle');
+
+    button.addEventListener('click', function() {
+        const root = document.documentElement;
+        const current = root.getAttribute('data-theme')
+            || (window.matchMedia('(prefers-color-scheme: dark)').matches ? 'dark' : 'light');
+        const theme = current === 'dark' ? 'light' : 'dark';
+        root.setAttribute('data-theme', theme);
+        localStorage.setItem('theme', theme);
+    });
+})()
+"#
+        .to_string(),
+    );
+
     rsx! {
         nav {
             class: "navbar",
@@ -761,6 +1538,19 @@ fn NavBar() -> Element {
                         div {
                             class: "buttons",
 
+                            button {
+                                id: "theme-toggle",
+                                class: "button",
+                                title: "Toggle dark mode",
+
+                                span {
+                                    class: "icon",
+                                    i {
+                                        class: "fa-solid fa-circle-half-stroke",
+                                    }
+                                }
+                            }
+
                             a {
                                 class: "button",
                                 target: "_blank",
@@ -782,58 +1572,154 @@ fn NavBar() -> Element {
                 }
             }
         }
+
+        script {
+            script
+        }
     }
 }
 
-pub fn render_repo_page(mut repo: FullReadmeRepo, query: RepoPageQuery) -> String {
-    let mut tree = group_links_by_category(&repo.links);
-
-    // Filter out duplicates.
-    {
-        let mut seen = std::collections::HashSet::new();
-
-        repo.links
-            .retain(|link| seen.insert(link.link.ident.clone()));
+/// Approximate, human readable duration, like "~12 minutes".
+fn format_remaining(duration: std::time::Duration) -> String {
+    let minutes = duration.as_secs().div_ceil(60);
+    match minutes {
+        0 | 1 => "~1 minute".to_string(),
+        2..=90 => format!("~{minutes} minutes"),
+        _ => format!("~{} hours", minutes.div_ceil(60)),
+    }
+}
 
-        tree.visit_mut(|tree| {
-            let mut seen = std::collections::HashSet::new();
+/// Render the page of a readme repo.
+///
+/// `retry_in` is the time until the source rate limit resets, if exceeded.
+/// `public_url` is the base of the absolute page URL in the metadata.
+pub fn render_repo_page(
+    mut repo: FullReadmeRepo,
+    query: RepoPageQuery,
+    retry_in: Option<std::time::Duration>,
+    public_url: &str,
+) -> String {
+    // Collected before filtering, so all options remain selectable.
+    let languages = repo.primary_languages();
+    let language_histogram = repo.language_histogram();
+    let link_stats = LinkStats::new(&repo);
 
-            tree.links
-                .retain(|link| seen.insert(link.link.ident.clone()));
-        })
-    }
+    let tree = prepare_links(&mut repo, &query);
 
-    let sort = query.sort.unwrap_or(RepoSort::Stars);
-    match sort {
-        RepoSort::Title => {
-            repo.links.sort_by(|a, b| a.link.ident.cmp(&b.link.ident));
-            tree.sort_links_by(|a, b| a.link.ident.cmp(&b.link.ident))
-        }
-        RepoSort::Stars => {
-            repo.links
-                .sort_by(|a, b| b.details.stargazer_count.cmp(&a.details.stargazer_count));
-            tree.sort_links_by(|a, b| b.details.stargazer_count.cmp(&a.details.stargazer_count))
-        }
-        RepoSort::Updated => {
-            repo.links
-                .sort_by(|a, b| b.details.last_activity().cmp(&a.details.last_activity()));
-            tree.sort_links_by(|a, b| b.details.last_activity().cmp(&a.details.last_activity()))
-        }
-    };
+    let meta = PageMeta::repo_page(&repo, public_url);
+    let retry_in = retry_in.map(format_remaining).unwrap_or_default();
 
     let node = rsx! {
         PageLayout {
             title: &repo.repo.details.ident.repo,
+            meta: &meta,
             ReadmeRepoPage {
                 repo: &repo,
                 tree: &tree,
                 query: query,
+                languages: &languages,
+                language_histogram: &language_histogram,
+                link_stats: &link_stats,
+                retry_in: &retry_in,
             }
         }
     };
     node.render()
 }
 
+/// Render the links of a repo as a markdown list, grouped by section.
+///
+/// Honors the filters and sort of the query, like [`render_repo_page`].
+pub fn render_repo_markdown(mut repo: FullReadmeRepo, query: &RepoPageQuery) -> String {
+    let tree = prepare_links(&mut repo, query);
+
+    let mut out = format!("# {}\n", repo.repo.details.ident.name());
+    write_tree_markdown(&tree, &mut out);
+    out
+}
+
+/// The links of a repo, with the filters and sort of the query applied.
+pub fn filtered_links(mut repo: FullReadmeRepo, query: &RepoPageQuery) -> Vec<FullRepoLink> {
+    prepare_links(&mut repo, query);
+    repo.links
+}
+
+fn write_tree_markdown(tree: &LinkTree, out: &mut String) {
+    if !tree.links.is_empty() {
+        if !tree.section.is_empty() {
+            out.push_str(&format!("\n## {}\n\n", tree.section.join(" > ")));
+        } else {
+            out.push('\n');
+        }
+        for link in &tree.links {
+            let ident = &link.link.ident;
+            let description = link
+                .description()
+                .map(|x| format!(" — {}", x.split_whitespace().collect::<Vec<_>>().join(" ")))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "- [{}]({}){} (⭐{})\n",
+                ident.name(),
+                ident.url(),
+                description,
+                link.details.stargazer_count
+            ));
+        }
+    }
+
+    for (_, category) in &tree.categories {
+        write_tree_markdown(category, out);
+    }
+}
+
+/// Filter, deduplicate and sort the links of a repo according to the query.
+///
+/// Returns the links grouped by category. `repo.links` is updated to the
+/// flat list.
+fn prepare_links(repo: &mut FullReadmeRepo, query: &RepoPageQuery) -> LinkTree {
+    repo.links.retain(|link| query.matches(link));
+
+    let mut tree = group_links_by_category(&repo.links);
+    order_categories(&mut tree, repo, query.category_order());
+
+    // Filter out duplicates.
+    {
+        let mut seen = std::collections::HashSet::new();
+
+        repo.links
+            .retain(|link| seen.insert(link.link.ident.clone()));
+
+        match query.dedup() {
+            RepoDedup::Category => tree.visit_mut(|tree| {
+                let mut seen = std::collections::HashSet::new();
+
+                tree.links
+                    .retain(|link| seen.insert(link.link.ident.clone()));
+            }),
+            RepoDedup::Global => tree.dedup_global(&mut HashSet::new()),
+        }
+    }
+
+    let sort = query.sort();
+    let dir = query.sort_dir();
+    // Ties are broken by ident, so the order does not depend on the README.
+    let compare = move |a: &FullRepoLink, b: &FullRepoLink| {
+        let ordering = match sort {
+            RepoSort::Title => a.link.ident.cmp(&b.link.ident),
+            RepoSort::Stars => a.details.stargazer_count.cmp(&b.details.stargazer_count),
+            RepoSort::Updated => a.details.last_activity().cmp(&b.details.last_activity()),
+            RepoSort::Forks => a.details.fork_count.cmp(&b.details.fork_count),
+            RepoSort::Issues => a.details.issues.cmp(&b.details.issues),
+        };
+        dir.apply(ordering)
+            .then_with(|| a.link.ident.cmp(&b.link.ident))
+    };
+    repo.links.sort_by(compare);
+    tree.sort_links_by(compare);
+
+    tree
+}
+
 #[component]
 fn SearchBar() -> Element {
     rsx! {
@@ -861,7 +1747,7 @@ fn SearchBar() -> Element {
                         r#type: "text",
                         title: "Repository URL",
                         "aria-label": "Repository URL",
-                        placeholder: "github.com/org/repo",
+                        placeholder: "github.com/org/repo or list name",
                         "hx-get": "{PATH_SEARCH}",
                         "hx-target": "#search-results",
                         "hx-trigger":"keyup changed delay:500ms, search",
@@ -1000,7 +1886,7 @@ fn RepoLinkBox<'a>(repo: &'a FullReadmeRepo) -> Element {
     let details = &repo.repo.details;
     let ident = &details.ident;
 
-    let link = format!("/repo/{}/{}/{}", ident.source, ident.owner, ident.repo);
+    let link = super::repo_page_uri(ident);
 
     let icon = source_icon_class(&ident.source);
 
@@ -1068,10 +1954,12 @@ fn RepoLinkBox<'a>(repo: &'a FullReadmeRepo) -> Element {
     }
 }
 
-pub fn render_homepage(popular_repos: Vec<Arc<FullReadmeRepo>>) -> String {
+pub fn render_homepage(popular_repos: Vec<Arc<FullReadmeRepo>>, public_url: &str) -> String {
+    let meta = PageMeta::homepage(public_url);
     let output = rsx! {
         PageLayout {
             title: "awesomelify - awesome- Link List Viewer",
+            meta: &meta,
             Homepage {
                 popular_repos: popular_repos,
             }
@@ -1082,7 +1970,20 @@ pub fn render_homepage(popular_repos: Vec<Arc<FullReadmeRepo>>) -> String {
 }
 
 #[component]
-pub fn ReadmeListPage(repos: Vec<Arc<FullReadmeRepo>>) -> String {
+pub fn ReadmeListPage<'a>(
+    repos: Vec<Arc<FullReadmeRepo>>,
+    query: &'a ListPageQuery,
+    page: usize,
+    page_count: usize,
+) -> String {
+    let sort = query.sort();
+    let link_sort_stars = (*query).clone().with_sort(ListSort::Stars).to_query();
+    let link_sort_links = (*query).clone().with_sort(ListSort::Links).to_query();
+    let link_sort_updated = (*query).clone().with_sort(ListSort::Updated).to_query();
+    let page_links = (1..=*page_count)
+        .map(|n| (*query).clone().with_page(n).to_query())
+        .collect::<Vec<_>>();
+
     rsx! {
         PageLayout {
             title: "awesomelify - awesome- Link List Viewer",
@@ -1090,23 +1991,391 @@ pub fn ReadmeListPage(repos: Vec<Arc<FullReadmeRepo>>) -> String {
                 class: "is-flex is-flex-direction-column",
                 style: "gap: 1rem;",
 
+                div {
+                    class: "is-flex",
+                    style: "gap: 2rem",
+
+                    div {
+                        b {
+                            "Sort: "
+                        }
+                    }
+
+                    div {
+                        AddonField {
+                            AddonFieldButton {
+                                url: link_sort_stars,
+                                icon: "fa-solid fa-star",
+                                name: "Stars",
+                                is_active: sort == ListSort::Stars,
+                            }
+
+                            AddonFieldButton {
+                                url: link_sort_links,
+                                icon: "fa-solid fa-list",
+                                name: "Links",
+                                is_active: sort == ListSort::Links,
+                            }
+
+                            AddonFieldButton {
+                                url: link_sort_updated,
+                                icon: "fa-solid fa-clock",
+                                name: "Updated",
+                                is_active: sort == ListSort::Updated,
+                            }
+                        }
+                    }
+                }
+
                 for repo in repos {
                     RepoLinkBox {
                         repo: &repo,
                     }
                 }
+
+                Pagination {
+                    page: *page,
+                    page_links: page_links,
+                }
             }
         }
     }
 }
 
-pub fn render_readme_list_page(repos: Vec<Arc<FullReadmeRepo>>) -> String {
+pub fn render_readme_list_page(
+    repos: Vec<Arc<FullReadmeRepo>>,
+    query: &ListPageQuery,
+    page: usize,
+    page_count: usize,
+) -> String {
     let output = rsx! {
         ReadmeListPage {
             repos: repos,
+            query: query,
+            page: page,
+            page_count: page_count,
+        }
+    };
+
+    output.render()
+}
+
+/// HTMX fragment with the lists found by a search.
+pub fn render_search_results(repos: Vec<Arc<FullReadmeRepo>>) -> String {
+    let output = rsx! {
+        div {
+            class: "is-flex is-flex-direction-column",
+            style: "gap: 1rem;",
+
+            for repo in repos.iter() {
+                RepoLinkBox {
+                    repo: repo,
+                }
+            }
+        }
+    };
+
+    output.render()
+}
+
+/// Markdown list of broken links, for pasting into issues or PRs.
+fn broken_links_markdown(broken: &[BrokenLink]) -> String {
+    broken
+        .iter()
+        .map(|broken| {
+            let section = if broken.link.section.is_empty() {
+                String::new()
+            } else {
+                format!(" - {}", broken.link.section.join(" > "))
+            };
+            format!(
+                "- [{}]({}) ({}){}\n",
+                broken.link.ident.name(),
+                broken.link.ident.url(),
+                broken.reason,
+                section
+            )
+        })
+        .collect()
+}
+
+#[component]
+fn BrokenLinksPage<'a>(repo: &'a FullReadmeRepo, broken: &'a [BrokenLink<'a>]) -> Element {
+    let details = &repo.repo.details;
+    let name = details.ident.name();
+    let repo_uri = super::repo_page_uri(&details.ident);
+    let markdown = broken_links_markdown(broken);
+
+    let content = if broken.is_empty() {
+        rsx! {
+            div {
+                class: "notification is-success",
+                "No broken links found."
+            }
+        }
+    } else {
+        rsx! {
+            table {
+                class: "table",
+                style: "width: 100%",
+                thead {
+                    tr {
+                        th {
+                            "Repo"
+                        }
+                        th {
+                            "Problem"
+                        }
+                        th {
+                            "Section"
+                        }
+                    }
+                }
+                tbody {
+                    for item in broken.iter() {
+                        tr {
+                            td {
+                                a {
+                                    href: "{item.link.ident.url()}",
+                                    target: "_blank",
+                                    "{item.link.ident.name()}"
+                                }
+                            }
+                            td {
+                                span {
+                                    class: "tag is-warning is-light",
+                                    "{item.reason}"
+                                }
+                            }
+                            td {
+                                "{item.link.section.join(\" > \")}"
+                            }
+                        }
+                    }
+                }
+            }
+
+            h2 {
+                class: "title is-5",
+                "Markdown"
+            }
+
+            div {
+                class: "field",
+                textarea {
+                    id: "broken-links-markdown",
+                    class: "textarea is-family-monospace",
+                    readonly: "readonly",
+                    rows: "10",
+                    "{markdown}"
+                }
+            }
+            button {
+                class: "button",
+                onclick: "navigator.clipboard.writeText(document.getElementById('broken-links-markdown').value)",
+                span {
+                    class: "icon",
+                    i {
+                        class: "fa-solid fa-copy",
+                    }
+                }
+                span {
+                    "Copy"
+                }
+            }
         }
     };
 
+    rsx! {
+        div {
+            h1 {
+                class: "title",
+                "Broken links in "
+                a {
+                    href: "{repo_uri}",
+                    "{name}"
+                }
+            }
+            p {
+                class: "mb-4",
+                "Links to repositories that could not be found or are archived."
+            }
+
+            content
+        }
+    }
+}
+
+pub fn render_broken_links_page(repo: &FullReadmeRepo) -> String {
+    let broken = repo.broken_links();
+    let title = format!("Broken links - {}", repo.repo.details.ident.name());
+
+    let output = rsx! {
+        PageLayout {
+            title: &title,
+            BrokenLinksPage {
+                repo: repo,
+                broken: &broken,
+            }
+        }
+    };
+    output.render()
+}
+
+/// Links of two lists, grouped by which list contains them.
+struct LinkComparison {
+    only_a: Vec<FullRepoLink>,
+    only_b: Vec<FullRepoLink>,
+    both: Vec<FullRepoLink>,
+}
+
+impl LinkComparison {
+    fn new(a: &FullReadmeRepo, b: &FullReadmeRepo) -> Self {
+        let idents_a: HashSet<&RepoIdent> = a.links.iter().map(|x| &x.link.ident).collect();
+        let idents_b: HashSet<&RepoIdent> = b.links.iter().map(|x| &x.link.ident).collect();
+
+        let mut seen = HashSet::new();
+        let (both, only_a) = a
+            .links
+            .iter()
+            .filter(|x| seen.insert(&x.link.ident))
+            .cloned()
+            .partition(|x| idents_b.contains(&x.link.ident));
+
+        let mut seen = HashSet::new();
+        let only_b = b
+            .links
+            .iter()
+            .filter(|x| seen.insert(&x.link.ident) && !idents_a.contains(&x.link.ident))
+            .cloned()
+            .collect();
+
+        let mut cmp = Self {
+            only_a,
+            only_b,
+            both,
+        };
+        for links in [&mut cmp.only_a, &mut cmp.only_b, &mut cmp.both] {
+            links.sort_by_key(|x| std::cmp::Reverse(x.details.stargazer_count));
+        }
+        cmp
+    }
+}
+
+#[component]
+fn CompareSide<'a>(side: &'a Result<Arc<FullReadmeRepo>, HtmlError>) -> Element {
+    match side {
+        Ok(repo) => rsx! {
+            RepoLinkBox {
+                repo: repo,
+            }
+        },
+        Err(error) => rsx! {
+            HtmlErrorView {
+                error: error,
+            }
+        },
+    }
+}
+
+#[component]
+fn CompareColumn<'a>(id: &'a str, title: &'a str, links: &'a [FullRepoLink]) -> Element {
+    rsx! {
+        div {
+            id: "{id}",
+            class: "column is-one-third",
+            h2 {
+                class: "title is-5",
+                "{title}"
+                span {
+                    class: "tag is-light ml-2",
+                    "{links.len()}"
+                }
+            }
+            div {
+                class: "table-container",
+                LinksTable {
+                    links: links,
+                    show_category: false,
+                }
+            }
+        }
+    }
+}
+
+/// Page comparing the links of two lists.
+///
+/// A list that failed to load is shown as an error on its side.
+pub fn render_compare_page(
+    a: Result<Arc<FullReadmeRepo>, HtmlError>,
+    b: Result<Arc<FullReadmeRepo>, HtmlError>,
+) -> String {
+    let cmp = match (&a, &b) {
+        (Ok(a), Ok(b)) => Some(LinkComparison::new(a, b)),
+        _ => None,
+    };
+    let comparison = match &cmp {
+        Some(cmp) => {
+            rsx! {
+                div {
+                    class: "columns",
+                    CompareColumn {
+                        id: "only-a",
+                        title: "Only in A",
+                        links: &cmp.only_a,
+                    }
+                    CompareColumn {
+                        id: "only-b",
+                        title: "Only in B",
+                        links: &cmp.only_b,
+                    }
+                    CompareColumn {
+                        id: "in-both",
+                        title: "In both",
+                        links: &cmp.both,
+                    }
+                }
+            }
+        }
+        None => rsx! {},
+    };
+
+    let output = rsx! {
+        PageLayout {
+            title: "Compare lists",
+            div {
+                h1 {
+                    class: "title",
+                    "Compare lists"
+                }
+                div {
+                    class: "columns",
+                    div {
+                        class: "column",
+                        h2 {
+                            class: "title is-5",
+                            "A"
+                        }
+                        CompareSide {
+                            side: &a,
+                        }
+                    }
+                    div {
+                        class: "column",
+                        h2 {
+                            class: "title is-5",
+                            "B"
+                        }
+                        CompareSide {
+                            side: &b,
+                        }
+                    }
+                }
+
+                comparison
+            }
+        }
+    };
     output.render()
 }
 
@@ -1125,12 +2394,23 @@ fn HtmlErrorView<'a>(error: &'a HtmlError) -> Element {
         rsx! {}
     };
 
+    let request_id = super::request_id::current().unwrap_or_default();
+
     rsx! {
         p {
             class: "notification is-danger",
 
             "{error.message}"
 
+            if !request_id.is_empty() {
+                br {}
+                small {
+                    class: "is-size-7",
+                    "Request ID: {request_id}"
+                }
+            } else {
+            }
+
             details
         }
     }
@@ -1145,6 +2425,77 @@ pub fn render_html_error_standalone(error: &HtmlError) -> String {
     output.render()
 }
 
+/// Page shown when a repo does not look like an awesome list.
+pub fn render_not_awesome_list_page(ident: &RepoIdent) -> String {
+    let name = ident.name();
+    let output = rsx! {
+        PageLayout {
+            title: "Not an awesome list",
+            div {
+                class: "content",
+                h1 {
+                    class: "title",
+                    "Not an awesome list"
+                }
+                p {
+                    "The README of "
+                    a {
+                        href: "{ident.url()}",
+                        target: "_blank",
+                        "{name}"
+                    }
+                    " does not link to any repositories."
+                }
+                p {
+                    "Awesome lists are curated collections of links to projects about a "
+                    "topic, usually in a repository named "
+                    code {
+                        "awesome-<topic>"
+                    }
+                    ". awesomelify shows the linked repositories with their stars, "
+                    "activity and languages."
+                }
+                a {
+                    href: "/",
+                    class: "button is-primary",
+                    "Search for another list"
+                }
+            }
+        }
+    };
+    output.render()
+}
+
+pub fn render_not_found_page() -> String {
+    let output = rsx! {
+        PageLayout {
+            title: "Page not found",
+            div {
+                class: "content",
+                h1 {
+                    class: "title",
+                    "Page not found"
+                }
+                p {
+                    "The page you are looking for does not exist. "
+                    "Search for a list instead, or go back to the "
+                    a {
+                        href: "/",
+                        "homepage"
+                    }
+                    "."
+                }
+            }
+            div {
+                class: "box",
+                style: "max-width: 500px",
+                SearchBar {}
+            }
+        }
+    };
+    output.render()
+}
+
 pub fn render_html_error_page(error: &HtmlError) -> String {
     let output = rsx! {
         PageLayout {
@@ -1156,3 +2507,257 @@ pub fn render_html_error_page(error: &HtmlError) -> String {
     };
     output.render()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_remaining() {
+        use std::time::Duration;
+
+        assert_eq!(format_remaining(Duration::from_secs(5)), "~1 minute");
+        assert_eq!(
+            format_remaining(Duration::from_secs(12 * 60 - 30)),
+            "~12 minutes"
+        );
+        assert_eq!(
+            format_remaining(Duration::from_secs(3 * 60 * 60)),
+            "~3 hours"
+        );
+    }
+
+    #[test]
+    fn test_pagination_items() {
+        assert_eq!(pagination_items(1, 1), vec![Some(1)]);
+        assert_eq!(
+            pagination_items(1, 5),
+            vec![Some(1), Some(2), None, Some(5)]
+        );
+        assert_eq!(
+            pagination_items(4, 9),
+            vec![Some(1), None, Some(3), Some(4), Some(5), None, Some(9)]
+        );
+        assert_eq!(
+            pagination_items(3, 4),
+            vec![Some(1), Some(2), Some(3), Some(4)]
+        );
+    }
+
+    #[test]
+    fn test_link_tree_dedup_global() {
+        let link = |repo: &str, section: &str| {
+            FullRepoLink::new_test(RepoIdent::new_github("org", repo), &[section])
+        };
+        let links = vec![link("a", "Zeta"), link("a", "Alpha"), link("b", "Zeta")];
+
+        let names = |tree: &LinkTree| {
+            tree.categories
+                .iter()
+                .map(|(name, tree)| {
+                    let repos = tree
+                        .links
+                        .iter()
+                        .map(|x| x.link.ident.repo.as_str())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    format!("{name}:{repos}")
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut tree = group_links_by_category(&links);
+        assert_eq!(names(&tree), vec!["Alpha:a", "Zeta:a,b"]);
+
+        tree.dedup_global(&mut HashSet::new());
+        assert_eq!(names(&tree), vec!["Alpha:a", "Zeta:b"]);
+    }
+
+    #[test]
+    fn test_link_tree_total_link_count() {
+        let link = |repo: &str, section: &[&str]| {
+            FullRepoLink::new_test(RepoIdent::new_github("org", repo), section)
+        };
+        let links = vec![
+            link("a", &["Tools"]),
+            link("b", &["Tools", "Cli"]),
+            link("c", &["Tools", "Cli"]),
+            link("d", &["Other"]),
+        ];
+
+        let tree = group_links_by_category(&links);
+        assert_eq!(tree.total_link_count(), 4);
+        let (name, tools) = &tree.categories[1];
+        assert_eq!(name, "Tools");
+        assert_eq!(tools.total_link_count(), 3);
+        assert_eq!(tools.categories[0].1.total_link_count(), 2);
+    }
+
+    #[test]
+    fn test_group_links_many_categories() {
+        // Would take noticeably long with quadratic category lookups.
+        let links = (0..20_000)
+            .map(|n| {
+                let category = format!("cat{:05}", n % 10_000);
+                FullRepoLink::new_test(
+                    RepoIdent::new_github("org", format!("repo{n}")),
+                    &[&category, "sub"],
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut tree = group_links_by_category(&links);
+        assert_eq!(tree.categories.len(), 10_000);
+        assert_eq!(tree.total_link_count(), 20_000);
+        assert_eq!(tree.categories[0].0, "cat00000");
+        assert_eq!(tree.categories[9_999].0, "cat09999");
+        assert_eq!(tree.categories[42].1.categories.len(), 1);
+        assert_eq!(tree.categories[42].1.total_link_count(), 2);
+
+        // Lookups still work after sorting.
+        let category = tree.category_mut("cat00042") as *const LinkTree;
+        assert!(std::ptr::eq(category, &tree.categories[42].1));
+    }
+
+    #[test]
+    fn test_order_categories() {
+        let mut repo = crate::source::ReadmeRepo::new_test(
+            RepoIdent::new_github("org", "list"),
+            ["a", "b", "c", "d"]
+                .into_iter()
+                .map(|x| RepoIdent::new_github("org", x))
+                .collect(),
+        );
+        for (link, section) in repo
+            .repo_links
+            .iter_mut()
+            .zip(["Zeta", "Beta", "Zeta", "Alpha"])
+        {
+            link.section = vec![section.to_string()];
+        }
+        let links = repo
+            .repo_links
+            .iter()
+            // Filtered out, must not affect the order.
+            .filter(|link| link.ident.repo != "b")
+            .map(|link| FullRepoLink::new_test(link.ident.clone(), &[&link.section[0]]))
+            .collect::<Vec<_>>();
+        let repo = FullReadmeRepo {
+            repo,
+            links,
+            not_found: Vec::new(),
+            rate_limit_reset_at: None,
+        };
+        let names = |tree: &LinkTree| {
+            tree.categories
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let mut tree = group_links_by_category(&repo.links);
+        assert_eq!(names(&tree), vec!["Alpha", "Zeta"]);
+
+        order_categories(&mut tree, &repo, CategoryOrder::Readme);
+        assert_eq!(names(&tree), vec!["Zeta", "Alpha"]);
+        assert!(std::ptr::eq(
+            tree.category_mut("Alpha") as *const LinkTree,
+            &tree.categories[1].1
+        ));
+
+        order_categories(&mut tree, &repo, CategoryOrder::Alpha);
+        assert_eq!(names(&tree), vec!["Alpha", "Zeta"]);
+    }
+
+    #[test]
+    fn test_prepare_links_stable_sort() {
+        let idents = ["c", "a", "d", "b"].map(|x| RepoIdent::new_github("org", x));
+        let readme = crate::source::ReadmeRepo::new_test(
+            RepoIdent::new_github("org", "list"),
+            idents.to_vec(),
+        );
+        let links = readme
+            .repo_links
+            .iter()
+            .map(|link| {
+                let mut link = FullRepoLink::new_test(link.ident.clone(), &["section"]);
+                link.details.stargazer_count = if link.link.ident.repo == "d" { 20 } else { 10 };
+                link.details.fork_count = 1;
+                link.details.last_pushed_at = Some(time::macros::datetime!(2024-07-01 00:00 UTC));
+                link
+            })
+            .collect::<Vec<_>>();
+
+        for (sort, expected) in [
+            ("stars", ["d", "a", "b", "c"]),
+            ("stars&dir=asc", ["a", "b", "c", "d"]),
+            ("forks", ["a", "b", "c", "d"]),
+            ("updated", ["a", "b", "c", "d"]),
+        ] {
+            // The README order must not matter.
+            for reverse in [false, true] {
+                let mut links = links.clone();
+                if reverse {
+                    links.reverse();
+                }
+                let mut repo = FullReadmeRepo {
+                    repo: readme.clone(),
+                    links,
+                    not_found: Vec::new(),
+                    rate_limit_reset_at: None,
+                };
+                let query: RepoPageQuery =
+                    serde_urlencoded::from_str(&format!("sort={sort}")).unwrap();
+
+                let mut tree = prepare_links(&mut repo, &query);
+                let names = |links: &[FullRepoLink]| {
+                    links
+                        .iter()
+                        .map(|x| x.link.ident.repo.clone())
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(names(&repo.links), expected, "{sort}");
+                assert_eq!(
+                    names(&tree.category_mut("section").links),
+                    expected,
+                    "{sort}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_link_comparison() {
+        let list = |links: &[&str]| {
+            let repo = crate::source::ReadmeRepo::new_test(
+                RepoIdent::new_github("org", "list"),
+                links
+                    .iter()
+                    .map(|x| RepoIdent::new_github("org", *x))
+                    .collect(),
+            );
+            let links = repo
+                .repo_links
+                .iter()
+                .map(|link| FullRepoLink::new_test(link.ident.clone(), &["section"]))
+                .collect();
+            FullReadmeRepo {
+                repo,
+                links,
+                not_found: Vec::new(),
+                rate_limit_reset_at: None,
+            }
+        };
+        let repos = |links: &[FullRepoLink]| {
+            links
+                .iter()
+                .map(|x| x.link.ident.repo.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let cmp = LinkComparison::new(&list(&["a", "b", "b"]), &list(&["b", "c"]));
+        assert_eq!(repos(&cmp.only_a), vec!["a"]);
+        assert_eq!(repos(&cmp.only_b), vec!["c"]);
+        assert_eq!(repos(&cmp.both), vec!["b"]);
+    }
+}
@@ -0,0 +1,319 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use time::OffsetDateTime;
+
+use super::{github::ReadmeResponse, ReadmeLocation, RepoDetails, RepoIdent, SourceError};
+
+/// File loaded as README if the location does not specify a path.
+///
+/// Unlike Github, the Gitea API does not detect the README of a repo.
+const DEFAULT_README_PATH: &str = "README.md";
+
+/// Client for the REST API of Gitea instances, like Codeberg.
+#[derive(Clone)]
+pub struct GiteaClient {
+    client: reqwest::Client,
+    /// Overrides the API base URL of all hosts.
+    api_url: Option<String>,
+}
+
+impl Default for GiteaClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GiteaClient {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent("awesomelify")
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap();
+        Self {
+            client,
+            api_url: None,
+        }
+    }
+
+    /// Use a different base URL instead of `https://<host>/api/v1`.
+    #[cfg(test)]
+    pub(crate) fn with_api_url(mut self, url: impl Into<String>) -> Self {
+        self.api_url = Some(url.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    fn repo_api_url(&self, ident: &RepoIdent) -> String {
+        let base = match &self.api_url {
+            Some(url) => url.clone(),
+            None => format!("https://{}/api/v1", ident.domain()),
+        };
+        format!("{base}/repos/{}/{}", ident.owner, ident.repo)
+    }
+
+    /// Fetch the raw README of a repository.
+    ///
+    /// Loads `README.md`, unless the location specifies a path.
+    pub async fn repo_readme(
+        &self,
+        ident: &RepoIdent,
+        location: &ReadmeLocation,
+        etag: Option<&str>,
+    ) -> Result<ReadmeResponse, SourceError> {
+        let path = location
+            .encoded_path()
+            .unwrap_or_else(|| DEFAULT_README_PATH.to_string());
+        let url = format!("{}/raw/{path}", self.repo_api_url(ident));
+        let mut req = self.client.get(&url);
+        if let Some(git_ref) = &location.git_ref {
+            req = req.query(&[("ref", git_ref)]);
+        }
+        if let Some(etag) = etag {
+            req = req.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+
+        let res = req.send().await?;
+        if res.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(ReadmeResponse::NotModified);
+        }
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(SourceError::NotFound(ident.clone()));
+        }
+
+        let res = res.error_for_status()?;
+        let etag = res
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|x| x.to_str().ok())
+            .map(|x| x.to_string());
+        let content = res.bytes().await?;
+        let content = String::from_utf8(content.into()).context("non-UTF8 readme")?;
+
+        Ok(ReadmeResponse::Modified { content, etag })
+    }
+
+    pub async fn repo_details(
+        &self,
+        ident: &RepoIdent,
+    ) -> Result<Option<RepoDetails>, SourceError> {
+        let res = self.client.get(self.repo_api_url(ident)).send().await?;
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let repo = res
+            .error_for_status()?
+            .json::<RepoData>()
+            .await
+            .context("failed to parse Gitea repo response")?;
+
+        // Gitea redirects renamed and transferred repos to the new location.
+        let renamed_to = (!repo.owner.login.eq_ignore_ascii_case(&ident.owner)
+            || !repo.name.eq_ignore_ascii_case(&ident.repo))
+        .then(|| RepoIdent {
+            owner: repo.owner.login,
+            repo: repo.name,
+            ..ident.clone()
+        });
+
+        let language = Some(repo.language).filter(|x| !x.is_empty());
+        let now = OffsetDateTime::now_utc();
+        let details = RepoDetails {
+            ident: ident.clone(),
+            renamed_to,
+            description: Some(repo.description).filter(|x| !x.trim().is_empty()),
+            // Gitea does not report pushes separately.
+            last_pushed_at: repo.updated_at,
+            total_pull_requests: repo.open_pr_counter,
+            stargazer_count: repo.stars_count,
+            fork_count: repo.forks_count,
+            issues: repo.open_issues_count,
+            last_pullrequest_merged_at: None,
+            languages: language.iter().cloned().collect(),
+            primary_language: language,
+            language_sizes: Vec::new(),
+            archived: repo.archived,
+            license: repo.licenses.into_iter().next(),
+            topics: repo.topics,
+            first_seen_at: Some(now),
+            updated_at: now,
+        };
+
+        Ok(Some(details))
+    }
+}
+
+/// Response of `GET /repos/{owner}/{repo}`.
+#[derive(serde::Deserialize, Debug)]
+struct RepoData {
+    owner: Owner,
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    stars_count: u32,
+    #[serde(default)]
+    forks_count: u32,
+    #[serde(default)]
+    open_issues_count: u32,
+    #[serde(default)]
+    open_pr_counter: u32,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    language: String,
+    /// SPDX identifiers, only reported by recent Gitea versions.
+    #[serde(default)]
+    licenses: Vec<String>,
+    #[serde(default)]
+    topics: Vec<String>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    updated_at: Option<OffsetDateTime>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct Owner {
+    login: String,
+}
+
+/// Minimal mock of the Gitea API for tests.
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use axum::{extract::Path, http::StatusCode, response::IntoResponse, routing, Json, Router};
+
+    use super::GiteaClient;
+
+    /// Name of the repo that does not exist.
+    pub(crate) const MISSING_REPO: &str = "missing";
+
+    #[derive(Clone)]
+    pub(crate) struct MockGitea {
+        pub url: String,
+        /// Number of README requests received.
+        pub readme_requests: Arc<AtomicUsize>,
+    }
+
+    impl MockGitea {
+        /// Start a server that returns `readme` as the `README.md` of every
+        /// repo.
+        ///
+        /// All repos exist, with 5 stars, except for [`MISSING_REPO`].
+        pub(crate) async fn start(readme: &str) -> Self {
+            let readme_requests = Arc::new(AtomicUsize::new(0));
+
+            let readme = readme.to_string();
+            let readme_handler = {
+                let counter = readme_requests.clone();
+                move |Path((_owner, repo, path)): Path<(String, String, String)>| async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    if repo == MISSING_REPO || path != "README.md" {
+                        return StatusCode::NOT_FOUND.into_response();
+                    }
+                    readme.into_response()
+                }
+            };
+            let repo_handler = |Path((owner, repo)): Path<(String, String)>| async move {
+                if repo == MISSING_REPO {
+                    return StatusCode::NOT_FOUND.into_response();
+                }
+                Json(serde_json::json!({
+                    "owner": { "login": owner },
+                    "name": repo,
+                    "description": "description",
+                    "stars_count": 5,
+                    "forks_count": 2,
+                    "open_issues_count": 1,
+                    "open_pr_counter": 0,
+                    "archived": false,
+                    "language": "Go",
+                    "licenses": ["MIT"],
+                    "topics": ["cli"],
+                    "updated_at": "2024-07-01T10:00:00+02:00",
+                }))
+                .into_response()
+            };
+
+            let app = Router::new()
+                .route("/repos/:owner/:repo", routing::get(repo_handler))
+                .route(
+                    "/repos/:owner/:repo/raw/*path",
+                    routing::get(readme_handler),
+                );
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.unwrap();
+            });
+
+            Self {
+                url,
+                readme_requests,
+            }
+        }
+
+        pub(crate) fn client(&self) -> GiteaClient {
+            GiteaClient::new().with_api_url(&self.url)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::{mock::MockGitea, *};
+    use crate::source::Source;
+
+    #[tokio::test]
+    async fn test_gitea_client_repo_details() {
+        let gitea = MockGitea::start("").await;
+        let ident = RepoIdent::new(Source::Codeberg, "org", "repo");
+
+        let details = gitea.client().repo_details(&ident).await.unwrap().unwrap();
+        assert_eq!(details.ident, ident);
+        assert_eq!(details.renamed_to, None);
+        assert_eq!(details.stargazer_count, 5);
+        assert_eq!(details.primary_language.as_deref(), Some("Go"));
+        assert_eq!(details.license.as_deref(), Some("MIT"));
+        assert_eq!(
+            details.last_pushed_at,
+            Some(datetime!(2024-07-01 08:00 UTC))
+        );
+
+        let missing = RepoIdent::new(Source::Codeberg, "org", mock::MISSING_REPO);
+        assert_eq!(gitea.client().repo_details(&missing).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_gitea_client_readme() {
+        let gitea = MockGitea::start("# Awesome\n").await;
+        let ident = RepoIdent::new(Source::Codeberg, "org", "repo");
+
+        let res = gitea
+            .client()
+            .repo_readme(&ident, &ReadmeLocation::default(), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            ReadmeResponse::Modified {
+                content: "# Awesome\n".to_string(),
+                etag: None,
+            }
+        );
+
+        let location = ReadmeLocation {
+            path: Some("docs/list.md".to_string()),
+            git_ref: None,
+        };
+        let res = gitea.client().repo_readme(&ident, &location, None).await;
+        assert!(matches!(res, Err(SourceError::NotFound(_))));
+    }
+}
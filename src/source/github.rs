@@ -1,5 +1,8 @@
 use std::{
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, SystemTime},
 };
 
@@ -8,47 +11,52 @@ use base64::Engine;
 use query_repo_details::RepoDetailsResponse;
 use reqwest::RequestBuilder;
 
-use crate::source::RepoDetails;
+use crate::source::{LanguageSize, ReadmeLocation, RepoDetails};
 
-use super::{RateLimitError, RepoIdent};
+use super::{throttle::Throttle, RateLimitError, RepoIdent, SourceError};
 
 #[derive(Clone)]
 pub struct GithubClient {
     client: reqwest::Client,
-    rate_limited_until: Arc<Mutex<Option<SystemTime>>>,
+    /// Available tokens.
+    ///
+    /// Contains a single anonymous entry if no tokens are configured.
+    tokens: Arc<Vec<TokenState>>,
+    next_token: Arc<AtomicUsize>,
+    /// Overrides the api.github.com base URL.
+    api_url: Option<String>,
+    /// Paces outgoing requests, to avoid Github's secondary rate limits.
+    throttle: Option<Arc<Throttle>>,
+    /// Attempts per request for transient errors.
+    max_attempts: u32,
+    /// Delay before the first retry. Doubles with each retry.
+    retry_delay: Duration,
 }
 
-impl GithubClient {
-    pub fn from_env() -> Self {
-        let token = std::env::var("GITHUB_TOKEN").ok();
-        Self::new(token)
-    }
-
-    pub fn new(token: Option<String>) -> Self {
-        let mut headers = reqwest::header::HeaderMap::new();
-        if let Some(token) = token {
-            headers.insert(
-                reqwest::header::AUTHORIZATION,
-                format!("Bearer {}", token)
-                    .parse()
-                    .expect("Invalid Github auth token"),
-            );
-        }
+/// Default maximum of requests per second.
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 10.0;
+/// Default number of attempts per request for transient errors.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// Upper bound of the exponential backoff between attempts.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// Number of languages fetched per repo, largest first.
+const LANGUAGE_COUNT: u32 = 10;
 
-        let client = reqwest::Client::builder()
-            .user_agent("awesomelify")
-            .connect_timeout(Duration::from_secs(10))
-            .default_headers(headers)
-            .timeout(Duration::from_secs(30))
-            .build()
-            .unwrap();
-        GithubClient {
-            client,
-            rate_limited_until: Arc::new(Mutex::new(None)),
+/// A Github token with its rate limit state.
+struct TokenState {
+    token: Option<String>,
+    rate_limited_until: Mutex<Option<SystemTime>>,
+}
+
+impl TokenState {
+    fn new(token: Option<String>) -> Self {
+        Self {
+            token,
+            rate_limited_until: Mutex::new(None),
         }
     }
 
-    pub fn rate_limited_until(&self) -> Option<SystemTime> {
+    fn rate_limited_until(&self) -> Option<SystemTime> {
         let mut lock = self.rate_limited_until.lock().unwrap();
 
         if let Some(target) = *lock {
@@ -70,44 +78,237 @@ impl GithubClient {
             *lock = Some(until);
         }
     }
+}
+
+fn is_transient_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 502..=504)
+}
+
+/// Determine when a rate limit resets from the headers of a 403/429 response.
+///
+/// Secondary rate limits send `retry-after`, either in seconds or as an HTTP
+/// date. The primary rate limit sends `x-ratelimit-reset` as a unix timestamp.
+///
+/// Values too large to represent are treated as missing.
+fn rate_limit_reset(headers: &reqwest::header::HeaderMap, now: SystemTime) -> Option<SystemTime> {
+    let header = |name: &str| headers.get(name).and_then(|x| x.to_str().ok());
+
+    if let Some(value) = header("retry-after").map(str::trim) {
+        if let Ok(seconds) = value.parse::<u64>() {
+            return now.checked_add(Duration::from_secs(seconds));
+        }
+        if let Ok(date) = httpdate::parse_http_date(value) {
+            return Some(date);
+        }
+    }
+
+    header("x-ratelimit-reset")
+        .and_then(|x| x.trim().parse::<u64>().ok())
+        .and_then(|reset| SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(reset)))
+}
+
+impl GithubClient {
+    /// Create a client with the tokens from the `GITHUB_TOKEN` env var.
+    ///
+    /// Multiple tokens can be provided as a comma-separated list.
+    pub fn from_env() -> Self {
+        let tokens = std::env::var("GITHUB_TOKEN")
+            .map(|x| x.split(',').map(|x| x.trim().to_string()).collect())
+            .unwrap_or_default();
+        Self::new(tokens)
+    }
+
+    /// Create a new client.
+    ///
+    /// Requests are distributed across all provided tokens, skipping tokens
+    /// that are currently rate limited.
+    pub fn new(tokens: Vec<String>) -> Self {
+        let mut tokens: Vec<_> = tokens
+            .into_iter()
+            .filter(|x| !x.is_empty())
+            .map(|x| TokenState::new(Some(x)))
+            .collect();
+        if tokens.is_empty() {
+            tokens.push(TokenState::new(None));
+        }
+
+        let client = reqwest::Client::builder()
+            .user_agent("awesomelify")
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap();
+        GithubClient {
+            client,
+            tokens: Arc::new(tokens),
+            next_token: Arc::new(AtomicUsize::new(0)),
+            api_url: None,
+            throttle: Some(Arc::new(Throttle::new(DEFAULT_REQUESTS_PER_SECOND))),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            retry_delay: Duration::from_millis(500),
+        }
+    }
 
-    async fn fetch(&self, builder: RequestBuilder) -> Result<reqwest::Response, anyhow::Error> {
-        if let Some(until) = self.rate_limited_until() {
-            return Err(RateLimitError {
-                message: "Github API rate limit exceeded".to_string(),
-                reset_at: Some(until),
+    /// Set how often requests are attempted on transient errors, like
+    /// timeouts or 503 responses.
+    ///
+    /// 1 disables retries.
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Limit outgoing requests to the given number per second.
+    ///
+    /// Zero disables the limit.
+    pub fn with_requests_per_second(mut self, rate: f64) -> Self {
+        self.throttle = (rate > 0.0).then(|| Arc::new(Throttle::new(rate)));
+        self
+    }
+
+    /// Use a different base URL instead of `https://api.github.com`.
+    ///
+    /// Does not affect Github Enterprise repos.
+    #[cfg(test)]
+    pub(crate) fn with_api_url(mut self, url: impl Into<String>) -> Self {
+        self.api_url = Some(url.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Returns the time until which all tokens are rate limited.
+    ///
+    /// Returns `None` if at least one token is usable.
+    pub fn rate_limited_until(&self) -> Option<SystemTime> {
+        let mut min: Option<SystemTime> = None;
+        for state in self.tokens.iter() {
+            let until = state.rate_limited_until()?;
+            min = Some(min.map_or(until, |x| x.min(until)));
+        }
+        min
+    }
+
+    /// Clear the rate limits of all tokens, as if they had reset.
+    #[cfg(test)]
+    pub(crate) fn reset_rate_limits(&self) {
+        for state in self.tokens.iter() {
+            *state.rate_limited_until.lock().unwrap() = None;
+        }
+    }
+
+    /// Select the next token that is not rate limited, round-robin.
+    fn select_token(&self) -> Option<&TokenState> {
+        let count = self.tokens.len();
+        let start = self.next_token.fetch_add(1, Ordering::Relaxed);
+
+        (0..count)
+            .map(|offset| &self.tokens[(start + offset) % count])
+            .find(|state| state.rate_limited_until().is_none())
+    }
+
+    async fn fetch(&self, builder: RequestBuilder) -> Result<reqwest::Response, SourceError> {
+        // Each attempt either succeeds or marks a token as rate limited, so
+        // at most one attempt per token is needed.
+        for _ in 0..self.tokens.len() {
+            let Some(state) = self.select_token() else {
+                break;
+            };
+
+            let mut req = builder
+                .try_clone()
+                .context("could not clone Github request")?;
+            if let Some(token) = &state.token {
+                req = req.bearer_auth(token);
+            }
+
+            let res = self.send_with_retry(req).await?;
+            let status = res.status();
+            if !status.is_success() && (status == 403 || status == 429) {
+                if let Some(reset_at) = rate_limit_reset(res.headers(), SystemTime::now()) {
+                    state.set_rate_limited_until(reset_at);
+                    tracing::debug!("Github token rate limited, trying next token");
+                    continue;
+                }
             }
-            .into());
+            return Ok(res);
         }
 
-        let res = builder.send().await?;
-        let status = res.status();
-        if !status.is_success() && (status == 403 || status == 429) {
-            let reset_at = res
-                .headers()
-                .get("x-ratelimit-reset")
-                .and_then(|x| x.to_str().ok())
-                .and_then(|x| x.parse::<u64>().ok());
-
-            if let Some(reset) = reset_at {
-                let reset_at = SystemTime::UNIX_EPOCH + Duration::from_secs(reset);
-                self.set_rate_limited_until(reset_at);
-
-                return Err(RateLimitError {
-                    message: "Github API rate limit exceeded".to_string(),
-                    reset_at: Some(reset_at),
+        Err(SourceError::RateLimited(RateLimitError {
+            message: "Github API rate limit exceeded".to_string(),
+            reset_at: self.rate_limited_until(),
+        }))
+    }
+
+    /// Send a request, retrying transient errors with exponential backoff.
+    ///
+    /// Connection errors, timeouts and 502/503/504 responses are retried.
+    /// Other responses, including rate limits, are returned as is.
+    async fn send_with_retry(
+        &self,
+        builder: RequestBuilder,
+    ) -> Result<reqwest::Response, SourceError> {
+        let mut delay = self.retry_delay;
+        let mut attempt = 1;
+        loop {
+            if let Some(throttle) = &self.throttle {
+                throttle.acquire().await;
+            }
+
+            let req = builder
+                .try_clone()
+                .context("could not clone Github request")?;
+            let is_last = attempt >= self.max_attempts;
+            match req.send().await {
+                Ok(res) if is_last || !is_transient_status(res.status()) => return Ok(res),
+                Err(err) if is_last || !(err.is_timeout() || err.is_connect()) => {
+                    return Err(err.into())
+                }
+                Ok(res) => {
+                    tracing::debug!(status=%res.status(), attempt, "transient Github error, retrying");
+                }
+                Err(err) => {
+                    tracing::debug!(attempt, "Github request failed, retrying: {}", err);
                 }
-                .into());
             }
+
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+            attempt += 1;
         }
-        Ok(res)
     }
 
+    /// Base URL of the REST API for the host of the given repo.
+    fn rest_api_url(&self, ident: &RepoIdent) -> String {
+        match (&ident.host, &self.api_url) {
+            (Some(host), _) => format!("https://{host}/api/v3"),
+            (None, Some(url)) => url.clone(),
+            (None, None) => "https://api.github.com".to_string(),
+        }
+    }
+
+    /// URL of the GraphQL API for the host of the given repo.
+    fn graphql_api_url(&self, ident: &RepoIdent) -> String {
+        match (&ident.host, &self.api_url) {
+            (Some(host), _) => format!("https://{host}/api/graphql"),
+            (None, Some(url)) => format!("{url}/graphql"),
+            (None, None) => "https://api.github.com/graphql".to_string(),
+        }
+    }
+
+    /// Run a GraphQL query.
+    ///
+    /// Returns `None` if Github reports that a requested object was not found.
     async fn graphql<V, D>(
         &self,
+        url: &str,
         query: impl Into<String>,
         variables: V,
-    ) -> Result<D, anyhow::Error>
+    ) -> Result<Option<D>, SourceError>
     where
         V: serde::Serialize,
         D: serde::de::DeserializeOwned,
@@ -119,7 +320,7 @@ impl GithubClient {
 
         let req = self
             .client
-            .post("https://api.github.com/graphql")
+            .post(url)
             .header(reqwest::header::CONTENT_TYPE, "application/json")
             .header(reqwest::header::ACCEPT, "application/json")
             .json(&query);
@@ -128,79 +329,138 @@ impl GithubClient {
 
         if !res.status().is_success() {
             let error = res.text().await?;
-            anyhow::bail!("GraphQL request failed: {}", error);
+            return Err(anyhow::anyhow!("GraphQL request failed: {}", error).into());
         }
 
         let body = res.text().await?;
-        let data: GraphqlResponse<D> = match deserialize_json(&body) {
-            Ok(v) => v,
-            Err(err) => Err(err).context("failed to parse json response")?,
-        };
+        let data: GraphqlResponse<D> =
+            deserialize_json(&body).context("failed to parse json response")?;
 
-        data.data.context("GraphQL response returned no data")
+        match data.data {
+            Some(data) => Ok(Some(data)),
+            None => {
+                let errors = data.errors.unwrap_or_default();
+                if errors
+                    .iter()
+                    .any(|x| x.kind.as_deref() == Some("NOT_FOUND"))
+                {
+                    return Ok(None);
+                }
+                let messages = errors
+                    .iter()
+                    .map(|x| x.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                Err(anyhow::anyhow!("GraphQL response returned no data: {}", messages).into())
+            }
+        }
     }
 
-    pub async fn repo_readme(&self, ident: &RepoIdent) -> Result<String, anyhow::Error> {
-        let url = format!(
-            "https://api.github.com/repos/{}/{}/readme",
-            ident.owner, ident.repo
+    /// Fetch the README of a repository.
+    ///
+    /// Uses the README detected by Github, unless the location specifies a
+    /// path, which is then loaded via the contents API.
+    ///
+    /// If an `etag` from a previous fetch is provided, a conditional request
+    /// is made, and [`ReadmeResponse::NotModified`] is returned if the README
+    /// did not change.
+    pub async fn repo_readme(
+        &self,
+        ident: &RepoIdent,
+        location: &ReadmeLocation,
+        etag: Option<&str>,
+    ) -> Result<ReadmeResponse, SourceError> {
+        let base = format!(
+            "{}/repos/{}/{}",
+            self.rest_api_url(ident),
+            ident.owner,
+            ident.repo
         );
-        let req = self.client.get(&url);
-        let res = self
-            .fetch(req)
-            .await?
-            .error_for_status()?
-            .json::<ReadmeData>()
-            .await?;
+        let url = match location.encoded_path() {
+            Some(path) => format!("{base}/contents/{path}"),
+            None => format!("{base}/readme"),
+        };
+        let mut req = self.client.get(&url);
+        if let Some(git_ref) = &location.git_ref {
+            req = req.query(&[("ref", git_ref)]);
+        }
+        if let Some(etag) = etag {
+            req = req.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
 
-        if res.encoding != "base64" {
-            anyhow::bail!("unexpected encoding: {}", res.encoding);
+        let res = self.fetch(req).await?;
+        if res.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(ReadmeResponse::NotModified);
+        }
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(SourceError::NotFound(ident.clone()));
         }
 
-        let content = base64::engine::general_purpose::STANDARD
-            .decode(res.content.replace("\n", ""))
-            .context("failed to decode README base64")?;
+        let res = res.error_for_status()?;
+        let etag = res
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|x| x.to_str().ok())
+            .map(|x| x.to_string());
+        let res = res.json::<ReadmeData>().await?;
 
-        let content = String::from_utf8(content).context("non-UTF8 readme")?;
+        let content = match res.encoding.as_str() {
+            "base64" => {
+                let content = base64::engine::general_purpose::STANDARD
+                    .decode(res.content.replace("\n", ""))
+                    .context("failed to decode README base64")?;
+                String::from_utf8(content).context("non-UTF8 readme")?
+            }
+            // Content is not encoded.
+            "" | "none" => res.content,
+            other => return Err(SourceError::UnsupportedEncoding(other.to_string())),
+        };
 
-        Ok(content)
+        Ok(ReadmeResponse::Modified { content, etag })
     }
 
     pub async fn repo_details(
         &self,
         ident: &RepoIdent,
-    ) -> Result<Option<RepoDetails>, anyhow::Error> {
+    ) -> Result<Option<RepoDetails>, SourceError> {
         let res = self
             .graphql::<_, RepoDetailsResponse>(
+                &self.graphql_api_url(ident),
                 query_repo_details::REPO_DETAILS_QUERY,
                 RepoVariables {
                     owner: ident.owner.clone(),
                     repo: ident.repo.clone(),
+                    language_count: LANGUAGE_COUNT,
                 },
             )
-            .await;
+            .await?;
 
-        let data = match res {
-            Ok(v) => v,
-            Err(err) => {
-                if err
-                    .to_string()
-                    .to_lowercase()
-                    .contains("could not resolve to a repository")
-                {
-                    return Ok(None);
-                } else {
-                    return Err(err);
-                }
-            }
+        let Some(data) = res else {
+            return Ok(None);
         };
-
         let Some(repo) = data.repository else {
             return Ok(None);
         };
 
+        // Github resolves renamed and transferred repos to the new location.
+        // Names are case-insensitive, so only a different spelling is a rename.
+        let renamed_to = match (repo.owner, repo.name) {
+            (Some(owner), Some(name))
+                if !owner.login.eq_ignore_ascii_case(&ident.owner)
+                    || !name.eq_ignore_ascii_case(&ident.repo) =>
+            {
+                Some(RepoIdent {
+                    owner: owner.login,
+                    repo: name,
+                    ..ident.clone()
+                })
+            }
+            _ => None,
+        };
+
         let data = RepoDetails {
             ident: ident.clone(),
+            renamed_to,
             description: repo.description,
             total_pull_requests: repo.total_pull_requests.total_count,
             stargazer_count: repo.stargazer_count,
@@ -215,10 +475,36 @@ impl GithubClient {
             primary_language: repo.primary_language.map(|x| x.name),
             languages: repo
                 .languages
-                .nodes
+                .edges
                 .iter()
-                .map(|x| x.name.clone())
+                .map(|x| x.node.name.clone())
                 .collect(),
+            language_sizes: repo
+                .languages
+                .edges
+                .into_iter()
+                .map(|x| LanguageSize {
+                    name: x.node.name,
+                    bytes: x.size,
+                })
+                .collect(),
+            archived: repo.is_archived,
+            license: repo
+                .license_info
+                .and_then(|x| x.spdx_id)
+                // Used by Github for licenses it doesn't recognize.
+                .map(|x| {
+                    if x == "NOASSERTION" {
+                        "Other".to_string()
+                    } else {
+                        x
+                    }
+                }),
+            topics: repo
+                .repository_topics
+                .map(|x| x.nodes.into_iter().map(|x| x.topic.name).collect())
+                .unwrap_or_default(),
+            first_seen_at: Some(time::OffsetDateTime::now_utc()),
             updated_at: time::OffsetDateTime::now_utc(),
         };
 
@@ -226,9 +512,21 @@ impl GithubClient {
     }
 }
 
+/// Response of [`GithubClient::repo_readme`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReadmeResponse {
+    Modified {
+        content: String,
+        etag: Option<String>,
+    },
+    /// The README matches the provided ETag.
+    NotModified,
+}
+
 #[derive(serde::Deserialize, Debug)]
 struct ReadmeData {
     content: String,
+    #[serde(default)]
     encoding: String,
 }
 
@@ -241,20 +539,23 @@ struct GraphqlQuery<V> {
 #[derive(serde::Deserialize, Debug)]
 struct GraphqlResponse<V> {
     data: Option<V>,
-    #[allow(dead_code)]
     errors: Option<Vec<Error>>,
 }
 
 #[derive(serde::Deserialize, Debug)]
 struct Error {
-    #[allow(dead_code)]
     message: String,
+    /// Error type, like `NOT_FOUND`.
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
 }
 
 #[derive(serde::Serialize, Debug)]
 struct RepoVariables {
     owner: String,
     repo: String,
+    #[serde(rename = "languageCount")]
+    language_count: u32,
 }
 
 mod query_repo_details {
@@ -262,12 +563,23 @@ mod query_repo_details {
     use time::OffsetDateTime;
 
     pub const REPO_DETAILS_QUERY: &str = r#"
-query ($owner: String!, $repo: String!) {
+query ($owner: String!, $repo: String!, $languageCount: Int!) {
   repository(owner: $owner, name: $repo) {
     owner {
       login
     }
     name
+    isArchived
+    licenseInfo {
+      spdxId
+    }
+    repositoryTopics(first: 20) {
+      nodes {
+        topic {
+          name
+        }
+      }
+    }
     stargazerCount
     forkCount
     description
@@ -291,14 +603,13 @@ query ($owner: String!, $repo: String!) {
       name
       color
     }
-    languages(first:3, orderBy:{
-      field:SIZE,
-      direction:DESC
-      
-    }) {
-      nodes {
-        name
-        color
+    languages(first: $languageCount, orderBy: {field: SIZE, direction: DESC}) {
+      edges {
+        size
+        node {
+          name
+          color
+        }
       }
     }
   }
@@ -312,8 +623,20 @@ query ($owner: String!, $repo: String!) {
 
     #[derive(Deserialize, Debug)]
     pub struct Repository {
-        // pub owner: Owner,
-        // pub name: String,
+        /// Current owner, which differs from the requested one if the repo
+        /// was transferred.
+        #[serde(default)]
+        pub owner: Option<Owner>,
+        /// Current name, which differs from the requested one if the repo
+        /// was renamed.
+        #[serde(default)]
+        pub name: Option<String>,
+        #[serde(rename = "isArchived", default)]
+        pub is_archived: bool,
+        #[serde(rename = "licenseInfo")]
+        pub license_info: Option<License>,
+        #[serde(rename = "repositoryTopics")]
+        pub repository_topics: Option<Connection<RepositoryTopic>>,
         #[serde(rename = "stargazerCount")]
         pub stargazer_count: u32,
         #[serde(rename = "forkCount")]
@@ -328,7 +651,23 @@ query ($owner: String!, $repo: String!) {
         pub issues: TotalCount,
         #[serde(rename = "primaryLanguage")]
         pub primary_language: Option<Language>,
-        pub languages: Connection<Language>,
+        pub languages: LanguageConnection,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct License {
+        #[serde(rename = "spdxId")]
+        pub spdx_id: Option<String>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct RepositoryTopic {
+        pub topic: Topic,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct Topic {
+        pub name: String,
     }
 
     #[derive(Deserialize, Debug, PartialEq, Eq)]
@@ -337,6 +676,18 @@ query ($owner: String!, $repo: String!) {
         pub color: Option<String>,
     }
 
+    #[derive(Deserialize, Debug)]
+    pub struct LanguageConnection {
+        pub edges: Vec<LanguageEdge>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct LanguageEdge {
+        /// Size of the code in bytes.
+        pub size: u64,
+        pub node: Language,
+    }
+
     #[derive(Deserialize, Debug)]
     pub struct Connection<T> {
         pub nodes: Vec<T>,
@@ -356,7 +707,7 @@ query ($owner: String!, $repo: String!) {
 
     #[derive(Deserialize, Debug)]
     pub struct Owner {
-        // pub login: String,
+        pub login: String,
     }
 }
 
@@ -368,13 +719,289 @@ where
     serde_path_to_error::deserialize(jd)
 }
 
+/// Minimal mock of the Github API for tests.
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use std::collections::HashMap;
+
+    use axum::{
+        extract::Path,
+        http::{header, HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing, Json, Router,
+    };
+    use base64::Engine;
+
+    use super::GithubClient;
+
+    /// Name of the repo that does not exist.
+    pub(crate) const MISSING_REPO: &str = "missing";
+    /// Name of the repo whose README is returned without encoding.
+    pub(crate) const PLAIN_README_REPO: &str = "plain-readme";
+    /// Name of the repo whose README has an unsupported encoding.
+    pub(crate) const UTF16_README_REPO: &str = "utf16-readme";
+    /// Name of the repo that was renamed to [`RENAMED_REPO_TARGET`].
+    pub(crate) const RENAMED_REPO: &str = "old-name";
+    /// ETag of all READMEs returned by [`MockGithub`].
+    pub(crate) const MOCK_README_ETAG: &str = "\"mock\"";
+    /// New owner and name of [`RENAMED_REPO`].
+    pub(crate) const RENAMED_REPO_TARGET: (&str, &str) = ("new-owner", "new-name");
+
+    #[derive(Clone)]
+    pub(crate) struct MockGithub {
+        pub url: String,
+        /// Number of README requests received.
+        pub readme_requests: Arc<AtomicUsize>,
+        /// Number of GraphQL requests received.
+        pub graphql_requests: Arc<AtomicUsize>,
+        /// Number of README requests answered with a 304 Not Modified.
+        pub not_modified_responses: Arc<AtomicUsize>,
+        /// Number of README requests via the contents API received.
+        pub contents_requests: Arc<AtomicUsize>,
+        /// Number of upcoming README requests to answer with a 503.
+        pub readme_failures: Arc<AtomicUsize>,
+        /// Number of upcoming GraphQL requests to answer with a rate limit
+        /// error, which resets after one second.
+        pub graphql_rate_limits: Arc<AtomicUsize>,
+    }
+
+    impl MockGithub {
+        /// Start a server that returns `readme` as the README of every repo.
+        ///
+        /// All repos exist, with 10 stars, except for [`MISSING_REPO`].
+        /// READMEs have the [`MOCK_README_ETAG`] ETag.
+        pub(crate) async fn start(readme: &str) -> Self {
+            let readme_requests = Arc::new(AtomicUsize::new(0));
+            let graphql_requests = Arc::new(AtomicUsize::new(0));
+
+            let readme_failures = Arc::new(AtomicUsize::new(0));
+            let not_modified_responses = Arc::new(AtomicUsize::new(0));
+            let contents_requests = Arc::new(AtomicUsize::new(0));
+            let graphql_rate_limits = Arc::new(AtomicUsize::new(0));
+
+            let content = base64::engine::general_purpose::STANDARD.encode(readme);
+            let plain = readme.to_string();
+            let readme_handler = {
+                let counter = readme_requests.clone();
+                let failures = readme_failures.clone();
+                let not_modified = not_modified_responses.clone();
+                move |Path(params): Path<HashMap<String, String>>, headers: HeaderMap| async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    match params.get("repo").map(String::as_str) {
+                        Some(MISSING_REPO) => return StatusCode::NOT_FOUND.into_response(),
+                        Some(PLAIN_README_REPO) => {
+                            return Json(serde_json::json!({
+                                "content": plain,
+                                "encoding": "none",
+                            }))
+                            .into_response();
+                        }
+                        Some(UTF16_README_REPO) => {
+                            return Json(serde_json::json!({
+                                "content": "",
+                                "encoding": "utf-16",
+                            }))
+                            .into_response();
+                        }
+                        _ => {}
+                    }
+                    let failed = failures
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| x.checked_sub(1))
+                        .is_ok();
+                    if failed {
+                        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+                    }
+                    if headers
+                        .get(header::IF_NONE_MATCH)
+                        .is_some_and(|x| x == MOCK_README_ETAG)
+                    {
+                        not_modified.fetch_add(1, Ordering::SeqCst);
+                        return StatusCode::NOT_MODIFIED.into_response();
+                    }
+                    // Give concurrent callers a chance to overlap.
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    (
+                        [(header::ETAG, MOCK_README_ETAG)],
+                        Json(serde_json::json!({
+                            "content": content,
+                            "encoding": "base64",
+                        })),
+                    )
+                        .into_response()
+                }
+            };
+            let graphql_handler = {
+                let counter = graphql_requests.clone();
+                let rate_limits = graphql_rate_limits.clone();
+                move |Json(body): Json<serde_json::Value>| async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let rate_limited = rate_limits
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| x.checked_sub(1))
+                        .is_ok();
+                    if rate_limited {
+                        return (StatusCode::TOO_MANY_REQUESTS, [("retry-after", "1")])
+                            .into_response();
+                    }
+                    if body["variables"]["repo"] == MISSING_REPO {
+                        return Json(serde_json::json!({
+                            "data": null,
+                            "errors": [{
+                                "type": "NOT_FOUND",
+                                "message": "Could not resolve to a Repository.",
+                            }],
+                        }))
+                        .into_response();
+                    }
+                    let (owner, name) = if body["variables"]["repo"] == RENAMED_REPO {
+                        (
+                            serde_json::json!(RENAMED_REPO_TARGET.0),
+                            serde_json::json!(RENAMED_REPO_TARGET.1),
+                        )
+                    } else {
+                        (
+                            body["variables"]["owner"].clone(),
+                            body["variables"]["repo"].clone(),
+                        )
+                    };
+                    Json(serde_json::json!({
+                        "data": {
+                            "repository": {
+                                "owner": { "login": owner },
+                                "name": name,
+                                "isArchived": false,
+                                "licenseInfo": { "spdxId": "MIT" },
+                                "repositoryTopics": {
+                                    "nodes": [{ "topic": { "name": "cli" } }]
+                                },
+                                "stargazerCount": 10,
+                                "forkCount": 1,
+                                "pushedAt": null,
+                                "description": "description",
+                                "totalPullRequests": { "totalCount": 0 },
+                                "latestMergedPullRequest": { "nodes": [] },
+                                "issues": { "totalCount": 0 },
+                                "primaryLanguage": null,
+                                "languages": {
+                                    "edges": [
+                                        { "size": 3000, "node": { "name": "Rust", "color": null } },
+                                        { "size": 1000, "node": { "name": "Shell", "color": null } },
+                                    ]
+                                },
+                            }
+                        }
+                    }))
+                    .into_response()
+                }
+            };
+
+            let contents_handler = {
+                let counter = contents_requests.clone();
+                let readme_handler = readme_handler.clone();
+                move |params, headers| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    readme_handler(params, headers)
+                }
+            };
+
+            let app = Router::new()
+                .route("/repos/:owner/:repo/readme", routing::get(readme_handler))
+                .route(
+                    "/repos/:owner/:repo/contents/*path",
+                    routing::get(contents_handler),
+                )
+                .route("/graphql", routing::post(graphql_handler));
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move {
+                axum::serve(listener, app).await.unwrap();
+            });
+
+            Self {
+                url,
+                readme_requests,
+                graphql_requests,
+                readme_failures,
+                not_modified_responses,
+                contents_requests,
+                graphql_rate_limits,
+            }
+        }
+
+        pub(crate) fn client(&self) -> GithubClient {
+            GithubClient::new(Vec::new())
+                .with_api_url(&self.url)
+                .with_retry_delay(std::time::Duration::from_millis(1))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_rate_limit_reset() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut map = HeaderMap::new();
+            for (name, value) in pairs {
+                map.insert(*name, HeaderValue::from_static(value));
+            }
+            map
+        };
+
+        assert_eq!(rate_limit_reset(&headers(&[]), now), None);
+        assert_eq!(
+            rate_limit_reset(&headers(&[("retry-after", "60")]), now),
+            Some(now + Duration::from_secs(60))
+        );
+        assert_eq!(
+            rate_limit_reset(
+                &headers(&[("retry-after", "Tue, 14 Nov 2023 22:15:00 GMT")]),
+                now
+            ),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_100))
+        );
+        assert_eq!(
+            rate_limit_reset(&headers(&[("x-ratelimit-reset", "1700000500")]), now),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_500))
+        );
+        // Secondary limits take precedence.
+        assert_eq!(
+            rate_limit_reset(
+                &headers(&[("retry-after", "5"), ("x-ratelimit-reset", "1700000500")]),
+                now
+            ),
+            Some(now + Duration::from_secs(5))
+        );
+        assert_eq!(
+            rate_limit_reset(&headers(&[("retry-after", "soon")]), now),
+            None
+        );
+        assert_eq!(
+            rate_limit_reset(&headers(&[("retry-after", "18446744073709551615")]), now),
+            None
+        );
+        assert_eq!(
+            rate_limit_reset(
+                &headers(&[("x-ratelimit-reset", "18446744073709551615")]),
+                now
+            ),
+            None
+        );
+    }
+
     fn test_client() -> Option<GithubClient> {
         let token = std::env::var("GITHUB_TOKEN").ok()?;
-        Some(GithubClient::new(Some(token)))
+        Some(GithubClient::new(vec![token]))
     }
 
     macro_rules! test_client {
@@ -393,9 +1020,21 @@ mod tests {
         let client = test_client!();
 
         let id = RepoIdent::new_github("rust-unofficial", "awesome-rust");
-        let readme = client.repo_readme(&id).await.unwrap();
+        let ReadmeResponse::Modified { content, etag } = client
+            .repo_readme(&id, &ReadmeLocation::default(), None)
+            .await
+            .unwrap()
+        else {
+            panic!("expected modified response");
+        };
+        assert!(content.contains("Awesome Rust"));
 
-        assert!(readme.contains("Awesome Rust"));
+        let etag = etag.expect("missing etag");
+        let res = client
+            .repo_readme(&id, &ReadmeLocation::default(), Some(&etag))
+            .await
+            .unwrap();
+        assert_eq!(res, ReadmeResponse::NotModified);
     }
 
     #[tokio::test]
@@ -410,4 +1049,143 @@ mod tests {
         assert!(data.fork_count > 0);
         assert_eq!(data.primary_language.as_deref(), Some("Rust"),);
     }
+
+    #[tokio::test]
+    async fn test_github_client_repo_details_languages() {
+        let mock = super::mock::MockGithub::start("# Awesome").await;
+        let details = mock
+            .client()
+            .repo_details(&RepoIdent::new_github("a", "b"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(details.languages, vec!["Rust", "Shell"]);
+        assert_eq!(
+            details.language_sizes,
+            vec![
+                LanguageSize {
+                    name: "Rust".to_string(),
+                    bytes: 3000,
+                },
+                LanguageSize {
+                    name: "Shell".to_string(),
+                    bytes: 1000,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_github_client_not_found() {
+        let mock = super::mock::MockGithub::start("# Awesome").await;
+        let client = mock.client();
+        let ident = RepoIdent::new_github("org", super::mock::MISSING_REPO);
+
+        assert_eq!(client.repo_details(&ident).await.unwrap(), None);
+
+        let err = client
+            .repo_readme(&ident, &ReadmeLocation::default(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SourceError::NotFound(x) if x == ident));
+    }
+
+    #[tokio::test]
+    async fn test_github_client_readme_encoding() {
+        let mock = super::mock::MockGithub::start("# Awesome\n").await;
+        let client = mock.client();
+
+        let ident = RepoIdent::new_github("org", super::mock::PLAIN_README_REPO);
+        let res = client
+            .repo_readme(&ident, &ReadmeLocation::default(), None)
+            .await
+            .unwrap();
+        assert!(
+            matches!(res, ReadmeResponse::Modified { content, .. } if content == "# Awesome\n")
+        );
+
+        let ident = RepoIdent::new_github("org", super::mock::UTF16_README_REPO);
+        let err = client
+            .repo_readme(&ident, &ReadmeLocation::default(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SourceError::UnsupportedEncoding(x) if x == "utf-16"));
+    }
+
+    #[tokio::test]
+    async fn test_github_client_retry_transient() {
+        use std::sync::atomic::Ordering;
+
+        let mock = super::mock::MockGithub::start("# Awesome").await;
+        let ident = RepoIdent::new_github("a", "b");
+
+        mock.readme_failures.store(2, Ordering::SeqCst);
+        let res = mock
+            .client()
+            .repo_readme(&ident, &ReadmeLocation::default(), None)
+            .await
+            .unwrap();
+        assert!(matches!(res, ReadmeResponse::Modified { .. }));
+        assert_eq!(mock.readme_requests.load(Ordering::SeqCst), 3);
+
+        // Gives up after the configured number of attempts.
+        mock.readme_requests.store(0, Ordering::SeqCst);
+        mock.readme_failures.store(5, Ordering::SeqCst);
+        let res = mock
+            .client()
+            .with_max_attempts(2)
+            .repo_readme(&ident, &ReadmeLocation::default(), None)
+            .await;
+        assert!(res.is_err());
+        assert_eq!(mock.readme_requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_github_client_token_rotation() {
+        let client = GithubClient::new(vec!["a".to_string(), "b".to_string()]);
+        let token = |state: Option<&TokenState>| state.and_then(|x| x.token.clone());
+
+        assert_eq!(token(client.select_token()).as_deref(), Some("a"));
+        assert_eq!(token(client.select_token()).as_deref(), Some("b"));
+        assert_eq!(client.rate_limited_until(), None);
+
+        let reset = SystemTime::now() + Duration::from_secs(60);
+        client.tokens[0].set_rate_limited_until(reset);
+        assert_eq!(token(client.select_token()).as_deref(), Some("b"));
+        assert_eq!(token(client.select_token()).as_deref(), Some("b"));
+        assert_eq!(client.rate_limited_until(), None);
+
+        let reset2 = reset + Duration::from_secs(60);
+        client.tokens[1].set_rate_limited_until(reset2);
+        assert!(client.select_token().is_none());
+        assert_eq!(client.rate_limited_until(), Some(reset));
+    }
+
+    #[tokio::test]
+    async fn test_github_client_repo_details_renamed() {
+        use super::mock::{RENAMED_REPO, RENAMED_REPO_TARGET};
+
+        let mock = super::mock::MockGithub::start("# Awesome").await;
+        let client = mock.client();
+
+        let ident = RepoIdent::new_github("org", RENAMED_REPO);
+        let details = client.repo_details(&ident).await.unwrap().unwrap();
+        assert_eq!(details.ident, ident);
+        assert_eq!(
+            details.renamed_to,
+            Some(RepoIdent::new_github(
+                RENAMED_REPO_TARGET.0,
+                RENAMED_REPO_TARGET.1
+            ))
+        );
+
+        // Differences in case are not a rename.
+        let details = client
+            .repo_details(&RepoIdent::new_github("Org", "Repo"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(details.renamed_to, None);
+    }
 }
@@ -1,25 +1,61 @@
 use anyhow::Context;
 
-use super::{github::GithubClient, ReadmeRepo, RepoDetailsItem, RepoIdent, Source};
+use crate::markdown::ParseOptions;
+
+use super::{
+    gitea::GiteaClient,
+    github::{GithubClient, ReadmeResponse},
+    NotAwesomeListError, ReadmeLocation, ReadmeRepo, RepoDetails, RepoDetailsItem, RepoIdent,
+    RepoLink, Source, SourceError,
+};
 
 #[derive(Clone)]
 pub struct SourceLoader {
     github: GithubClient,
+    /// Client for Gitea based sources, like Codeberg.
+    gitea: GiteaClient,
+    parse_options: ParseOptions,
 }
 
 impl SourceLoader {
     pub fn new(github: GithubClient) -> Self {
-        Self { github }
+        Self {
+            github,
+            gitea: GiteaClient::new(),
+            parse_options: ParseOptions::default(),
+        }
+    }
+
+    /// Use a different Gitea client, like a mock.
+    #[cfg(test)]
+    pub(crate) fn with_gitea(mut self, gitea: GiteaClient) -> Self {
+        self.gitea = gitea;
+        self
+    }
+
+    /// Returns the time until which the sources are rate limited, if any.
+    pub fn rate_limited_until(&self) -> Option<std::time::SystemTime> {
+        self.github.rate_limited_until()
+    }
+
+    /// Set additional Github Enterprise hosts to recognize in READMEs.
+    pub fn with_github_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.parse_options.github_hosts = hosts;
+        self
+    }
+
+    /// Only keep the first link to each repo in parsed READMEs.
+    pub fn with_dedup_links(mut self, dedup: bool) -> Self {
+        self.parse_options.dedup = dedup;
+        self
     }
 
     pub async fn load_repo_details(
         &self,
         ident: &RepoIdent,
-    ) -> Result<RepoDetailsItem, anyhow::Error> {
+    ) -> Result<RepoDetailsItem, SourceError> {
         tracing::trace!("loading repo details for {}", ident);
-        let opt = match ident.source {
-            Source::Github => self.github.repo_details(ident).await?,
-        };
+        let opt = self.repo_details(ident).await?;
 
         if let Some(x) = opt {
             Ok(RepoDetailsItem::Found(x))
@@ -31,35 +67,100 @@ impl SourceLoader {
         }
     }
 
-    pub async fn load_readme_repo(&self, ident: &RepoIdent) -> Result<ReadmeRepo, anyhow::Error> {
-        let (readme, details) = match ident.source {
-            Source::Github => {
-                tracing::trace!("loading README for {}", ident);
-                let readme = self.github.repo_readme(ident).await?;
-                let details = self
-                    .github
+    /// Load a README repo from the source.
+    ///
+    /// If a `previous` version is provided and the README did not change, the
+    /// previous links are kept without parsing the README again. The repo
+    /// details are always refreshed.
+    pub async fn load_readme_repo(
+        &self,
+        ident: &RepoIdent,
+        location: ReadmeLocation,
+        previous: Option<ReadmeRepo>,
+    ) -> Result<ReadmeRepo, SourceError> {
+        // The ETag is only valid for the same file.
+        let previous = previous.filter(|x| x.readme_location == location);
+        let etag = previous.as_ref().and_then(|x| x.readme_etag.as_deref());
+
+        tracing::trace!("loading README for {}", ident);
+        let (readme, etag) = match self.repo_readme(ident, &location, etag).await? {
+            ReadmeResponse::Modified { content, etag } => (content, etag),
+            ReadmeResponse::NotModified => {
+                tracing::trace!("README for {} not modified", ident);
+                let mut repo =
+                    previous.context("README not modified, but no previous version available")?;
+                repo.details = self
                     .repo_details(ident)
                     .await?
-                    .context("not found")?;
-                (readme, details)
+                    .ok_or_else(|| SourceError::NotFound(ident.clone()))?;
+                repo.updated_at = time::OffsetDateTime::now_utc();
+                return Ok(repo);
             }
         };
+        let details = self
+            .repo_details(ident)
+            .await?
+            .ok_or_else(|| SourceError::NotFound(ident.clone()))?;
 
-        let mut links = crate::markdown::parse_markdown(&readme)?;
-        // Filter out links to self.
-        links.retain(|link| link.ident != *ident);
-
+        let links = self.parse_readme(ident, &readme)?;
         if links.is_empty() {
-            anyhow::bail!("Does not appear to be an awesome- repo");
+            return Err(SourceError::NotAwesomeList(NotAwesomeListError {
+                ident: ident.clone(),
+            }));
         }
 
         let repo = ReadmeRepo {
             details,
             readme_content: readme,
+            readme_etag: etag,
+            readme_location: location,
             repo_links: links,
             updated_at: time::OffsetDateTime::now_utc(),
         };
 
         Ok(repo)
     }
+
+    /// Fetch the current README of a repo.
+    pub async fn fetch_readme(&self, ident: &RepoIdent) -> Result<String, SourceError> {
+        match self
+            .repo_readme(ident, &ReadmeLocation::default(), None)
+            .await?
+        {
+            ReadmeResponse::Modified { content, .. } => Ok(content),
+            ReadmeResponse::NotModified => {
+                Err(anyhow::anyhow!("README not modified, but no ETag was sent").into())
+            }
+        }
+    }
+
+    async fn repo_details(&self, ident: &RepoIdent) -> Result<Option<RepoDetails>, SourceError> {
+        match ident.source {
+            Source::Github => self.github.repo_details(ident).await,
+            Source::Codeberg => self.gitea.repo_details(ident).await,
+        }
+    }
+
+    async fn repo_readme(
+        &self,
+        ident: &RepoIdent,
+        location: &ReadmeLocation,
+        etag: Option<&str>,
+    ) -> Result<ReadmeResponse, SourceError> {
+        match ident.source {
+            Source::Github => self.github.repo_readme(ident, location, etag).await,
+            Source::Codeberg => self.gitea.repo_readme(ident, location, etag).await,
+        }
+    }
+
+    /// Extract the repo links of a README, excluding links to the repo itself.
+    pub fn parse_readme(
+        &self,
+        ident: &RepoIdent,
+        readme: &str,
+    ) -> Result<Vec<RepoLink>, anyhow::Error> {
+        let mut links = crate::markdown::parse_markdown(readme, &self.parse_options)?;
+        links.retain(|link| link.ident != *ident);
+        Ok(links)
+    }
 }
@@ -3,26 +3,41 @@ use std::time::SystemTime;
 use anyhow::{anyhow, bail, Context};
 use time::OffsetDateTime;
 
+pub mod gitea;
 pub mod github;
 pub mod loader;
+mod throttle;
 
 #[derive(
     serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord,
 )]
 pub enum Source {
     Github,
+    /// Codeberg, a Gitea instance.
+    Codeberg,
 }
 
 impl Source {
+    /// All supported sources.
+    pub const ALL: &'static [Source] = &[Source::Github, Source::Codeberg];
+
+    /// The source with the given public domain, with or without `www.`.
+    pub fn from_domain(domain: &str) -> Option<Self> {
+        let domain = domain.strip_prefix("www.").unwrap_or(domain);
+        Self::ALL.iter().find(|x| x.domain() == domain).cloned()
+    }
+
     const fn as_str(&self) -> &'static str {
         match self {
             Source::Github => "github",
+            Source::Codeberg => "codeberg",
         }
     }
 
-    const fn domain(&self) -> &'static str {
+    pub const fn domain(&self) -> &'static str {
         match self {
             Source::Github => "github.com",
+            Source::Codeberg => "codeberg.org",
         }
     }
 }
@@ -33,6 +48,7 @@ impl std::str::FromStr for Source {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "github" => Ok(Source::Github),
+            "codeberg" => Ok(Source::Codeberg),
             _ => bail!("unknown source: {}", s),
         }
     }
@@ -47,6 +63,11 @@ impl std::fmt::Display for Source {
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct RepoIdent {
     pub source: Source,
+    /// Custom host, for self-hosted instances like Github Enterprise.
+    ///
+    /// `None` for the default public host of the source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
     pub owner: String,
     pub repo: String,
 }
@@ -55,6 +76,7 @@ impl Ord for RepoIdent {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.source
             .cmp(&other.source)
+            .then_with(|| self.host.cmp(&other.host))
             .then_with(|| self.owner.cmp(&other.owner))
             .then_with(|| self.repo.cmp(&other.repo))
     }
@@ -66,10 +88,110 @@ impl PartialOrd for RepoIdent {
     }
 }
 
+/// First path segments of Github URLs that are not repository owners.
+const RESERVED_OWNERS: &[&str] = &[
+    "about",
+    "account",
+    "apps",
+    "codespaces",
+    "collections",
+    "contact",
+    "customer-stories",
+    "dashboard",
+    "enterprise",
+    "events",
+    "explore",
+    "features",
+    "login",
+    "marketplace",
+    "new",
+    "notifications",
+    "orgs",
+    "organizations",
+    "pricing",
+    "pulls",
+    "search",
+    "settings",
+    "site",
+    "sponsors",
+    "topics",
+    "trending",
+    "user-attachments",
+    "users",
+];
+
+/// Fail if the owner is a reserved Github path, like `github.com/sponsors/x`.
+fn check_owner(owner: &str) -> Result<(), anyhow::Error> {
+    if RESERVED_OWNERS
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(owner))
+    {
+        bail!("'{owner}' is a reserved path, not a repository owner");
+    }
+    Ok(())
+}
+
+/// Fail if an ident segment is empty, a relative path component or contains
+/// characters outside of ASCII alphanumerics, `-`, `_` and `.`.
+///
+/// Idents are used as file names by the fs store, so this also rejects
+/// path traversal like `../etc`.
+fn check_ident_segment(kind: &str, value: &str, extra: &[char]) -> Result<(), anyhow::Error> {
+    if value.is_empty() {
+        bail!("empty {kind}");
+    }
+    if value == "." || value == ".." {
+        bail!("invalid {kind} '{value}'");
+    }
+    if let Some(c) = value
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') || extra.contains(c)))
+    {
+        bail!("invalid character {c:?} in {kind} '{value}'");
+    }
+    Ok(())
+}
+
+/// Check a README path given by a user.
+///
+/// The path is appended to source API URLs, so segments that change the
+/// meaning of the URL are rejected.
+fn check_readme_path(path: &str) -> Result<(), anyhow::Error> {
+    if let Some(c) = path.chars().find(|c| matches!(c, '?' | '#' | '\\')) {
+        bail!("invalid character {c:?} in README path '{path}'");
+    }
+    if path
+        .split('/')
+        .any(|segment| segment.is_empty() || segment == "." || segment == "..")
+    {
+        bail!("invalid README path '{path}'");
+    }
+    Ok(())
+}
+
+/// Percent-encode everything except unreserved characters.
+fn encode_path_segment(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    for b in segment.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+/// Strip the `.git` suffix of clone URLs from a repo name.
+fn trim_repo_name(repo: &str) -> &str {
+    repo.strip_suffix(".git").unwrap_or(repo)
+}
+
 impl RepoIdent {
     pub fn new(source: Source, owner: impl Into<String>, repo: impl Into<String>) -> Self {
         Self {
             source,
+            host: None,
             owner: owner.into(),
             repo: repo.into(),
         }
@@ -79,62 +201,199 @@ impl RepoIdent {
         Self::new(Source::Github, owner, repo)
     }
 
+    pub fn with_host(self, host: impl Into<String>) -> Self {
+        Self {
+            host: Some(host.into()),
+            ..self
+        }
+    }
+
+    /// Ensure the host, owner and repo only contain safe characters.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if let Some(host) = &self.host {
+            // Allow a port.
+            check_ident_segment("host", host, &[':'])?;
+        }
+        check_ident_segment("owner", &self.owner, &[])?;
+        check_ident_segment("repo", &self.repo, &[])?;
+        Ok(())
+    }
+
+    /// Build an ident from the path segments of a repo page URL.
+    ///
+    /// See [`Self::path_source`].
+    pub fn from_path(
+        source: &str,
+        owner: impl Into<String>,
+        repo: impl Into<String>,
+        github_hosts: &[String],
+    ) -> Result<Self, anyhow::Error> {
+        let ident = if github_hosts.iter().any(|host| host == source) {
+            Self::new_github(owner, repo).with_host(source)
+        } else {
+            Self::new(source.parse()?, owner, repo)
+        };
+        ident.validate()?;
+        Ok(ident)
+    }
+
     pub fn parse_url(url: &str) -> Result<Self, anyhow::Error> {
+        Self::parse_url_with_hosts(url, &[])
+    }
+
+    /// Parse a repository URL of any supported source.
+    ///
+    /// `github_hosts` are additional Github Enterprise hosts to recognize.
+    pub fn parse_url_with_hosts(url: &str, github_hosts: &[String]) -> Result<Self, anyhow::Error> {
         let url: url::Url = url.parse()?;
+        let (source, host, owner, path) = Self::split_url_owner(&url, github_hosts)?;
+        let repo = path
+            .split('/')
+            .next()
+            .map(|x| trim_repo_name(x.trim()))
+            .filter(|x| !x.is_empty())
+            .ok_or_else(|| anyhow!("missing repo"))?;
 
-        match url.host_str() {
-            Some("github.com") => {
-                let mut path = url.path().split('/').skip(1);
-                let owner = path
-                    .next()
-                    .map(|x| x.trim())
-                    .filter(|x| !x.is_empty())
-                    .ok_or_else(|| anyhow!("missing owner"))?;
-                let repo = path
-                    .next()
-                    .map(|x| x.trim())
-                    .filter(|x| !x.is_empty())
-                    .ok_or_else(|| anyhow!("missing repo"))?;
-
-                Ok(Self::new_github(owner, repo))
-            }
-            Some(host) => bail!("unsupported host: {}", host),
+        let ident = Self::new(source, owner, repo);
+        Ok(match host {
+            Some(host) => ident.with_host(host),
+            None => ident,
+        })
+    }
+
+    /// Parse an owner URL without a repo, like `github.com/rust-unofficial`.
+    ///
+    /// The scheme is optional. Returns the source, the custom Github host, if
+    /// any, and the owner.
+    pub fn parse_owner_url(
+        url: &str,
+        github_hosts: &[String],
+    ) -> Result<(Source, Option<String>, String), anyhow::Error> {
+        let url = url.trim();
+        let url: url::Url = if url.contains("://") {
+            url.parse()?
+        } else {
+            format!("https://{url}").parse()?
+        };
+        let (source, host, owner, path) = Self::split_url_owner(&url, github_hosts)?;
+        check_ident_segment("owner", owner, &[])?;
+        if !path.is_empty() {
+            bail!("not an owner URL: {}", url);
+        }
+        Ok((source, host, owner.to_string()))
+    }
+
+    /// Split a URL into its source, custom Github host and owner.
+    ///
+    /// Also returns the remaining path after the owner.
+    fn split_url_owner<'a>(
+        url: &'a url::Url,
+        github_hosts: &[String],
+    ) -> Result<(Source, Option<String>, &'a str, &'a str), anyhow::Error> {
+        let (source, host) = match url.host_str() {
+            Some(host) => match Source::from_domain(host) {
+                Some(source) => (source, None),
+                None if github_hosts.iter().any(|x| x == host) => {
+                    (Source::Github, Some(host.to_string()))
+                }
+                None => bail!("unsupported host: {}", host),
+            },
             None => bail!("missing host"),
+        };
+
+        let path = url.path().trim_start_matches('/');
+        let (owner, rest) = path.split_once('/').unwrap_or((path, ""));
+        let owner = Some(owner.trim())
+            .filter(|x| !x.is_empty())
+            .ok_or_else(|| anyhow!("missing owner"))?;
+        match source {
+            Source::Github => check_owner(owner)?,
+            Source::Codeberg => {}
         }
+        Ok((source, host, owner, rest))
     }
 
     pub fn parse_ident(ident: &str) -> Result<Self, anyhow::Error> {
-        if let Ok(url) = Self::parse_url(ident) {
+        Self::parse_ident_with_hosts(ident, &[])
+    }
+
+    /// Parse a repository URL, a `<host>/<org>/<repo>` path of any supported
+    /// source or a plain `<org>/<repo>` Github repo.
+    ///
+    /// `github_hosts` are additional Github Enterprise hosts to recognize.
+    pub fn parse_ident_with_hosts(
+        ident: &str,
+        github_hosts: &[String],
+    ) -> Result<Self, anyhow::Error> {
+        if let Ok(url) = Self::parse_url_with_hosts(ident, github_hosts) {
             return Ok(url);
         }
 
-        if ident.starts_with("github.com/") {
-            let rest = ident.trim_start_matches("github.com/");
-            let (org, repo) = rest
-                .split_once('/')
-                .filter(|(owner, repo)| {
-                    !owner.is_empty() && !repo.is_empty() && !repo.contains('/')
-                })
-                .context("invalid github.com/ URL - expected github.com/<org>/<repo>")?;
+        let ident = ident.trim().trim_end_matches('/');
+        let ident = ident.strip_prefix("www.").unwrap_or(ident);
+
+        for source in Source::ALL {
+            let domain = source.domain();
+            if let Some(rest) = ident.strip_prefix(&format!("{domain}/")) {
+                let (org, repo) = rest
+                    .split_once('/')
+                    .filter(|(owner, repo)| {
+                        !owner.is_empty() && !repo.is_empty() && !repo.contains('/')
+                    })
+                    .with_context(|| {
+                        format!("invalid {domain}/ URL - expected {domain}/<org>/<repo>")
+                    })?;
+                if *source == Source::Github {
+                    check_owner(org)?;
+                }
 
-            return Ok(Self::new_github(org, repo));
+                return Ok(Self::new(source.clone(), org, trim_repo_name(repo)));
+            }
+        }
+
+        for host in github_hosts {
+            if let Some(rest) = ident.strip_prefix(&format!("{host}/")) {
+                let (org, repo) = rest
+                    .split_once('/')
+                    .filter(|(owner, repo)| {
+                        !owner.is_empty() && !repo.is_empty() && !repo.contains('/')
+                    })
+                    .with_context(|| {
+                        format!("invalid {host}/ URL - expected {host}/<org>/<repo>")
+                    })?;
+                check_owner(org)?;
+
+                return Ok(Self::new_github(org, trim_repo_name(repo)).with_host(host));
+            }
         }
 
         let (org, repo) = ident
             .split_once('/')
             .filter(|(owner, repo)| !owner.is_empty() && !repo.is_empty() && !repo.contains('/'))
             .context("invalid Github repo - expected <org>/<repo>")?;
+        check_owner(org)?;
 
-        Ok(Self::new_github(org, repo))
+        Ok(Self::new_github(org, trim_repo_name(repo)))
+    }
+
+    /// The domain of the repository host.
+    pub fn domain(&self) -> &str {
+        self.host.as_deref().unwrap_or(self.source.domain())
+    }
+
+    /// The source segment used in repo page URLs.
+    ///
+    /// This is the source name, or the host for custom hosts.
+    pub fn path_source(&self) -> &str {
+        self.host.as_deref().unwrap_or(self.source.as_str())
     }
 
     pub fn url(&self) -> String {
-        let domain = self.source.domain();
-        format!("https://{}/{}/{}", domain, self.owner, self.repo)
+        format!("https://{}/{}/{}", self.domain(), self.owner, self.repo)
     }
 
     pub fn pretty_url(&self) -> String {
-        format!("{}/{}/{}", self.source.domain(), self.owner, self.repo)
+        format!("{}/{}/{}", self.domain(), self.owner, self.repo)
     }
 
     pub fn name(&self) -> String {
@@ -144,7 +403,10 @@ impl RepoIdent {
 
 impl std::fmt::Display for RepoIdent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}:{}/{}", self.source, self.owner, self.repo)
+        match &self.host {
+            Some(host) => write!(f, "{}:{}/{}/{}", self.source, host, self.owner, self.repo),
+            None => write!(f, "{}:{}/{}", self.source, self.owner, self.repo),
+        }
     }
 }
 
@@ -152,19 +414,81 @@ impl std::fmt::Display for RepoIdent {
 pub struct ReadmeRepo {
     pub details: RepoDetails,
     pub readme_content: String,
+    /// ETag of the README, used for conditional requests on refresh.
+    #[serde(default)]
+    pub readme_etag: Option<String>,
+    /// Where the README was loaded from. Refreshes use the same location.
+    #[serde(default, skip_serializing_if = "ReadmeLocation::is_default")]
+    pub readme_location: ReadmeLocation,
     pub repo_links: Vec<RepoLink>,
     pub updated_at: time::OffsetDateTime,
 }
 
+/// Location of the README in a repo.
+///
+/// By default the README detected by the source on the default branch is
+/// used.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReadmeLocation {
+    /// Path of the file, relative to the repo root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Branch, tag or commit.
+    #[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+}
+
+impl ReadmeLocation {
+    /// Build a location from user input, ignoring empty values.
+    ///
+    /// Returns `None` if neither a path nor a ref is set. Paths that could
+    /// point outside of the repo contents, like `../..`, are rejected.
+    pub fn from_input(
+        path: Option<&str>,
+        git_ref: Option<&str>,
+    ) -> Result<Option<Self>, anyhow::Error> {
+        let path = path
+            .map(|x| x.trim().trim_matches('/'))
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_string());
+        if let Some(path) = &path {
+            check_readme_path(path)?;
+        }
+        let git_ref = git_ref
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_string());
+        let location = Self { path, git_ref };
+        Ok((!location.is_default()).then_some(location))
+    }
+
+    /// The path with each segment percent-encoded, for use in API URLs.
+    pub fn encoded_path(&self) -> Option<String> {
+        let path = self.path.as_ref()?;
+        let segments: Vec<_> = path.split('/').map(encode_path_segment).collect();
+        Some(segments.join("/"))
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.path.is_none() && self.git_ref.is_none()
+    }
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct RepoLink {
     pub ident: RepoIdent,
     pub section: Vec<String>,
+    /// Description text next to the link in the README.
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct RepoDetails {
     pub ident: RepoIdent,
+    /// Current location, if the repository was renamed or transferred.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renamed_to: Option<RepoIdent>,
 
     pub description: Option<String>,
 
@@ -179,11 +503,33 @@ pub struct RepoDetails {
     pub last_pullrequest_merged_at: Option<OffsetDateTime>,
     pub primary_language: Option<String>,
     pub languages: Vec<String>,
+    /// Size of the code per language, largest first.
+    #[serde(default)]
+    pub language_sizes: Vec<LanguageSize>,
+    /// Whether the repository is archived (read-only).
+    #[serde(default)]
+    pub archived: bool,
+    /// SPDX identifier of the license.
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub topics: Vec<String>,
 
+    /// When the repo was first loaded.
+    ///
+    /// Preserved by storage when the details are updated. `None` for repos
+    /// stored before this was tracked.
+    #[serde(default, with = "time::serde::iso8601::option")]
+    pub first_seen_at: Option<OffsetDateTime>,
     pub updated_at: time::OffsetDateTime,
 }
 
 impl RepoDetails {
+    /// Returns `true` if the repo has the given topic (case-insensitive).
+    pub fn has_topic(&self, topic: &str) -> bool {
+        self.topics.iter().any(|x| x.eq_ignore_ascii_case(topic))
+    }
+
     pub fn last_activity(&self) -> Option<&OffsetDateTime> {
         self.last_pushed_at
             .as_ref()
@@ -191,35 +537,68 @@ impl RepoDetails {
     }
 
     pub fn last_activity_relative_time(&self) -> Option<String> {
-        let time = self.last_activity()?;
-        let elapsed = OffsetDateTime::now_utc() - *time;
-
-        let days = elapsed.whole_days();
-
-        let v = if days < 1 {
-            "today".to_string()
-        } else if days < 2 {
-            "yesterday".to_string()
-        } else if days < 7 {
-            format!("{} days", days)
-        } else if days < 14 {
-            "1 week".to_string()
-        } else if days < 30 {
-            format!("{} weeks", days / 7)
-        } else if days < 60 {
-            "1 month".to_string()
-        } else if days < 365 {
-            format!("{} months", days / 30)
-        } else if days < 365 * 2 {
-            "1 year".to_string()
-        } else {
-            format!("{} years", days / 365)
-        };
+        self.last_activity().map(relative_time)
+    }
 
-        Some(v)
+    /// Returns `true` if the last activity is more than `threshold_days` ago.
+    ///
+    /// Repos without known activity are never considered stale.
+    pub fn is_stale(&self, threshold_days: i64) -> bool {
+        self.last_activity()
+            .is_some_and(|time| (OffsetDateTime::now_utc() - *time).whole_days() > threshold_days)
+    }
+
+    /// Returns `true` if the repo was first seen within the last `days`.
+    pub fn is_new(&self, days: i64) -> bool {
+        self.first_seen_at
+            .is_some_and(|time| (OffsetDateTime::now_utc() - time).whole_days() < days)
+    }
+
+    /// The last activity as an RFC 3339 timestamp in UTC.
+    pub fn last_activity_exact(&self) -> Option<String> {
+        self.last_activity()?
+            .to_offset(time::UtcOffset::UTC)
+            .format(&time::format_description::well_known::Rfc3339)
+            .ok()
+    }
+}
+
+/// Human readable time since `time`, like "3 weeks".
+pub fn relative_time(time: &OffsetDateTime) -> String {
+    let elapsed = OffsetDateTime::now_utc() - *time;
+
+    let days = elapsed.whole_days();
+
+    if days < 1 {
+        "today".to_string()
+    } else if days < 2 {
+        "yesterday".to_string()
+    } else if days < 7 {
+        format!("{} days", days)
+    } else if days < 14 {
+        "1 week".to_string()
+    } else if days < 30 {
+        format!("{} weeks", days / 7)
+    } else if days < 60 {
+        "1 month".to_string()
+    } else if days < 365 {
+        format!("{} months", days / 30)
+    } else if days < 365 * 2 {
+        "1 year".to_string()
+    } else {
+        format!("{} years", days / 365)
     }
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LanguageSize {
+    pub name: String,
+    /// Size of the code in bytes.
+    pub bytes: u64,
+}
+
+// Almost all items are `Found`, so boxing would only add indirection.
+#[allow(clippy::large_enum_variant)]
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum RepoDetailsItem {
     Found(RepoDetails),
@@ -237,6 +616,21 @@ impl RepoDetailsItem {
         }
     }
 
+    /// Keep the earliest `first_seen_at` of this and a previously stored
+    /// version.
+    ///
+    /// A previous version without `first_seen_at` was stored before it was
+    /// tracked, so the time stays unknown instead of becoming now.
+    pub fn keep_first_seen(&mut self, previous: Option<&RepoDetailsItem>) {
+        if let (Self::Found(details), Some(Self::Found(previous))) = (self, previous) {
+            details.first_seen_at = match (details.first_seen_at, previous.first_seen_at) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (None, b) => b,
+                (Some(_), None) => None,
+            };
+        }
+    }
+
     /// Returns `true` if the repo details item is [`NotFound`].
     ///
     /// [`NotFound`]: RepoDetailsItem::NotFound
@@ -261,14 +655,127 @@ impl RepoDetailsItem {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(serde::Serialize, Clone, Debug)]
 pub struct FullReadmeRepo {
     pub repo: ReadmeRepo,
     pub links: Vec<FullRepoLink>,
     pub not_found: Vec<RepoIdent>,
+    /// When the rate limit that interrupted resolving the links resets, if
+    /// known.
+    #[serde(skip)]
+    pub rate_limit_reset_at: Option<SystemTime>,
 }
 
 impl FullReadmeRepo {
+    /// Sorted, unique list of the primary languages of all resolved links.
+    pub fn primary_languages(&self) -> Vec<String> {
+        let mut langs: Vec<_> = self
+            .links
+            .iter()
+            .filter_map(|link| link.details.primary_language.clone())
+            .collect();
+        langs.sort();
+        langs.dedup();
+        langs
+    }
+
+    /// Resolved links, skipping repos that were already linked before.
+    fn unique_links(&self) -> impl Iterator<Item = &FullRepoLink> {
+        let mut seen = std::collections::HashSet::new();
+        self.links
+            .iter()
+            .filter(move |link| seen.insert(&link.link.ident))
+    }
+
+    /// Sum of the stars of all linked repos.
+    pub fn total_link_stars(&self) -> u64 {
+        self.unique_links()
+            .map(|link| u64::from(link.details.stargazer_count))
+            .sum()
+    }
+
+    /// Number of linked repos that are archived or inactive for more than
+    /// `stale_days`.
+    pub fn inactive_link_count(&self, stale_days: i64) -> usize {
+        self.unique_links()
+            .filter(|link| link.details.archived || link.details.is_stale(stale_days))
+            .count()
+    }
+
+    /// Median last activity of the linked repos.
+    ///
+    /// Repos without known activity are skipped.
+    pub fn median_link_activity(&self) -> Option<OffsetDateTime> {
+        let mut times: Vec<_> = self
+            .unique_links()
+            .filter_map(|link| link.details.last_activity().copied())
+            .collect();
+        if times.is_empty() {
+            return None;
+        }
+        times.sort();
+        let mid = times.len() / 2;
+        if times.len() % 2 == 0 {
+            Some(times[mid - 1] + (times[mid] - times[mid - 1]) / 2)
+        } else {
+            Some(times[mid])
+        }
+    }
+
+    /// Number of linked repos per primary language, most common first.
+    ///
+    /// Repos linked multiple times are counted once. Repos without a primary
+    /// language are skipped.
+    pub fn language_histogram(&self) -> Vec<(String, usize)> {
+        let mut counts = std::collections::HashMap::<&str, usize>::new();
+        for link in self.unique_links() {
+            if let Some(lang) = &link.details.primary_language {
+                *counts.entry(lang).or_default() += 1;
+            }
+        }
+
+        let mut histogram: Vec<_> = counts
+            .into_iter()
+            .map(|(lang, count)| (lang.to_string(), count))
+            .collect();
+        histogram.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        histogram
+    }
+
+    /// Links to repos that were not found or are archived, in README order.
+    pub fn broken_links(&self) -> Vec<BrokenLink<'_>> {
+        let mut seen = std::collections::HashSet::new();
+
+        self.repo
+            .repo_links
+            .iter()
+            .filter(|link| seen.insert(&link.ident))
+            .filter_map(|link| {
+                let reason = if self.not_found.contains(&link.ident) {
+                    BrokenLinkReason::NotFound
+                } else if self
+                    .links
+                    .iter()
+                    .any(|l| l.link.ident == link.ident && l.details.archived)
+                {
+                    BrokenLinkReason::Archived
+                } else {
+                    return None;
+                };
+                Some(BrokenLink { link, reason })
+            })
+            .collect()
+    }
+
+    /// Number of distinct resolved links.
+    pub fn unique_link_count(&self) -> usize {
+        self.links
+            .iter()
+            .map(|link| &link.link.ident)
+            .collect::<std::collections::HashSet<_>>()
+            .len()
+    }
+
     pub fn missing_links_count(&self) -> usize {
         self.missing_links().len()
     }
@@ -293,16 +800,49 @@ impl FullReadmeRepo {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(serde::Serialize, Clone, Debug)]
 pub struct FullRepoLink {
     pub link: RepoLink,
     pub details: RepoDetails,
 }
 
+impl FullRepoLink {
+    /// The repository description, falling back to the description from the
+    /// README if the repo has none.
+    pub fn description(&self) -> Option<&str> {
+        self.details
+            .description
+            .as_deref()
+            .filter(|x| !x.trim().is_empty())
+            .or(self.link.description.as_deref())
+    }
+}
+
+/// A link in a README that points to a repo that is gone or archived.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BrokenLink<'a> {
+    pub link: &'a RepoLink,
+    pub reason: BrokenLinkReason,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrokenLinkReason {
+    NotFound,
+    Archived,
+}
+
+impl std::fmt::Display for BrokenLinkReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BrokenLinkReason::NotFound => write!(f, "not found"),
+            BrokenLinkReason::Archived => write!(f, "archived"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct RateLimitError {
     pub message: String,
-    #[allow(dead_code)]
     pub reset_at: Option<SystemTime>,
 }
 
@@ -313,3 +853,521 @@ impl std::fmt::Display for RateLimitError {
 }
 
 impl std::error::Error for RateLimitError {}
+
+/// Returned when a README does not contain any repository links.
+#[derive(Clone, Debug)]
+pub struct NotAwesomeListError {
+    pub ident: RepoIdent,
+}
+
+impl std::fmt::Display for NotAwesomeListError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} does not appear to be an awesome- repo", self.ident)
+    }
+}
+
+impl std::error::Error for NotAwesomeListError {}
+
+/// Error returned by sources.
+#[derive(Debug)]
+pub enum SourceError {
+    /// The repository does not exist.
+    NotFound(RepoIdent),
+    RateLimited(RateLimitError),
+    NotAwesomeList(NotAwesomeListError),
+    /// The README content uses an encoding that can not be decoded.
+    UnsupportedEncoding(String),
+    Other(anyhow::Error),
+}
+
+impl SourceError {
+    /// Returns the source error wrapped in an [`anyhow::Error`], if any.
+    pub fn find(err: &anyhow::Error) -> Option<&SourceError> {
+        err.downcast_ref()
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, Self::RateLimited(_))
+    }
+
+    /// Returns when the rate limit resets, for rate limit errors with a
+    /// known reset time.
+    pub fn rate_limit_reset_at(&self) -> Option<SystemTime> {
+        match self {
+            Self::RateLimited(err) => err.reset_at,
+            _ => None,
+        }
+    }
+
+    /// Clone the error.
+    ///
+    /// [`SourceError::Other`] can not be cloned, so only its message is kept.
+    pub fn clone_lossy(&self) -> Self {
+        match self {
+            Self::NotFound(ident) => Self::NotFound(ident.clone()),
+            Self::RateLimited(e) => Self::RateLimited(e.clone()),
+            Self::NotAwesomeList(e) => Self::NotAwesomeList(e.clone()),
+            Self::UnsupportedEncoding(e) => Self::UnsupportedEncoding(e.clone()),
+            Self::Other(e) => Self::Other(anyhow::anyhow!("{e:#}")),
+        }
+    }
+}
+
+impl std::fmt::Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NotFound(ident) => write!(f, "repository {} not found", ident),
+            Self::RateLimited(e) => e.fmt(f),
+            Self::NotAwesomeList(e) => e.fmt(f),
+            Self::UnsupportedEncoding(encoding) => {
+                write!(f, "unsupported README encoding: '{}'", encoding)
+            }
+            Self::Other(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SourceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Other(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<RateLimitError> for SourceError {
+    fn from(e: RateLimitError) -> Self {
+        Self::RateLimited(e)
+    }
+}
+
+impl From<NotAwesomeListError> for SourceError {
+    fn from(e: NotAwesomeListError) -> Self {
+        Self::NotAwesomeList(e)
+    }
+}
+
+impl From<anyhow::Error> for SourceError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Other(e)
+    }
+}
+
+impl From<reqwest::Error> for SourceError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Other(e.into())
+    }
+}
+
+#[cfg(test)]
+impl RepoDetails {
+    pub(crate) fn new_test(ident: RepoIdent) -> Self {
+        let now = OffsetDateTime::now_utc();
+        Self {
+            ident,
+            renamed_to: None,
+            description: Some("description".to_string()),
+            last_pushed_at: Some(now),
+            total_pull_requests: 1,
+            stargazer_count: 10,
+            fork_count: 2,
+            issues: 3,
+            last_pullrequest_merged_at: Some(now),
+            primary_language: Some("Rust".to_string()),
+            languages: vec!["Rust".to_string()],
+            language_sizes: vec![LanguageSize {
+                name: "Rust".to_string(),
+                bytes: 1000,
+            }],
+            archived: false,
+            license: Some("MIT".to_string()),
+            topics: vec!["cli".to_string()],
+            first_seen_at: Some(now),
+            updated_at: now,
+        }
+    }
+}
+
+#[cfg(test)]
+impl FullRepoLink {
+    /// A link in `section`, with [`RepoDetails::new_test`] details.
+    pub(crate) fn new_test(ident: RepoIdent, section: &[&str]) -> Self {
+        Self {
+            link: RepoLink {
+                ident: ident.clone(),
+                section: section.iter().map(|x| x.to_string()).collect(),
+                description: None,
+            },
+            details: RepoDetails::new_test(ident),
+        }
+    }
+}
+
+#[cfg(test)]
+impl ReadmeRepo {
+    pub(crate) fn new_test(ident: RepoIdent, links: Vec<RepoIdent>) -> Self {
+        Self {
+            details: RepoDetails::new_test(ident),
+            readme_content: "readme".to_string(),
+            readme_etag: None,
+            readme_location: ReadmeLocation::default(),
+            repo_links: links
+                .into_iter()
+                .map(|ident| RepoLink {
+                    ident,
+                    section: vec!["section".to_string()],
+                    description: None,
+                })
+                .collect(),
+            updated_at: OffsetDateTime::now_utc(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_readme_repo_language_histogram() {
+        let link = |repo: &str, lang: Option<&str>| {
+            let mut link = FullRepoLink::new_test(RepoIdent::new_github("org", repo), &[]);
+            link.details.primary_language = lang.map(|x| x.to_string());
+            link
+        };
+        let repo = FullReadmeRepo {
+            repo: ReadmeRepo::new_test(RepoIdent::new_github("org", "list"), Vec::new()),
+            links: vec![
+                link("a", Some("Go")),
+                link("b", Some("Rust")),
+                link("c", Some("Rust")),
+                // Linked twice.
+                link("c", Some("Rust")),
+                link("d", Some("C")),
+                link("e", None),
+            ],
+            not_found: Vec::new(),
+            rate_limit_reset_at: None,
+        };
+
+        assert_eq!(
+            repo.language_histogram(),
+            vec![
+                ("Rust".to_string(), 2),
+                ("C".to_string(), 1),
+                ("Go".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_full_readme_repo_link_aggregates() {
+        let now = OffsetDateTime::now_utc();
+        let link = |repo: &str, stars: u32, activity_days: Option<i64>, archived: bool| {
+            let mut link = FullRepoLink::new_test(RepoIdent::new_github("org", repo), &[]);
+            link.details.stargazer_count = stars;
+            link.details.archived = archived;
+            link.details.last_pushed_at = activity_days.map(|x| now - time::Duration::days(x));
+            link.details.last_pullrequest_merged_at = None;
+            link
+        };
+        let mut repo = FullReadmeRepo {
+            repo: ReadmeRepo::new_test(RepoIdent::new_github("org", "list"), Vec::new()),
+            links: vec![
+                link("a", 10, Some(1), false),
+                link("b", 20, Some(400), false),
+                link("c", 30, Some(10), true),
+                // Linked twice.
+                link("c", 30, Some(10), true),
+                link("d", 40, None, false),
+            ],
+            not_found: Vec::new(),
+            rate_limit_reset_at: None,
+        };
+
+        assert_eq!(repo.total_link_stars(), 100);
+        assert_eq!(repo.inactive_link_count(365), 2);
+        assert_eq!(
+            repo.median_link_activity(),
+            Some(now - time::Duration::days(10))
+        );
+
+        // Even number of values.
+        repo.links.push(link("e", 0, Some(20), false));
+        assert_eq!(
+            repo.median_link_activity(),
+            Some(now - time::Duration::days(15))
+        );
+
+        repo.links.clear();
+        assert_eq!(repo.total_link_stars(), 0);
+        assert_eq!(repo.median_link_activity(), None);
+    }
+
+    #[test]
+    fn test_repo_ident_github_enterprise() {
+        let hosts = vec!["github.mycorp.com".to_string()];
+        let expected = RepoIdent::new_github("org", "repo").with_host("github.mycorp.com");
+
+        assert_eq!(
+            RepoIdent::parse_url_with_hosts("https://github.mycorp.com/org/repo", &hosts).unwrap(),
+            expected
+        );
+        assert_eq!(
+            RepoIdent::parse_ident_with_hosts("github.mycorp.com/org/repo", &hosts).unwrap(),
+            expected
+        );
+        assert!(RepoIdent::parse_url("https://github.mycorp.com/org/repo").is_err());
+
+        assert_eq!(expected.url(), "https://github.mycorp.com/org/repo");
+        assert_eq!(expected.pretty_url(), "github.mycorp.com/org/repo");
+        assert_eq!(
+            RepoIdent::from_path(expected.path_source(), "org", "repo", &hosts).unwrap(),
+            expected
+        );
+
+        // Default host is unchanged.
+        let public = RepoIdent::parse_url("https://github.com/org/repo").unwrap();
+        assert_eq!(public, RepoIdent::new_github("org", "repo"));
+        assert_eq!(public.url(), "https://github.com/org/repo");
+        assert_eq!(public.path_source(), "github");
+    }
+
+    #[test]
+    fn test_repo_details_deserialize_without_new_fields() {
+        let details = RepoDetails::new_test(RepoIdent::new_github("org", "repo"));
+        let mut value = serde_json::to_value(&details).unwrap();
+        // Data stored by older versions lacks the optional fields.
+        let obj = value.as_object_mut().unwrap();
+        obj.remove("archived");
+        obj.remove("license");
+        obj.remove("topics");
+        obj.remove("language_sizes");
+
+        let parsed: RepoDetails = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            parsed,
+            RepoDetails {
+                license: None,
+                topics: Vec::new(),
+                language_sizes: Vec::new(),
+                ..details
+            }
+        );
+    }
+
+    #[test]
+    fn test_repo_details_last_activity_exact() {
+        let mut details = RepoDetails::new_test(RepoIdent::new_github("a", "b"));
+        details.last_pushed_at = Some(time::macros::datetime!(2024-03-05 14:22 +02:00));
+        assert_eq!(
+            details.last_activity_exact().as_deref(),
+            Some("2024-03-05T12:22:00Z")
+        );
+
+        details.last_pushed_at = None;
+        details.last_pullrequest_merged_at = None;
+        assert_eq!(details.last_activity_exact(), None);
+    }
+
+    #[test]
+    fn test_repo_details_is_stale() {
+        let mut details = RepoDetails::new_test(RepoIdent::new_github("a", "b"));
+        let now = OffsetDateTime::now_utc();
+
+        details.last_pushed_at = Some(now - time::Duration::days(10));
+        assert!(!details.is_stale(365));
+
+        details.last_pushed_at = Some(now - time::Duration::days(400));
+        assert!(details.is_stale(365));
+
+        details.last_pushed_at = None;
+        details.last_pullrequest_merged_at = None;
+        assert!(!details.is_stale(365));
+    }
+
+    #[test]
+    fn test_repo_ident_normalize() {
+        let expected = RepoIdent::new_github("owner", "repo");
+        for input in [
+            "owner/repo",
+            "owner/repo/",
+            "owner/repo.git",
+            "github.com/owner/repo",
+            "github.com/owner/repo/",
+            "github.com/owner/repo.git",
+            "www.github.com/owner/repo",
+            "https://github.com/owner/repo",
+            "https://github.com/owner/repo/",
+            "https://github.com/owner/repo.git",
+            "https://www.github.com/owner/repo",
+            "https://www.github.com/owner/repo.git/",
+        ] {
+            assert_eq!(RepoIdent::parse_ident(input).unwrap(), expected, "{input}");
+        }
+
+        let hosts = vec!["github.example.com".to_string()];
+        assert_eq!(
+            RepoIdent::parse_ident_with_hosts("github.example.com/owner/repo.git/", &hosts)
+                .unwrap(),
+            RepoIdent::new_github("owner", "repo").with_host("github.example.com")
+        );
+    }
+
+    #[test]
+    fn test_repo_ident_codeberg() {
+        let expected = RepoIdent::new(Source::Codeberg, "owner", "repo");
+        for input in [
+            "codeberg.org/owner/repo",
+            "www.codeberg.org/owner/repo.git",
+            "https://codeberg.org/owner/repo",
+            "https://codeberg.org/owner/repo/src/branch/main/README.md",
+        ] {
+            assert_eq!(RepoIdent::parse_ident(input).unwrap(), expected, "{input}");
+        }
+        assert_eq!(expected.url(), "https://codeberg.org/owner/repo");
+        assert_eq!(expected.path_source(), "codeberg");
+        assert_eq!(
+            RepoIdent::from_path("codeberg", "owner", "repo", &[]).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_repo_ident_reserved_paths() {
+        for input in [
+            "https://github.com/orgs/rust-lang",
+            "https://github.com/sponsors/theduke",
+            "https://github.com/topics/rust",
+            "https://github.com/Marketplace/actions",
+            "github.com/features/copilot",
+            "https://github.com/user-attachments/assets/0123",
+        ] {
+            assert!(RepoIdent::parse_ident(input).is_err(), "{input}");
+        }
+
+        assert_eq!(
+            RepoIdent::parse_url("https://github.com/owner/repo/tree/main/subdir").unwrap(),
+            RepoIdent::new_github("owner", "repo")
+        );
+        assert_eq!(
+            RepoIdent::parse_url("https://github.com/owner/repo#readme").unwrap(),
+            RepoIdent::new_github("owner", "repo")
+        );
+    }
+
+    #[test]
+    fn test_repo_ident_validate() {
+        assert!(RepoIdent::new_github("my-org_1", "repo.rs")
+            .validate()
+            .is_ok());
+        assert!(RepoIdent::new_github("org", "repo")
+            .with_host("github.example.com:8443")
+            .validate()
+            .is_ok());
+
+        for (owner, repo) in [
+            ("../../etc", "repo"),
+            ("..", "repo"),
+            ("org", "."),
+            ("org/sub", "repo"),
+            ("org", "a\\b"),
+            ("", "repo"),
+            ("org", "re po"),
+        ] {
+            assert!(
+                RepoIdent::new_github(owner, repo).validate().is_err(),
+                "{owner}/{repo}"
+            );
+        }
+        assert!(RepoIdent::new_github("org", "repo")
+            .with_host("../host")
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_readme_location_from_input() {
+        assert_eq!(
+            ReadmeLocation::from_input(Some(" / "), Some("")).unwrap(),
+            None
+        );
+
+        let location = ReadmeLocation::from_input(Some("/docs/My List.md"), Some(" dev "))
+            .unwrap()
+            .unwrap();
+        assert_eq!(location.path.as_deref(), Some("docs/My List.md"));
+        assert_eq!(location.git_ref.as_deref(), Some("dev"));
+        assert_eq!(
+            location.encoded_path().as_deref(),
+            Some("docs/My%20List.md")
+        );
+
+        for path in [
+            "../../user",
+            "docs/../../user",
+            "./README.md",
+            "docs//README.md",
+            "README.md?ref=x",
+            "README.md#x",
+            "docs\\README.md",
+        ] {
+            assert!(
+                ReadmeLocation::from_input(Some(path), None).is_err(),
+                "{path}"
+            );
+        }
+
+        // Percent signs are encoded, so they can't smuggle in dot segments.
+        let location = ReadmeLocation::from_input(Some("%2e%2e/x"), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(location.encoded_path().as_deref(), Some("%252e%252e/x"));
+    }
+
+    #[test]
+    fn test_repo_details_keep_first_seen() {
+        let ident = RepoIdent::new_github("org", "repo");
+        let item = |first_seen_at| {
+            let mut details = RepoDetails::new_test(ident.clone());
+            details.first_seen_at = first_seen_at;
+            RepoDetailsItem::Found(details)
+        };
+        let first_seen_at = |item: &RepoDetailsItem| match item {
+            RepoDetailsItem::Found(details) => details.first_seen_at,
+            RepoDetailsItem::NotFound { .. } => unreachable!(),
+        };
+        let earlier = time::macros::datetime!(2024-01-01 00:00 UTC);
+        let now = OffsetDateTime::now_utc();
+
+        let mut fresh = item(Some(now));
+        fresh.keep_first_seen(None);
+        assert_eq!(first_seen_at(&fresh), Some(now));
+
+        let mut fresh = item(Some(now));
+        fresh.keep_first_seen(Some(&item(Some(earlier))));
+        assert_eq!(first_seen_at(&fresh), Some(earlier));
+
+        // Repos stored before the time was tracked are not marked as new.
+        let mut fresh = item(Some(now));
+        fresh.keep_first_seen(Some(&item(None)));
+        assert_eq!(first_seen_at(&fresh), None);
+    }
+
+    #[test]
+    fn test_repo_details_first_seen_format() {
+        let mut details = RepoDetails::new_test(RepoIdent::new_github("org", "repo"));
+        let at = time::macros::datetime!(2024-07-01 12:00 UTC);
+        details.first_seen_at = Some(at);
+        details.last_pushed_at = Some(at);
+
+        let mut value = serde_json::to_value(&details).unwrap();
+        assert_eq!(value["first_seen_at"], value["last_pushed_at"]);
+
+        // Values written in RFC 3339 format are still readable.
+        value["first_seen_at"] = "2024-07-01T12:00:00Z".into();
+        let parsed: RepoDetails = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.first_seen_at, Some(at));
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+/// Token bucket rate limiter.
+///
+/// Allows bursts of up to `capacity` requests, and refills at `rate` requests
+/// per second.
+pub struct Throttle {
+    rate: f64,
+    capacity: f64,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    /// Available tokens. Negative if requests are waiting for tokens.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Throttle {
+    /// Create a throttle allowing `rate` requests per second.
+    ///
+    /// The burst capacity equals one second worth of requests.
+    pub fn new(rate: f64) -> Self {
+        let capacity = rate.max(1.0);
+        Self {
+            rate,
+            capacity,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a request may be made.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut state = self.state.lock().await;
+            let now = Instant::now();
+            let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+            state.tokens = (state.tokens + elapsed * self.rate).min(self.capacity);
+            state.last_refill = now;
+
+            // Reserve a token, even if it is not available yet, so waiting
+            // requests are served in order.
+            state.tokens -= 1.0;
+            if state.tokens >= 0.0 {
+                return;
+            }
+            // Tiny rates can exceed the range of a duration.
+            Duration::try_from_secs_f64(-state.tokens / self.rate).unwrap_or(Duration::MAX)
+        };
+
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_throttle_paces_requests() {
+        let throttle = Throttle::new(20.0);
+        let start = Instant::now();
+
+        // The first 20 are served from the burst capacity.
+        for _ in 0..25 {
+            throttle.acquire().await;
+        }
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(240), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(1), "{elapsed:?}");
+    }
+}
@@ -1,19 +1,37 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use anyhow::Context;
+use futures::{stream::BoxStream, StreamExt};
+use time::OffsetDateTime;
 
 use crate::source::{ReadmeRepo, RepoDetailsItem, RepoIdent};
 
-use super::Item;
+use super::{ExportFilter, ImportStats, Item, ItemKind, StarSnapshot, Task, MAX_STAR_SNAPSHOTS};
 
 #[derive(Clone, Debug)]
 pub struct FsStore {
     root: PathBuf,
+    readonly: bool,
 }
 
+/// Maximum number of blocking tasks used by [`FsStore::repo_details_multi`].
+const MULTI_GET_TASKS: usize = 8;
+/// Minimum number of idents read by each of these tasks.
+const MULTI_GET_MIN_CHUNK: usize = 16;
+/// Size above which a star history file is compacted to the latest
+/// [`MAX_STAR_SNAPSHOTS`] entries (roughly twice the capped size).
+const STAR_HISTORY_COMPACT_BYTES: u64 = MAX_STAR_SNAPSHOTS as u64 * 2 * 64;
+
 impl FsStore {
     pub fn new(root: PathBuf) -> Result<Self, anyhow::Error> {
-        let s = Self { root };
+        Self::check_data_dir(&root)?;
+        let s = Self {
+            root,
+            readonly: false,
+        };
 
         let p = s.repo_details_dir();
         std::fs::create_dir_all(&p)
@@ -26,40 +44,147 @@ impl FsStore {
         Ok(s)
     }
 
+    /// Open an existing data directory without writing to it.
+    ///
+    /// Used for readonly servers, for example on a read-only volume.
+    pub fn new_readonly(root: PathBuf) -> Result<Self, anyhow::Error> {
+        Self::check_data_dir_readable(&root)?;
+        Ok(Self {
+            root,
+            readonly: true,
+        })
+    }
+
+    fn check_data_dir_readable(root: &Path) -> Result<(), anyhow::Error> {
+        std::fs::read_dir(root)
+            .with_context(|| format!("data directory '{}' is not readable", root.display()))?;
+        Ok(())
+    }
+
+    /// Ensure the data directory exists and is writable.
+    ///
+    /// Creates and removes a probe file, to fail early with an actionable
+    /// error instead of on the first write.
+    fn check_data_dir(root: &Path) -> Result<(), anyhow::Error> {
+        if root.exists() && !root.is_dir() {
+            anyhow::bail!(
+                "data directory '{}' exists but is not a directory",
+                root.display()
+            );
+        }
+        std::fs::create_dir_all(root).with_context(|| {
+            format!(
+                "failed to create data directory '{}' - check that the parent directory exists and is writable",
+                root.display()
+            )
+        })?;
+
+        probe_writable_sync(root).with_context(|| {
+                format!(
+                    "data directory '{}' is not writable - check permissions and that the volume is not mounted read-only",
+                    root.display()
+                )
+            })?;
+
+        Ok(())
+    }
+
     fn repo_details_dir(&self) -> PathBuf {
         self.root.join("repo_details")
     }
 
-    fn ident_to_storage_name(ident: &RepoIdent) -> String {
-        format!("{}:{}:{}.json", ident.source, ident.owner, ident.repo)
+    /// File name of an ident.
+    ///
+    /// Fails for idents that are not safe to use as a file name.
+    fn ident_to_storage_name(ident: &RepoIdent) -> Result<String, anyhow::Error> {
+        ident
+            .validate()
+            .with_context(|| format!("invalid repo ident: '{ident}'"))?;
+        Ok(match &ident.host {
+            Some(host) => format!(
+                "{}:{}:{}:{}.json",
+                ident.source, host, ident.owner, ident.repo
+            ),
+            None => format!("{}:{}:{}.json", ident.source, ident.owner, ident.repo),
+        })
+    }
+
+    /// Name of the shard subdirectory for a storage file name.
+    ///
+    /// Uses FNV-1a, since the std hasher is not stable across Rust releases.
+    fn shard_name(storage_name: &str) -> String {
+        let mut hash: u32 = 0x811c9dc5;
+        for byte in storage_name.bytes() {
+            hash ^= u32::from(byte);
+            hash = hash.wrapping_mul(0x01000193);
+        }
+        format!("{:02x}", hash & 0xff)
+    }
+
+    fn sharded_path(dir: PathBuf, ident: &RepoIdent) -> Result<PathBuf, anyhow::Error> {
+        let name = Self::ident_to_storage_name(ident)?;
+        Ok(dir.join(Self::shard_name(&name)).join(name))
+    }
+
+    fn repo_details_path(&self, ident: &RepoIdent) -> Result<PathBuf, anyhow::Error> {
+        Self::sharded_path(self.repo_details_dir(), ident)
     }
 
-    fn repo_details_path(&self, ident: &RepoIdent) -> PathBuf {
-        self.repo_details_dir()
-            .join(Self::ident_to_storage_name(ident))
+    /// Path used before files were sharded into subdirectories.
+    fn repo_details_legacy_path(&self, ident: &RepoIdent) -> Result<PathBuf, anyhow::Error> {
+        Ok(self
+            .repo_details_dir()
+            .join(Self::ident_to_storage_name(ident)?))
     }
 
     fn readme_repo_dir(&self) -> PathBuf {
         self.root.join("readme_repo")
     }
 
-    fn readme_repo_path(&self, ident: &RepoIdent) -> PathBuf {
-        self.readme_repo_dir()
-            .join(Self::ident_to_storage_name(ident))
+    fn readme_repo_path(&self, ident: &RepoIdent) -> Result<PathBuf, anyhow::Error> {
+        Self::sharded_path(self.readme_repo_dir(), ident)
+    }
+
+    /// Path used before files were sharded into subdirectories.
+    fn readme_repo_legacy_path(&self, ident: &RepoIdent) -> Result<PathBuf, anyhow::Error> {
+        Ok(self
+            .readme_repo_dir()
+            .join(Self::ident_to_storage_name(ident)?))
+    }
+
+    /// Star history is stored as JSON lines, to allow appending.
+    fn star_history_path(&self, ident: &RepoIdent) -> Result<PathBuf, anyhow::Error> {
+        Ok(Self::sharded_path(self.root.join("star_history"), ident)?.with_extension("jsonl"))
+    }
+
+    fn tasks_path(&self) -> PathBuf {
+        self.root.join("tasks.json")
     }
 
     fn repo_details_sync(
         &self,
         ident: &RepoIdent,
     ) -> Result<Option<RepoDetailsItem>, anyhow::Error> {
-        let path = self.repo_details_path(ident);
-        match std::fs::read(&path) {
-            Ok(data) => {
+        let path = self.repo_details_path(ident)?;
+        let legacy_path = self.repo_details_legacy_path(ident)?;
+        match read_with_legacy_sync(&path, &legacy_path)? {
+            Some(data) => {
                 let details = serde_json::from_slice(&data)?;
                 Ok(Some(details))
             }
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(e).context(format!("failed to read file: '{}'", path.display())),
+            None => Ok(None),
+        }
+    }
+
+    fn readme_repo_sync(&self, ident: &RepoIdent) -> Result<Option<ReadmeRepo>, anyhow::Error> {
+        let path = self.readme_repo_path(ident)?;
+        let legacy_path = self.readme_repo_legacy_path(ident)?;
+        match read_with_legacy_sync(&path, &legacy_path)? {
+            Some(data) => {
+                let readme = serde_json::from_slice(&data)?;
+                Ok(Some(readme))
+            }
+            None => Ok(None),
         }
     }
 
@@ -83,6 +208,226 @@ impl FsStore {
     }
 }
 
+/// Unique name of a temporary file for `file_name`.
+///
+/// Unique so concurrent writers don't interfere. Does not end in .json, to be
+/// ignored by the list functions.
+fn tmp_file_name(file_name: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Check that a directory is writable by creating and removing a probe file.
+fn probe_writable_sync(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(tmp_file_name("write-probe"));
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)
+}
+
+/// Write a file by writing to a temporary file in the same directory and
+/// renaming it into place.
+///
+/// Prevents truncated files if the process is killed during the write.
+async fn write_atomic(path: &Path, data: &[u8]) -> Result<(), anyhow::Error> {
+    let parent = path
+        .parent()
+        .with_context(|| format!("path has no parent directory: '{}'", path.display()))?;
+    tokio::fs::create_dir_all(parent)
+        .await
+        .with_context(|| format!("failed to create directory: '{}'", parent.display()))?;
+
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("path has no file name: '{}'", path.display()))?
+        .to_string_lossy();
+    let tmp_path = parent.join(tmp_file_name(&file_name));
+
+    if let Err(e) = tokio::fs::write(&tmp_path, data).await {
+        tokio::fs::remove_file(&tmp_path).await.ok();
+        return Err(e).context(format!("failed to write file: '{}'", tmp_path.display()));
+    }
+
+    if let Err(e) = tokio::fs::rename(&tmp_path, path).await {
+        tokio::fs::remove_file(&tmp_path).await.ok();
+        return Err(e).context(format!(
+            "failed to rename '{}' to '{}'",
+            tmp_path.display(),
+            path.display()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Read a file, falling back to the legacy unsharded location.
+fn read_with_legacy_sync(
+    path: &Path,
+    legacy_path: &Path,
+) -> Result<Option<Vec<u8>>, anyhow::Error> {
+    for path in [path, legacy_path] {
+        match std::fs::read(path) {
+            Ok(data) => return Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).context(format!("failed to read file: '{}'", path.display()));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Remove a file, succeeding if it does not exist.
+async fn remove_file_if_exists(path: &Path) -> Result<(), anyhow::Error> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).context(format!("failed to remove file: '{}'", path.display())),
+    }
+}
+
+/// Number of items read ahead of the consumer of [`FsStore::export_stream`].
+const EXPORT_STREAM_BUFFER: usize = 16;
+
+type ItemSender = tokio::sync::mpsc::Sender<Result<Item, anyhow::Error>>;
+
+fn is_json_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+impl FsStore {
+    /// Read all items and send them to `tx`, one file at a time.
+    ///
+    /// Stops early if the receiver is dropped.
+    async fn export_to(&self, tx: &ItemSender) -> Result<(), anyhow::Error> {
+        type Parse = fn(&[u8]) -> Result<Item, serde_json::Error>;
+        let dirs: [(PathBuf, Parse); 2] = [
+            (self.repo_details_dir(), |data| {
+                serde_json::from_slice(data).map(Item::Repo)
+            }),
+            (self.readme_repo_dir(), |data| {
+                serde_json::from_slice(data).map(Item::ReadmeRepo)
+            }),
+        ];
+
+        for (dir, parse) in dirs {
+            let mut iter = match tokio::fs::read_dir(&dir).await {
+                Ok(iter) => iter,
+                // Not created by readonly stores.
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(e).context(format!("failed to read directory: '{}'", dir.display()))
+                }
+            };
+
+            while let Some(entry) = iter.next_entry().await? {
+                let path = entry.path();
+
+                if entry.file_type().await?.is_dir() {
+                    let mut shard_iter = tokio::fs::read_dir(&path).await.with_context(|| {
+                        format!("failed to read directory: '{}'", path.display())
+                    })?;
+                    while let Some(entry) = shard_iter.next_entry().await? {
+                        if !export_file(&entry.path(), parse, tx).await? {
+                            return Ok(());
+                        }
+                    }
+                } else if is_json_file(&path) {
+                    // Skip legacy files that were superseded by a sharded file.
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let sharded = dir.join(Self::shard_name(&name)).join(&name);
+                    if tokio::fs::try_exists(&sharded).await? {
+                        continue;
+                    }
+                    if !export_file(&path, parse, tx).await? {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Read and send a single item file.
+///
+/// Returns `false` if the receiver was dropped.
+async fn export_file(
+    path: &Path,
+    parse: fn(&[u8]) -> Result<Item, serde_json::Error>,
+    tx: &ItemSender,
+) -> Result<bool, anyhow::Error> {
+    if !is_json_file(path) {
+        return Ok(true);
+    }
+
+    let data = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("failed to read file: '{}'", path.display()))?;
+    match parse(&data) {
+        Ok(item) => Ok(tx.send(Ok(item)).await.is_ok()),
+        Err(e) => {
+            tracing::error!("failed to parse json file: '{}': {}", path.display(), e);
+            Ok(true)
+        }
+    }
+}
+
+/// List all JSON files in a storage directory.
+///
+/// Files in shard subdirectories are listed before legacy files stored
+/// directly in the directory.
+async fn list_json_files(dir: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let mut sharded = Vec::new();
+    let mut legacy = Vec::new();
+
+    let mut iter = match tokio::fs::read_dir(dir).await {
+        Ok(iter) => iter,
+        // Not created by readonly stores.
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context(format!("failed to read directory: '{}'", dir.display())),
+    };
+
+    while let Some(entry) = iter.next_entry().await? {
+        let path = entry.path();
+
+        if entry.file_type().await?.is_dir() {
+            let mut shard_iter = tokio::fs::read_dir(&path)
+                .await
+                .with_context(|| format!("failed to read directory: '{}'", path.display()))?;
+            while let Some(entry) = shard_iter.next_entry().await? {
+                let path = entry.path();
+                if is_json_file(&path) {
+                    sharded.push(path);
+                }
+            }
+        } else if is_json_file(&path) {
+            legacy.push(path);
+        }
+    }
+
+    sharded.extend(legacy);
+    Ok(sharded)
+}
+
+/// List all JSON files in a storage directory, sorted by file name.
+///
+/// Legacy files superseded by a sharded file are skipped.
+async fn sorted_json_files(dir: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let mut files = list_json_files(dir).await?;
+    // The sort is stable, so dedup keeps sharded files over legacy ones.
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    files.dedup_by(|a, b| a.file_name() == b.file_name());
+    Ok(files)
+}
+
 impl super::Storage for FsStore {
     async fn repo_details(
         &self,
@@ -94,48 +439,60 @@ impl super::Storage for FsStore {
             .context("failed to spawn blocking task")?
     }
 
+    /// Files are read in parallel by up to [`MULTI_GET_TASKS`] blocking
+    /// tasks. Results keep the order of the idents.
     async fn repo_details_multi(
         &self,
         idents: Vec<RepoIdent>,
     ) -> Result<Vec<RepoDetailsItem>, anyhow::Error> {
-        let s = self.clone();
-        tokio::task::spawn_blocking(move || s.repo_details_multi_sync(idents))
+        let chunk_size = idents
+            .len()
+            .div_ceil(MULTI_GET_TASKS)
+            .max(MULTI_GET_MIN_CHUNK);
+        let tasks = idents.chunks(chunk_size).map(|chunk| {
+            let s = self.clone();
+            let chunk = chunk.to_vec();
+            tokio::task::spawn_blocking(move || s.repo_details_multi_sync(chunk))
+        });
+
+        let mut list = Vec::with_capacity(idents.len());
+        for chunk in futures::future::try_join_all(tasks)
             .await
             .context("failed to spawn blocking task")?
-    }
-
-    async fn repo_details_upsert(&self, details: RepoDetailsItem) -> Result<(), anyhow::Error> {
-        let path = self.repo_details_path(details.ident());
-        let data = serde_json::to_vec(&details)?;
-
-        if let Some(parent) = path.parent() {
-            tokio::fs::create_dir_all(parent)
-                .await
-                .with_context(|| format!("failed to create directory: '{}'", parent.display()))?;
+        {
+            list.extend(chunk?);
         }
+        Ok(list)
+    }
 
-        tokio::fs::write(&path, data)
+    async fn repo_details_upsert(&self, mut details: RepoDetailsItem) -> Result<(), anyhow::Error> {
+        let path = self.repo_details_path(details.ident())?;
+        // A broken previous version is replaced.
+        let previous = self
+            .repo_details(details.ident().clone())
             .await
-            .with_context(|| format!("failed to write file: '{}'", path.display()))?;
+            .unwrap_or_else(|err| {
+                tracing::warn!(
+                    ident=%details.ident(),
+                    "failed to read previous repo details: {:#}",
+                    err
+                );
+                None
+            });
+        details.keep_first_seen(previous.as_ref());
 
-        Ok(())
+        let data = serde_json::to_vec(&details)?;
+
+        write_atomic(&path, &data).await?;
+        // Remove the file from the legacy unsharded location.
+        remove_file_if_exists(&self.repo_details_legacy_path(details.ident())?).await
     }
 
     async fn repo_details_list(&self) -> Result<Vec<RepoDetailsItem>, anyhow::Error> {
         let mut list = Vec::new();
         let dir = self.repo_details_dir();
 
-        let mut iter = tokio::fs::read_dir(&dir)
-            .await
-            .with_context(|| format!("failed to read directory: '{}'", dir.display()))?;
-
-        while let Some(entry) = iter.next_entry().await? {
-            let path = entry.path();
-            let is_json = path.extension().map_or(false, |ext| ext == "json");
-            if !is_json {
-                continue;
-            }
-
+        for path in list_json_files(&dir).await? {
             let data = tokio::fs::read(&path)
                 .await
                 .with_context(|| format!("failed to read file: '{}'", path.display()))?;
@@ -154,55 +511,40 @@ impl super::Storage for FsStore {
             }
         }
 
+        // Directory iteration order is not stable.
+        // The sort is stable, so dedup keeps sharded files over legacy ones.
+        list.sort_by(|a, b| a.ident().cmp(b.ident()));
+        list.dedup_by(|a, b| a.ident() == b.ident());
+
         Ok(list)
     }
 
-    async fn readme_repo(&self, ident: RepoIdent) -> Result<Option<ReadmeRepo>, anyhow::Error> {
-        let path = self.readme_repo_path(&ident);
-
-        match tokio::fs::read(&path).await {
-            Ok(data) => {
-                let readme = serde_json::from_slice(&data)?;
+    async fn repo_details_delete(&self, ident: RepoIdent) -> Result<(), anyhow::Error> {
+        remove_file_if_exists(&self.repo_details_path(&ident)?).await?;
+        remove_file_if_exists(&self.repo_details_legacy_path(&ident)?).await
+    }
 
-                Ok(Some(readme))
-            }
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(e).context(format!("failed to read file: '{}'", path.display())),
-        }
+    async fn readme_repo(&self, ident: RepoIdent) -> Result<Option<ReadmeRepo>, anyhow::Error> {
+        let s = self.clone();
+        tokio::task::spawn_blocking(move || s.readme_repo_sync(&ident))
+            .await
+            .context("failed to spawn blocking task")?
     }
 
     async fn readme_repo_upsert(&self, readme: ReadmeRepo) -> Result<(), anyhow::Error> {
-        let path = self.readme_repo_path(&readme.details.ident);
+        let path = self.readme_repo_path(&readme.details.ident)?;
         let data = serde_json::to_vec(&readme)?;
 
-        if let Some(parent) = path.parent() {
-            tokio::fs::create_dir_all(parent)
-                .await
-                .with_context(|| format!("failed to create directory: '{}'", parent.display()))?;
-        }
-
-        tokio::fs::write(&path, data)
-            .await
-            .with_context(|| format!("failed to write file: '{}'", path.display()))?;
-
-        Ok(())
+        write_atomic(&path, &data).await?;
+        // Remove the file from the legacy unsharded location.
+        remove_file_if_exists(&self.readme_repo_legacy_path(&readme.details.ident)?).await
     }
 
     async fn readme_repo_list(&self) -> Result<Vec<ReadmeRepo>, anyhow::Error> {
         let mut list = Vec::new();
         let dir = self.readme_repo_dir();
 
-        let mut iter = tokio::fs::read_dir(&dir)
-            .await
-            .with_context(|| format!("failed to read directory: '{}'", dir.display()))?;
-
-        while let Some(entry) = iter.next_entry().await? {
-            let path = entry.path();
-            let is_json = path.extension().map_or(false, |ext| ext == "json");
-            if !is_json {
-                continue;
-            }
-
+        for path in list_json_files(&dir).await? {
             let data = tokio::fs::read(&path)
                 .await
                 .with_context(|| format!("failed to read file: '{}'", path.display()))?;
@@ -221,77 +563,570 @@ impl super::Storage for FsStore {
             }
         }
 
+        // Directory iteration order is not stable.
+        // The sort is stable, so dedup keeps sharded files over legacy ones.
+        list.sort_by(|a, b| a.details.ident.cmp(&b.details.ident));
+        list.dedup_by(|a, b| a.details.ident == b.details.ident);
+
         Ok(list)
     }
 
-    async fn export(&self) -> Result<Vec<Item>, anyhow::Error> {
-        let details = self.repo_details_list().await?.into_iter().map(Item::Repo);
-        let readmes = self
-            .readme_repo_list()
+    async fn readme_repo_delete(&self, ident: RepoIdent) -> Result<(), anyhow::Error> {
+        remove_file_if_exists(&self.readme_repo_path(&ident)?).await?;
+        remove_file_if_exists(&self.readme_repo_legacy_path(&ident)?).await
+    }
+
+    async fn record_star_snapshot(
+        &self,
+        ident: RepoIdent,
+        stars: u32,
+        at: OffsetDateTime,
+    ) -> Result<(), anyhow::Error> {
+        let path = self.star_history_path(&ident)?;
+        let mut line = serde_json::to_vec(&StarSnapshot { at, stars })?;
+        line.push(b'\n');
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("failed to create directory: '{}'", parent.display()))?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .with_context(|| format!("failed to open file: '{}'", path.display()))?;
+        tokio::io::AsyncWriteExt::write_all(&mut file, &line)
+            .await
+            .with_context(|| format!("failed to write file: '{}'", path.display()))?;
+
+        // Reading ignores entries beyond the cap, so the file only needs to
+        // be rewritten once in a while.
+        let len = file
+            .metadata()
+            .await
+            .with_context(|| format!("failed to read metadata: '{}'", path.display()))?
+            .len();
+        if len > STAR_HISTORY_COMPACT_BYTES {
+            let history = self.star_history(ident).await?;
+            let mut data = Vec::new();
+            for snapshot in &history {
+                serde_json::to_writer(&mut data, snapshot)?;
+                data.push(b'\n');
+            }
+            write_atomic(&path, &data).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn star_history(&self, ident: RepoIdent) -> Result<Vec<StarSnapshot>, anyhow::Error> {
+        let path = self.star_history_path(&ident)?;
+
+        let data = match tokio::fs::read_to_string(&path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context(format!("failed to read file: '{}'", path.display())),
+        };
+
+        let mut history = Vec::new();
+        for line in data.lines().filter(|x| !x.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(snapshot) => history.push(snapshot),
+                // A partially written last line is possible after a crash.
+                Err(e) => {
+                    tracing::warn!(
+                        "skipping invalid star snapshot in '{}': {}",
+                        path.display(),
+                        e
+                    );
+                }
+            }
+        }
+        if history.len() > MAX_STAR_SNAPSHOTS {
+            history.drain(..history.len() - MAX_STAR_SNAPSHOTS);
+        }
+
+        Ok(history)
+    }
+
+    async fn star_history_delete(&self, ident: RepoIdent) -> Result<(), anyhow::Error> {
+        remove_file_if_exists(&self.star_history_path(&ident)?).await
+    }
+
+    async fn tasks(&self) -> Result<Vec<Task>, anyhow::Error> {
+        let path = self.tasks_path();
+
+        match tokio::fs::read(&path).await {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("failed to parse tasks file: '{}'", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e).context(format!("failed to read file: '{}'", path.display())),
+        }
+    }
+
+    async fn tasks_replace(&self, tasks: Vec<Task>) -> Result<(), anyhow::Error> {
+        let path = self.tasks_path();
+        let data = serde_json::to_vec(&tasks)?;
+
+        write_atomic(&path, &data).await
+    }
+
+    async fn check_ready(&self) -> Result<(), anyhow::Error> {
+        if self.readonly {
+            return Self::check_data_dir_readable(&self.root);
+        }
+
+        let root = self.root.clone();
+        tokio::task::spawn_blocking(move || probe_writable_sync(&root))
             .await?
-            .into_iter()
-            .map(Item::ReadmeRepo);
+            .with_context(|| format!("data directory '{}' is not writable", self.root.display()))
+    }
 
-        let items = details.chain(readmes).collect();
-        Ok(items)
+    async fn export(&self) -> Result<Vec<Item>, anyhow::Error> {
+        super::export_items(self).await
     }
 
-    async fn import(&self, items: Vec<Item>) -> Result<(), anyhow::Error> {
-        let mut inserted = 0;
-        let mut skipped = 0;
+    fn export_stream(&self) -> BoxStream<'static, Result<Item, anyhow::Error>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(EXPORT_STREAM_BUFFER);
+        let s = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = s.export_to(&tx).await {
+                // Fails if the receiver is gone, which is fine.
+                tx.send(Err(err)).await.ok();
+            }
+        });
 
-        for item in items {
-            match item {
-                Item::Repo(imported) => {
-                    let existing = self.repo_details(imported.ident().clone()).await?;
+        futures::stream::unfold(rx, |mut rx| async move {
+            let item = rx.recv().await?;
+            Some((item, rx))
+        })
+        .boxed()
+    }
 
-                    let should_insert = match (&imported, &existing) {
-                        (RepoDetailsItem::Found(_), Some(RepoDetailsItem::NotFound { .. })) => true,
-                        (RepoDetailsItem::Found(new), Some(RepoDetailsItem::Found(old))) => {
-                            new.updated_at > old.updated_at
-                        }
-                        (
-                            RepoDetailsItem::NotFound {
-                                updated_at: new, ..
-                            },
-                            Some(RepoDetailsItem::NotFound {
-                                updated_at: old, ..
-                            }),
-                        ) => new > old,
-                        (RepoDetailsItem::NotFound { .. }, Some(RepoDetailsItem::Found(_))) => {
-                            false
-                        }
+    /// Items are ordered by file name, so only the files of the requested
+    /// slice are read, unless filtering by update time.
+    async fn export_slice(&self, filter: ExportFilter) -> Result<Vec<Item>, anyhow::Error> {
+        type Parse = fn(&[u8]) -> Result<Item, serde_json::Error>;
+        let dirs: [(ItemKind, PathBuf, Parse); 2] = [
+            (ItemKind::Repo, self.repo_details_dir(), |data| {
+                serde_json::from_slice(data).map(Item::Repo)
+            }),
+            (ItemKind::Readme, self.readme_repo_dir(), |data| {
+                serde_json::from_slice(data).map(Item::ReadmeRepo)
+            }),
+        ];
+        let limit = filter.limit.unwrap_or(usize::MAX);
 
-                        (_, None) => true,
-                    };
+        let mut items = Vec::new();
+        let mut skip = filter.offset;
+        for (kind, dir, parse) in dirs {
+            if filter.kind.is_some_and(|x| x != kind) {
+                continue;
+            }
 
-                    if should_insert {
-                        self.repo_details_upsert(imported).await?;
-                        inserted += 1;
-                    } else {
-                        skipped += 1;
-                    }
+            for path in sorted_json_files(&dir).await? {
+                if items.len() >= limit {
+                    return Ok(items);
+                }
+                // Without a time filter, every file matches.
+                if skip > 0 && filter.updated_since.is_none() {
+                    skip -= 1;
+                    continue;
                 }
-                Item::ReadmeRepo(imported) => {
-                    let old = self.readme_repo(imported.details.ident.clone()).await?;
-
-                    let should_insert = match old {
-                        Some(old) => imported.updated_at > old.updated_at,
-                        None => true,
-                    };
-
-                    if should_insert {
-                        self.readme_repo_upsert(imported).await?;
-                        inserted += 1;
-                    } else {
-                        skipped += 1;
+
+                let data = tokio::fs::read(&path)
+                    .await
+                    .with_context(|| format!("failed to read file: '{}'", path.display()))?;
+                let item = match parse(&data) {
+                    Ok(item) => item,
+                    Err(e) => {
+                        tracing::error!("failed to parse json file: '{}': {}", path.display(), e);
+                        continue;
                     }
+                };
+                if !filter.matches(&item) {
+                    continue;
+                }
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
                 }
+                items.push(item);
             }
         }
 
-        tracing::info!(%skipped, %inserted, "import complete");
+        Ok(items)
+    }
 
-        Ok(())
+    async fn import(&self, items: Vec<Item>) -> Result<ImportStats, anyhow::Error> {
+        super::import_items(self, items, false).await
+    }
+
+    async fn import_dry_run(&self, items: Vec<Item>) -> Result<ImportStats, anyhow::Error> {
+        super::import_items(self, items, true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::Storage;
+
+    use super::*;
+
+    #[test]
+    fn test_fs_store_data_dir_is_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        std::fs::write(&path, "").unwrap();
+
+        let err = FsStore::new(path.clone()).unwrap_err().to_string();
+        assert!(err.contains(&path.display().to_string()), "{err}");
+        assert!(err.contains("not a directory"), "{err}");
+
+        let err = FsStore::new(path.join("sub")).unwrap_err().to_string();
+        assert!(err.contains("failed to create data directory"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fs_store_data_dir_unwritable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("readonly");
+        std::fs::create_dir(&path).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+        // Permissions are not enforced for root.
+        if std::fs::write(path.join("check"), "").is_ok() {
+            return;
+        }
+
+        let err = FsStore::new(path.clone()).unwrap_err().to_string();
+        assert!(err.contains(&path.display().to_string()), "{err}");
+        assert!(err.contains("not writable"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_fs_store_check_ready_concurrent() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsStore::new(dir.path().to_owned()).unwrap();
+
+        let checks = (0..16).map(|_| store.check_ready());
+        for res in futures::future::join_all(checks).await {
+            res.unwrap();
+        }
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fs_store_upsert_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsStore::new(dir.path().to_owned()).unwrap();
+
+        let ident = RepoIdent::new_github("a", "b");
+        let readme = ReadmeRepo::new_test(ident.clone(), vec![RepoIdent::new_github("c", "d")]);
+
+        // Write twice to also cover overwriting an existing file.
+        store.readme_repo_upsert(readme.clone()).await.unwrap();
+        store.readme_repo_upsert(readme.clone()).await.unwrap();
+
+        let loaded = store.readme_repo(ident.clone()).await.unwrap();
+        assert_eq!(loaded, Some(readme));
+
+        // No temporary files must be left behind.
+        let shard_dir = store
+            .readme_repo_path(&ident)
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_owned();
+        let files: Vec<_> = std::fs::read_dir(shard_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(files, vec!["github:a:b.json".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_fs_store_star_history_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsStore::new(dir.path().to_owned()).unwrap();
+
+        let ident = RepoIdent::new_github("a", "b");
+        let start = OffsetDateTime::UNIX_EPOCH;
+        for index in 0..(MAX_STAR_SNAPSHOTS + 5) {
+            let at = start + time::Duration::days(index as i64);
+            store
+                .record_star_snapshot(ident.clone(), index as u32, at)
+                .await
+                .unwrap();
+        }
+
+        let history = store.star_history(ident.clone()).await.unwrap();
+        assert_eq!(history.len(), MAX_STAR_SNAPSHOTS);
+        assert_eq!(history[0].stars, 5);
+        assert_eq!(history.last().unwrap().stars, MAX_STAR_SNAPSHOTS as u32 + 4);
+
+        // The file is compacted once it grows too large.
+        for index in 0..(MAX_STAR_SNAPSHOTS * 3) {
+            let at = start + time::Duration::days(index as i64);
+            store
+                .record_star_snapshot(ident.clone(), 0, at)
+                .await
+                .unwrap();
+        }
+        let path = store.star_history_path(&ident).unwrap();
+        let len = std::fs::metadata(path).unwrap().len();
+        assert!(len <= STAR_HISTORY_COMPACT_BYTES);
+
+        let empty = store
+            .star_history(RepoIdent::new_github("a", "c"))
+            .await
+            .unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fs_store_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsStore::new(dir.path().to_owned()).unwrap();
+
+        let ident = RepoIdent::new_github("a", "b");
+        let details = RepoDetailsItem::Found(crate::source::RepoDetails::new_test(ident.clone()));
+        store.repo_details_upsert(details).await.unwrap();
+        store
+            .readme_repo_upsert(ReadmeRepo::new_test(ident.clone(), Vec::new()))
+            .await
+            .unwrap();
+
+        store.repo_details_delete(ident.clone()).await.unwrap();
+        store.readme_repo_delete(ident.clone()).await.unwrap();
+        assert_eq!(store.repo_details(ident.clone()).await.unwrap(), None);
+        assert_eq!(store.readme_repo(ident.clone()).await.unwrap(), None);
+
+        // Deleting missing items succeeds.
+        store.repo_details_delete(ident.clone()).await.unwrap();
+        store.readme_repo_delete(ident).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fs_store_reads_legacy_unsharded_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsStore::new(dir.path().to_owned()).unwrap();
+
+        let ident = RepoIdent::new_github("a", "b");
+        let details = RepoDetailsItem::Found(crate::source::RepoDetails::new_test(ident.clone()));
+
+        // Write a file in the old flat layout.
+        let legacy_path = store.repo_details_legacy_path(&ident).unwrap();
+        std::fs::write(&legacy_path, serde_json::to_vec(&details).unwrap()).unwrap();
+
+        assert_eq!(
+            store.repo_details(ident.clone()).await.unwrap(),
+            Some(details.clone())
+        );
+        assert_eq!(
+            store.repo_details_list().await.unwrap(),
+            vec![details.clone()]
+        );
+
+        // Upserting moves the file into its shard directory.
+        store.repo_details_upsert(details.clone()).await.unwrap();
+        assert!(!legacy_path.exists());
+        assert!(store.repo_details_path(&ident).unwrap().exists());
+        assert_eq!(store.repo_details_list().await.unwrap(), vec![details]);
+    }
+
+    #[tokio::test]
+    async fn test_fs_store_export_stream() {
+        use futures::TryStreamExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsStore::new(dir.path().to_owned()).unwrap();
+
+        let a = RepoIdent::new_github("org", "a");
+        let b = RepoIdent::new_github("org", "b");
+        let details_a = RepoDetailsItem::Found(crate::source::RepoDetails::new_test(a.clone()));
+        let details_b = RepoDetailsItem::Found(crate::source::RepoDetails::new_test(b.clone()));
+        let readme = ReadmeRepo::new_test(RepoIdent::new_github("org", "list"), vec![a.clone()]);
+        store.repo_details_upsert(details_a.clone()).await.unwrap();
+        store.readme_repo_upsert(readme.clone()).await.unwrap();
+
+        // A legacy file superseded by a sharded one, and a legacy-only file.
+        std::fs::write(
+            store.repo_details_legacy_path(&a).unwrap(),
+            serde_json::to_vec(&details_a).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            store.repo_details_legacy_path(&b).unwrap(),
+            serde_json::to_vec(&details_b).unwrap(),
+        )
+        .unwrap();
+
+        let mut items: Vec<Item> = store.export_stream().try_collect().await.unwrap();
+        items.sort_by_key(|item| match item {
+            Item::Repo(details) => details.ident().to_string(),
+            Item::ReadmeRepo(readme) => readme.details.ident.to_string(),
+        });
+        assert_eq!(
+            items,
+            vec![
+                Item::Repo(details_a),
+                Item::Repo(details_b),
+                Item::ReadmeRepo(readme),
+            ]
+        );
+
+        // Dropping the stream early stops the export.
+        let mut stream = store.export_stream();
+        assert!(stream.try_next().await.unwrap().is_some());
+        drop(stream);
+    }
+
+    #[tokio::test]
+    async fn test_fs_store_rejects_path_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("data");
+        let store = FsStore::new(root.clone()).unwrap();
+
+        let ident = RepoIdent::new_github("../../../etc", "b");
+        let details = RepoDetailsItem::Found(crate::source::RepoDetails::new_test(ident.clone()));
+
+        let err = store
+            .import(vec![Item::Repo(details.clone())])
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("invalid"), "{err:#}");
+
+        // Direct writes are rejected as well.
+        assert!(store.repo_details_upsert(details).await.is_err());
+        assert!(store.repo_details(ident.clone()).await.is_err());
+        assert!(store
+            .record_star_snapshot(ident, 1, OffsetDateTime::now_utc())
+            .await
+            .is_err());
+
+        // Nothing was written outside of the data directory.
+        let outside: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(outside, vec![std::ffi::OsString::from("data")]);
+    }
+
+    #[tokio::test]
+    async fn test_fs_store_export_slice() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsStore::new(dir.path().to_owned()).unwrap();
+
+        let details: Vec<_> = ["c", "a", "b"]
+            .into_iter()
+            .map(|repo| {
+                RepoDetailsItem::Found(crate::source::RepoDetails::new_test(RepoIdent::new_github(
+                    "org", repo,
+                )))
+            })
+            .collect();
+        for item in &details {
+            store.repo_details_upsert(item.clone()).await.unwrap();
+        }
+        let readme = ReadmeRepo::new_test(RepoIdent::new_github("org", "list"), vec![]);
+        store.readme_repo_upsert(readme.clone()).await.unwrap();
+
+        let idents = |items: Vec<Item>| {
+            items
+                .iter()
+                .map(|item| item.ident().repo.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let all = store.export_slice(ExportFilter::default()).await.unwrap();
+        assert_eq!(idents(all), vec!["a", "b", "c", "list"]);
+
+        let page = store
+            .export_slice(ExportFilter {
+                offset: 1,
+                limit: Some(2),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(idents(page), vec!["b", "c"]);
+
+        let readmes = store
+            .export_slice(ExportFilter {
+                kind: Some(ItemKind::Readme),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(readmes, vec![Item::ReadmeRepo(readme)]);
+
+        // Items updated before the given time are skipped.
+        let future = OffsetDateTime::now_utc() + time::Duration::days(1);
+        let recent = store
+            .export_slice(ExportFilter {
+                updated_since: Some(future),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(recent.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fs_store_repo_details_multi() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsStore::new(dir.path().to_owned()).unwrap();
+
+        let idents: Vec<_> = (0..300)
+            .map(|n| RepoIdent::new_github("org", format!("repo{n}")))
+            .collect();
+        // Repos ending in 0, 3, 6 or 9 are missing.
+        for ident in idents
+            .iter()
+            .filter(|x| !x.repo.ends_with(['0', '3', '6', '9']))
+        {
+            let details =
+                RepoDetailsItem::Found(crate::source::RepoDetails::new_test(ident.clone()));
+            store.repo_details_upsert(details).await.unwrap();
+        }
+        let stored: Vec<_> = store
+            .repo_details_list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|x| x.ident().clone())
+            .collect();
+        assert_eq!(stored.len(), 180);
+
+        // Results keep the requested order, for any order.
+        let mut reversed = idents.clone();
+        reversed.reverse();
+        for request in [idents, reversed] {
+            let expected: Vec<_> = request
+                .iter()
+                .filter(|x| stored.contains(x))
+                .cloned()
+                .collect();
+            let found: Vec<_> = store
+                .repo_details_multi(request)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|x| x.ident().clone())
+                .collect();
+            assert_eq!(found, expected);
+        }
+
+        assert!(store
+            .repo_details_multi(Vec::new())
+            .await
+            .unwrap()
+            .is_empty());
     }
 }
@@ -0,0 +1,161 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use time::OffsetDateTime;
+use tokio::sync::RwLock;
+
+use crate::source::{ReadmeRepo, RepoDetailsItem, RepoIdent};
+
+use super::{ExportFilter, ImportStats, Item, StarSnapshot, Task, MAX_STAR_SNAPSHOTS};
+
+/// In-memory storage.
+///
+/// Used for tests and ephemeral instances. Data is lost when the process
+/// exits.
+#[derive(Clone, Debug, Default)]
+pub struct MemStore {
+    repo_details: Arc<RwLock<HashMap<RepoIdent, RepoDetailsItem>>>,
+    readme_repos: Arc<RwLock<HashMap<RepoIdent, ReadmeRepo>>>,
+    star_history: Arc<RwLock<HashMap<RepoIdent, VecDeque<StarSnapshot>>>>,
+    tasks: Arc<RwLock<Vec<Task>>>,
+}
+
+impl MemStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl super::Storage for MemStore {
+    async fn repo_details(
+        &self,
+        ident: RepoIdent,
+    ) -> Result<Option<RepoDetailsItem>, anyhow::Error> {
+        Ok(self.repo_details.read().await.get(&ident).cloned())
+    }
+
+    async fn repo_details_multi(
+        &self,
+        idents: Vec<RepoIdent>,
+    ) -> Result<Vec<RepoDetailsItem>, anyhow::Error> {
+        let map = self.repo_details.read().await;
+        Ok(idents
+            .iter()
+            .filter_map(|ident| map.get(ident).cloned())
+            .collect())
+    }
+
+    async fn repo_details_upsert(&self, mut details: RepoDetailsItem) -> Result<(), anyhow::Error> {
+        let mut repos = self.repo_details.write().await;
+        details.keep_first_seen(repos.get(details.ident()));
+        repos.insert(details.ident().clone(), details);
+        Ok(())
+    }
+
+    async fn repo_details_list(&self) -> Result<Vec<RepoDetailsItem>, anyhow::Error> {
+        let mut list: Vec<_> = self.repo_details.read().await.values().cloned().collect();
+        list.sort_by(|a, b| a.ident().cmp(b.ident()));
+        Ok(list)
+    }
+
+    async fn repo_details_delete(&self, ident: RepoIdent) -> Result<(), anyhow::Error> {
+        self.repo_details.write().await.remove(&ident);
+        Ok(())
+    }
+
+    async fn readme_repo(&self, ident: RepoIdent) -> Result<Option<ReadmeRepo>, anyhow::Error> {
+        Ok(self.readme_repos.read().await.get(&ident).cloned())
+    }
+
+    async fn readme_repo_upsert(&self, readme: ReadmeRepo) -> Result<(), anyhow::Error> {
+        self.readme_repos
+            .write()
+            .await
+            .insert(readme.details.ident.clone(), readme);
+        Ok(())
+    }
+
+    async fn readme_repo_list(&self) -> Result<Vec<ReadmeRepo>, anyhow::Error> {
+        let mut list: Vec<_> = self.readme_repos.read().await.values().cloned().collect();
+        list.sort_by(|a, b| a.details.ident.cmp(&b.details.ident));
+        Ok(list)
+    }
+
+    async fn readme_repo_delete(&self, ident: RepoIdent) -> Result<(), anyhow::Error> {
+        self.readme_repos.write().await.remove(&ident);
+        Ok(())
+    }
+
+    async fn record_star_snapshot(
+        &self,
+        ident: RepoIdent,
+        stars: u32,
+        at: OffsetDateTime,
+    ) -> Result<(), anyhow::Error> {
+        let mut map = self.star_history.write().await;
+        let history = map.entry(ident).or_default();
+        history.push_back(StarSnapshot { at, stars });
+        while history.len() > MAX_STAR_SNAPSHOTS {
+            history.pop_front();
+        }
+        Ok(())
+    }
+
+    async fn star_history(&self, ident: RepoIdent) -> Result<Vec<StarSnapshot>, anyhow::Error> {
+        Ok(self
+            .star_history
+            .read()
+            .await
+            .get(&ident)
+            .map(|x| x.iter().copied().collect())
+            .unwrap_or_default())
+    }
+
+    async fn star_history_delete(&self, ident: RepoIdent) -> Result<(), anyhow::Error> {
+        self.star_history.write().await.remove(&ident);
+        Ok(())
+    }
+
+    async fn tasks(&self) -> Result<Vec<Task>, anyhow::Error> {
+        Ok(self.tasks.read().await.clone())
+    }
+
+    async fn tasks_replace(&self, tasks: Vec<Task>) -> Result<(), anyhow::Error> {
+        *self.tasks.write().await = tasks;
+        Ok(())
+    }
+
+    async fn check_ready(&self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    async fn export(&self) -> Result<Vec<Item>, anyhow::Error> {
+        super::export_items(self).await
+    }
+
+    /// All data is in memory anyway, so this just streams [`super::Storage::export`].
+    fn export_stream(&self) -> BoxStream<'static, Result<Item, anyhow::Error>> {
+        let s = self.clone();
+        futures::stream::once(async move {
+            let items = super::export_items(&s).await?;
+            Ok::<_, anyhow::Error>(futures::stream::iter(items.into_iter().map(Ok)))
+        })
+        .try_flatten()
+        .boxed()
+    }
+
+    async fn export_slice(&self, filter: ExportFilter) -> Result<Vec<Item>, anyhow::Error> {
+        super::export_items_slice(self, filter).await
+    }
+
+    async fn import(&self, items: Vec<Item>) -> Result<ImportStats, anyhow::Error> {
+        super::import_items(self, items, false).await
+    }
+
+    async fn import_dry_run(&self, items: Vec<Item>) -> Result<ImportStats, anyhow::Error> {
+        super::import_items(self, items, true).await
+    }
+}
@@ -1,7 +1,12 @@
 pub mod fs;
+pub mod mem;
 
 use std::future::Future;
 
+use anyhow::Context;
+use futures::stream::BoxStream;
+use time::OffsetDateTime;
+
 use crate::source::{ReadmeRepo, RepoDetailsItem, RepoIdent};
 
 pub trait Storage {
@@ -24,6 +29,14 @@ pub trait Storage {
         &self,
     ) -> impl Future<Output = Result<Vec<RepoDetailsItem>, anyhow::Error>> + Send;
 
+    /// Delete repo details.
+    ///
+    /// Succeeds if no details are stored for the repo.
+    fn repo_details_delete(
+        &self,
+        ident: RepoIdent,
+    ) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+
     fn readme_repo(
         &self,
         ident: RepoIdent,
@@ -38,9 +51,177 @@ pub trait Storage {
         &self,
     ) -> impl Future<Output = Result<Vec<ReadmeRepo>, anyhow::Error>> + Send;
 
+    /// Delete a readme repo.
+    ///
+    /// Succeeds if the repo is not stored.
+    fn readme_repo_delete(
+        &self,
+        ident: RepoIdent,
+    ) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+
+    /// Append a star count snapshot to the history of a repo.
+    ///
+    /// Only the latest [`MAX_STAR_SNAPSHOTS`] snapshots are retained.
+    fn record_star_snapshot(
+        &self,
+        ident: RepoIdent,
+        stars: u32,
+        at: OffsetDateTime,
+    ) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+
+    /// Star count history of a repo, oldest first.
+    fn star_history(
+        &self,
+        ident: RepoIdent,
+    ) -> impl Future<Output = Result<Vec<StarSnapshot>, anyhow::Error>> + Send;
+
+    /// Delete the star count history of a repo.
+    ///
+    /// Succeeds if no history is stored for the repo.
+    fn star_history_delete(
+        &self,
+        ident: RepoIdent,
+    ) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+
+    /// Load the persisted background task queue.
+    fn tasks(&self) -> impl Future<Output = Result<Vec<Task>, anyhow::Error>> + Send;
+
+    /// Replace the persisted background task queue.
+    fn tasks_replace(
+        &self,
+        tasks: Vec<Task>,
+    ) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+
+    /// Verify that the storage is usable.
+    ///
+    /// Checks that the storage is writable, or only readable for readonly
+    /// stores.
+    fn check_ready(&self) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+
     fn export(&self) -> impl Future<Output = Result<Vec<Item>, anyhow::Error>> + Send;
 
-    fn import(&self, items: Vec<Item>) -> impl Future<Output = Result<(), anyhow::Error>> + Send;
+    /// Export all items as a stream.
+    ///
+    /// Unlike [`Storage::export`], items are not sorted. Implementations
+    /// should only hold a bounded number of items in memory.
+    fn export_stream(&self) -> BoxStream<'static, Result<Item, anyhow::Error>>;
+
+    /// Export a slice of the items matching the filter.
+    ///
+    /// Repo details come before readme repos. Within a kind, items are in an
+    /// implementation specific order that is stable while the data does not
+    /// change, so clients can page through with `offset` and `limit`.
+    fn export_slice(
+        &self,
+        filter: ExportFilter,
+    ) -> impl Future<Output = Result<Vec<Item>, anyhow::Error>> + Send;
+
+    fn import(
+        &self,
+        items: Vec<Item>,
+    ) -> impl Future<Output = Result<ImportStats, anyhow::Error>> + Send;
+
+    /// Report what [`Self::import`] would do, without writing anything.
+    ///
+    /// Items are only compared to the stored versions, not to earlier items
+    /// of the same import.
+    fn import_dry_run(
+        &self,
+        items: Vec<Item>,
+    ) -> impl Future<Output = Result<ImportStats, anyhow::Error>> + Send;
+}
+
+/// Version of the export format written by [`ExportEnvelope`].
+///
+/// Must be increased when old importers can not read new exports, like when
+/// adding an [`Item`] variant.
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// Exported items, with the format version.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ExportEnvelope {
+    pub version: u32,
+    pub items: Vec<Item>,
+}
+
+impl ExportEnvelope {
+    pub fn new(items: Vec<Item>) -> Self {
+        Self {
+            version: EXPORT_FORMAT_VERSION,
+            items,
+        }
+    }
+}
+
+/// Items to import, either as an [`ExportEnvelope`] or as a bare array of
+/// items written by older versions.
+///
+/// Envelopes with a newer format version are rejected. Exports write the
+/// version first, so it is checked before parsing the items.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportItems(pub Vec<Item>);
+
+impl<'de> serde::Deserialize<'de> for ImportItems {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = ImportItems;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an export envelope or an array of items")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut items = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                Ok(ImportItems(items))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                use serde::de::Error;
+
+                let mut version = None;
+                let mut items = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "version" => {
+                            let value: u32 = map.next_value()?;
+                            // Checked right away, since items of newer
+                            // versions might not parse.
+                            if !(1..=EXPORT_FORMAT_VERSION).contains(&value) {
+                                return Err(A::Error::custom(format!(
+                                    "unsupported export format version {value}, \
+                                     only versions up to {EXPORT_FORMAT_VERSION} are supported"
+                                )));
+                            }
+                            version = Some(value);
+                        }
+                        "items" => items = Some(map.next_value()?),
+                        _ => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                version.ok_or_else(|| A::Error::missing_field("version"))?;
+                let items = items.ok_or_else(|| A::Error::missing_field("items"))?;
+                Ok(ImportItems(items))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
 }
 
 /// Represents any kind of item in storage.
@@ -51,6 +232,213 @@ pub enum Item {
     ReadmeRepo(ReadmeRepo),
 }
 
+impl Item {
+    pub fn updated_at(&self) -> OffsetDateTime {
+        match self {
+            Item::Repo(details) => details.updated_at(),
+            Item::ReadmeRepo(readme) => readme.updated_at,
+        }
+    }
+
+    pub fn ident(&self) -> &RepoIdent {
+        match self {
+            Item::Repo(details) => details.ident(),
+            Item::ReadmeRepo(readme) => &readme.details.ident,
+        }
+    }
+
+    pub fn kind(&self) -> ItemKind {
+        match self {
+            Item::Repo(_) => ItemKind::Repo,
+            Item::ReadmeRepo(_) => ItemKind::Readme,
+        }
+    }
+}
+
+/// Kind of an [`Item`].
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemKind {
+    Repo,
+    Readme,
+}
+
+/// Selects the items returned by [`Storage::export_slice`].
+#[derive(Clone, Debug, Default)]
+pub struct ExportFilter {
+    /// Only export items of this kind.
+    pub kind: Option<ItemKind>,
+    /// Only export items updated at or after this time.
+    pub updated_since: Option<OffsetDateTime>,
+    /// Number of matching items to skip.
+    pub offset: usize,
+    /// Maximum number of items to return.
+    pub limit: Option<usize>,
+}
+
+impl ExportFilter {
+    /// Returns `true` if the item matches the kind and update time.
+    ///
+    /// Does not consider the offset and limit.
+    pub fn matches(&self, item: &Item) -> bool {
+        self.kind.is_none_or(|kind| item.kind() == kind)
+            && self
+                .updated_since
+                .is_none_or(|since| item.updated_at() >= since)
+    }
+}
+
+/// Maximum number of star snapshots retained per repo.
+pub const MAX_STAR_SNAPSHOTS: usize = 365;
+
+/// Star count of a repo at a point in time.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StarSnapshot {
+    #[serde(with = "time::serde::rfc3339")]
+    pub at: OffsetDateTime,
+    pub stars: u32,
+}
+
+/// Background task of the loader.
+///
+/// Persisted in storage, so pending tasks survive restarts.
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash, Clone, Debug)]
+pub enum Task {
+    LoadRepoDetails(RepoIdent),
+    LoadReadmeRepo(RepoIdent),
+}
+
+/// Statistics about an import.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    /// Number of items written to storage.
+    pub inserted: usize,
+    /// Number of items skipped because storage already had the same or a newer version.
+    pub skipped: usize,
+}
+
+/// Export all items of a store.
+///
+/// Shared implementation of [`Storage::export`].
+async fn export_items<S: Storage + Sync>(store: &S) -> Result<Vec<Item>, anyhow::Error> {
+    let details = store.repo_details_list().await?.into_iter().map(Item::Repo);
+    let readmes = store
+        .readme_repo_list()
+        .await?
+        .into_iter()
+        .map(Item::ReadmeRepo);
+
+    let items = details.chain(readmes).collect();
+    Ok(items)
+}
+
+/// Export a slice of the items of a store.
+///
+/// Shared implementation of [`Storage::export_slice`].
+async fn export_items_slice<S: Storage + Sync>(
+    store: &S,
+    filter: ExportFilter,
+) -> Result<Vec<Item>, anyhow::Error> {
+    let items = export_items(store)
+        .await?
+        .into_iter()
+        .filter(|item| filter.matches(item))
+        .skip(filter.offset)
+        .take(filter.limit.unwrap_or(usize::MAX))
+        .collect();
+    Ok(items)
+}
+
+/// Import items into a store, skipping items that are not newer than the
+/// stored version.
+///
+/// With `dry_run`, nothing is written, but the same stats are returned.
+///
+/// Shared implementation of [`Storage::import`] and
+/// [`Storage::import_dry_run`].
+async fn import_items<S: Storage + Sync>(
+    store: &S,
+    items: Vec<Item>,
+    dry_run: bool,
+) -> Result<ImportStats, anyhow::Error> {
+    // Reject the whole import before writing anything.
+    for item in &items {
+        let ident = item.ident();
+        ident
+            .validate()
+            .with_context(|| format!("invalid repo ident in import: '{ident}'"))?;
+    }
+
+    let mut inserted = 0;
+    let mut skipped = 0;
+
+    for item in items {
+        match item {
+            Item::Repo(imported) => {
+                let existing = store.repo_details(imported.ident().clone()).await?;
+
+                let should_insert = match (&imported, &existing) {
+                    (RepoDetailsItem::Found(_), Some(RepoDetailsItem::NotFound { .. })) => true,
+                    (RepoDetailsItem::Found(new), Some(RepoDetailsItem::Found(old))) => {
+                        new.updated_at > old.updated_at
+                    }
+                    (
+                        RepoDetailsItem::NotFound {
+                            updated_at: new, ..
+                        },
+                        Some(RepoDetailsItem::NotFound {
+                            updated_at: old, ..
+                        }),
+                    ) => new > old,
+                    (RepoDetailsItem::NotFound { .. }, Some(RepoDetailsItem::Found(_))) => false,
+
+                    (_, None) => true,
+                };
+
+                if should_insert {
+                    if !dry_run {
+                        store.repo_details_upsert(imported).await?;
+                    }
+                    inserted += 1;
+                } else {
+                    skipped += 1;
+                }
+            }
+            Item::ReadmeRepo(imported) => {
+                let old = store.readme_repo(imported.details.ident.clone()).await?;
+
+                let should_insert = match old {
+                    Some(old) => imported.updated_at > old.updated_at,
+                    None => true,
+                };
+
+                if should_insert {
+                    if !dry_run {
+                        store.readme_repo_upsert(imported).await?;
+                    }
+                    inserted += 1;
+                } else {
+                    skipped += 1;
+                }
+            }
+        }
+    }
+
+    tracing::info!(%skipped, %inserted, %dry_run, "import complete");
+
+    Ok(ImportStats { inserted, skipped })
+}
+
+/// Available storage backends.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StorageKind {
+    /// Store data as JSON files in the data directory.
+    #[default]
+    Fs,
+    /// Keep data in memory only. Data is lost on restart.
+    Memory,
+}
+
 /// Different storage backends.
 ///
 // Used instead of `Box<dyn Storage>` to allow using `async fn` in trait,
@@ -58,6 +446,7 @@ pub enum Item {
 #[derive(Clone, Debug)]
 pub enum Store {
     Fs(fs::FsStore),
+    Mem(mem::MemStore),
 }
 
 impl From<fs::FsStore> for Store {
@@ -66,6 +455,12 @@ impl From<fs::FsStore> for Store {
     }
 }
 
+impl From<mem::MemStore> for Store {
+    fn from(mem: mem::MemStore) -> Self {
+        Store::Mem(mem)
+    }
+}
+
 impl Storage for Store {
     async fn repo_details(
         &self,
@@ -73,6 +468,7 @@ impl Storage for Store {
     ) -> Result<Option<RepoDetailsItem>, anyhow::Error> {
         match self {
             Store::Fs(fs) => fs.repo_details(ident).await,
+            Store::Mem(mem) => mem.repo_details(ident).await,
         }
     }
 
@@ -82,48 +478,138 @@ impl Storage for Store {
     ) -> Result<Vec<RepoDetailsItem>, anyhow::Error> {
         match self {
             Store::Fs(fs) => fs.repo_details_multi(idents).await,
+            Store::Mem(mem) => mem.repo_details_multi(idents).await,
         }
     }
 
     async fn repo_details_upsert(&self, details: RepoDetailsItem) -> Result<(), anyhow::Error> {
         match self {
             Store::Fs(fs) => fs.repo_details_upsert(details).await,
+            Store::Mem(mem) => mem.repo_details_upsert(details).await,
         }
     }
 
     async fn repo_details_list(&self) -> Result<Vec<RepoDetailsItem>, anyhow::Error> {
         match self {
             Store::Fs(fs) => fs.repo_details_list().await,
+            Store::Mem(mem) => mem.repo_details_list().await,
+        }
+    }
+
+    async fn repo_details_delete(&self, ident: RepoIdent) -> Result<(), anyhow::Error> {
+        match self {
+            Store::Fs(fs) => fs.repo_details_delete(ident).await,
+            Store::Mem(mem) => mem.repo_details_delete(ident).await,
         }
     }
 
     async fn readme_repo(&self, ident: RepoIdent) -> Result<Option<ReadmeRepo>, anyhow::Error> {
         match self {
             Store::Fs(fs) => fs.readme_repo(ident).await,
+            Store::Mem(mem) => mem.readme_repo(ident).await,
         }
     }
 
     async fn readme_repo_upsert(&self, readme: ReadmeRepo) -> Result<(), anyhow::Error> {
         match self {
             Store::Fs(fs) => fs.readme_repo_upsert(readme).await,
+            Store::Mem(mem) => mem.readme_repo_upsert(readme).await,
         }
     }
 
     async fn readme_repo_list(&self) -> Result<Vec<ReadmeRepo>, anyhow::Error> {
         match self {
             Store::Fs(fs) => fs.readme_repo_list().await,
+            Store::Mem(mem) => mem.readme_repo_list().await,
+        }
+    }
+
+    async fn readme_repo_delete(&self, ident: RepoIdent) -> Result<(), anyhow::Error> {
+        match self {
+            Store::Fs(fs) => fs.readme_repo_delete(ident).await,
+            Store::Mem(mem) => mem.readme_repo_delete(ident).await,
+        }
+    }
+
+    async fn record_star_snapshot(
+        &self,
+        ident: RepoIdent,
+        stars: u32,
+        at: OffsetDateTime,
+    ) -> Result<(), anyhow::Error> {
+        match self {
+            Store::Fs(fs) => fs.record_star_snapshot(ident, stars, at).await,
+            Store::Mem(mem) => mem.record_star_snapshot(ident, stars, at).await,
+        }
+    }
+
+    async fn star_history(&self, ident: RepoIdent) -> Result<Vec<StarSnapshot>, anyhow::Error> {
+        match self {
+            Store::Fs(fs) => fs.star_history(ident).await,
+            Store::Mem(mem) => mem.star_history(ident).await,
+        }
+    }
+
+    async fn star_history_delete(&self, ident: RepoIdent) -> Result<(), anyhow::Error> {
+        match self {
+            Store::Fs(fs) => fs.star_history_delete(ident).await,
+            Store::Mem(mem) => mem.star_history_delete(ident).await,
+        }
+    }
+
+    async fn tasks(&self) -> Result<Vec<Task>, anyhow::Error> {
+        match self {
+            Store::Fs(fs) => fs.tasks().await,
+            Store::Mem(mem) => mem.tasks().await,
+        }
+    }
+
+    async fn tasks_replace(&self, tasks: Vec<Task>) -> Result<(), anyhow::Error> {
+        match self {
+            Store::Fs(fs) => fs.tasks_replace(tasks).await,
+            Store::Mem(mem) => mem.tasks_replace(tasks).await,
+        }
+    }
+
+    async fn check_ready(&self) -> Result<(), anyhow::Error> {
+        match self {
+            Store::Fs(fs) => fs.check_ready().await,
+            Store::Mem(mem) => mem.check_ready().await,
         }
     }
 
     async fn export(&self) -> Result<Vec<Item>, anyhow::Error> {
         match self {
             Store::Fs(fs) => fs.export().await,
+            Store::Mem(mem) => mem.export().await,
         }
     }
 
-    async fn import(&self, items: Vec<Item>) -> Result<(), anyhow::Error> {
+    fn export_stream(&self) -> BoxStream<'static, Result<Item, anyhow::Error>> {
+        match self {
+            Store::Fs(fs) => fs.export_stream(),
+            Store::Mem(mem) => mem.export_stream(),
+        }
+    }
+
+    async fn export_slice(&self, filter: ExportFilter) -> Result<Vec<Item>, anyhow::Error> {
+        match self {
+            Store::Fs(fs) => fs.export_slice(filter).await,
+            Store::Mem(mem) => mem.export_slice(filter).await,
+        }
+    }
+
+    async fn import(&self, items: Vec<Item>) -> Result<ImportStats, anyhow::Error> {
         match self {
             Store::Fs(fs) => fs.import(items).await,
+            Store::Mem(mem) => mem.import(items).await,
+        }
+    }
+
+    async fn import_dry_run(&self, items: Vec<Item>) -> Result<ImportStats, anyhow::Error> {
+        match self {
+            Store::Fs(fs) => fs.import_dry_run(items).await,
+            Store::Mem(mem) => mem.import_dry_run(items).await,
         }
     }
 }
@@ -0,0 +1,96 @@
+//! Check how a README is parsed, without storing anything.
+
+use crate::{
+    markdown::count_links,
+    source::{github::GithubClient, loader::SourceLoader, RepoIdent},
+};
+
+/// Summary of the links found in a README.
+#[derive(Debug)]
+pub struct ReadmeReport {
+    pub ident: String,
+    /// All links in the README.
+    pub total_links: usize,
+    /// Links that point to a repository, excluding links to the list itself.
+    pub repo_links: usize,
+    pub unique_repos: usize,
+    /// Unique categories, in README order.
+    pub categories: Vec<String>,
+}
+
+impl ReadmeReport {
+    /// Whether the README would be accepted as an awesome list.
+    pub fn is_awesome_list(&self) -> bool {
+        self.repo_links > 0
+    }
+}
+
+/// Fetch and parse the README of a repo.
+///
+/// `ident` is a repo URL or `<org>/<repo>`.
+pub async fn validate_readme(
+    ident: &str,
+    github_tokens: Vec<String>,
+    github_hosts: Vec<String>,
+) -> Result<ReadmeReport, anyhow::Error> {
+    let ident = RepoIdent::parse_ident_with_hosts(ident, &github_hosts)?;
+    let source =
+        SourceLoader::new(GithubClient::new(github_tokens)).with_github_hosts(github_hosts);
+    validate_with_source(&source, &ident).await
+}
+
+async fn validate_with_source(
+    source: &SourceLoader,
+    ident: &RepoIdent,
+) -> Result<ReadmeReport, anyhow::Error> {
+    let readme = source.fetch_readme(ident).await?;
+    let links = source.parse_readme(ident, &readme)?;
+
+    let unique_repos = links
+        .iter()
+        .map(|link| &link.ident)
+        .collect::<std::collections::HashSet<_>>()
+        .len();
+
+    let mut categories: Vec<String> = Vec::new();
+    for link in &links {
+        let category = link.section.join(" > ");
+        if !category.is_empty() && !categories.contains(&category) {
+            categories.push(category);
+        }
+    }
+
+    Ok(ReadmeReport {
+        ident: ident.to_string(),
+        total_links: count_links(&readme),
+        repo_links: links.len(),
+        unique_repos,
+        categories,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::github::mock::MockGithub;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_validate_readme() {
+        let github = MockGithub::start(
+            "## Tools\n\n- [a](https://github.com/org/a)\n- [b](https://github.com/org/b)\n\n\
+             ## Libraries\n\n- [a](https://github.com/org/a)\n- [docs](https://example.com)\n",
+        )
+        .await;
+        let source = SourceLoader::new(github.client());
+
+        let report = validate_with_source(&source, &RepoIdent::new_github("org", "awesome"))
+            .await
+            .unwrap();
+        assert_eq!(report.total_links, 4);
+        assert_eq!(report.repo_links, 3);
+        assert_eq!(report.unique_repos, 2);
+        assert_eq!(report.categories, vec!["Tools", "Libraries"]);
+        assert!(report.is_awesome_list());
+    }
+}